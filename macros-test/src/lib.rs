@@ -3,3 +3,15 @@ where
     A: xtra::Actor,
 {
 }
+
+pub fn assert_handler<A, M>()
+where
+    A: xtra::Handler<M>,
+{
+}
+
+pub fn assert_actor_with_stop<A, S>()
+where
+    A: xtra::Actor<Stop = S>,
+{
+}
@@ -0,0 +1,5 @@
+#[derive(xtra::Actor)]
+#[xtra(stopped = Self::cleanup, stopped = Self::cleanup)]
+struct MyActor;
+
+fn main() {}
@@ -0,0 +1,6 @@
+#[derive(xtra::Actor)]
+struct MyActor<'a> {
+    name: &'a str,
+}
+
+fn main() {}
@@ -0,0 +1,5 @@
+#[derive(xtra::Actor)]
+#[xtra(stop_value = u32)]
+struct MyActor;
+
+fn main() {}
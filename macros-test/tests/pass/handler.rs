@@ -0,0 +1,53 @@
+use xtra::prelude::*;
+use xtra::WeakAddress;
+
+#[derive(xtra::Actor)]
+struct MyActor {
+    name: String,
+    count: u32,
+}
+
+#[xtra::handler]
+impl MyActor {
+    const STEP: u32 = 1;
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+
+    /// Increment the count.
+    pub async fn increment(&mut self, by: u32) -> u32 {
+        self.count += by * Self::STEP;
+        self.count()
+    }
+
+    async fn reset(&mut self, _ctx: &mut Context<Self>) {
+        self.count = 0;
+    }
+
+    pub(crate) async fn rename(&mut self, mut name: String, loud: bool, ctx: &mut xtra::Context<Self>) -> String {
+        if loud {
+            name.push('!');
+            ctx.stop_self();
+        }
+
+        std::mem::replace(&mut self.name, name)
+    }
+}
+
+#[allow(dead_code)]
+fn send_through_extension_trait(address: &Address<MyActor>, weak: &WeakAddress<MyActor>) {
+    let _ = address.increment(1).priority(1);
+    let _ = address.rename("name".to_string(), true);
+    let _ = weak.reset();
+}
+
+fn main() {
+    macros_test::assert_handler::<MyActor, MyActorIncrement>();
+    macros_test::assert_handler::<MyActor, MyActorReset>();
+    macros_test::assert_handler::<MyActor, MyActorRename>();
+
+    let _ = MyActorIncrement { by: 1 };
+    let _ = MyActorReset;
+    let _ = MyActorRename { name: String::new(), loud: false };
+}
@@ -0,0 +1,32 @@
+use xtra::prelude::*;
+
+#[derive(xtra::Actor)]
+struct Counter(u32);
+
+#[derive(xtra::Actor)]
+struct Greeter(String);
+
+#[xtra::handler]
+impl Counter {
+    async fn get(&mut self) -> u32 {
+        self.0
+    }
+}
+
+#[xtra::handler]
+impl Greeter {
+    async fn get(&mut self) -> String {
+        self.0.clone()
+    }
+}
+
+#[allow(dead_code)]
+fn send_through_extension_traits(counter: &Address<Counter>, greeter: &Address<Greeter>) {
+    let _ = counter.get();
+    let _ = greeter.get();
+}
+
+fn main() {
+    macros_test::assert_handler::<Counter, CounterGet>();
+    macros_test::assert_handler::<Greeter, GreeterGet>();
+}
@@ -0,0 +1,50 @@
+use xtra::prelude::*;
+
+#[derive(xtra::Actor)]
+#[xtra(stop = Summary, started = Self::init, stopped = Self::cleanup)]
+struct MyActor {
+    handled: usize,
+}
+
+struct Summary {
+    handled: usize,
+}
+
+impl MyActor {
+    async fn init(&mut self, _mailbox: &Mailbox<Self>) -> Result<(), Summary> {
+        if self.handled > 0 {
+            return Err(Summary { handled: self.handled });
+        }
+
+        Ok(())
+    }
+
+    async fn cleanup(self) -> Summary {
+        Summary { handled: self.handled }
+    }
+}
+
+#[derive(xtra::Actor)]
+#[xtra(stopped = close)]
+struct OnlyStopped;
+
+async fn close(_actor: OnlyStopped) {}
+
+#[derive(xtra::Actor)]
+#[xtra(stop = T)]
+#[xtra(stopped = Self::into_value, bound = "T: Clone")]
+struct Holder<T> {
+    value: T,
+}
+
+impl<T: Clone> Holder<T> {
+    async fn into_value(self) -> T {
+        self.value.clone()
+    }
+}
+
+fn main() {
+    macros_test::assert_actor_with_stop::<MyActor, Summary>();
+    macros_test::assert_actor_with_stop::<OnlyStopped, ()>();
+    macros_test::assert_actor_with_stop::<Holder<String>, String>();
+}
@@ -0,0 +1,179 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::{
+    parse_quote, DeriveInput, GenericParam, Ident, LitStr, Path, Token, Type, WherePredicate,
+};
+
+/// Expand `#[derive(Actor)]` on the given type.
+pub fn expand(input: DeriveInput) -> syn::Result<TokenStream> {
+    if let Some(lifetime) = input.generics.lifetimes().next() {
+        return Err(syn::Error::new_spanned(
+            &lifetime.lifetime,
+            "actors cannot have lifetime parameters",
+        ));
+    }
+
+    let options = Options::from_attributes(&input)?;
+
+    let mut bounds = send_and_static_bounds(&input);
+    bounds.extend(options.bound);
+
+    let actor_ident = &input.ident;
+    let (impl_generics, type_generics, where_clause) = input.generics.split_for_impl();
+    let where_clause = match where_clause.cloned() {
+        None => parse_quote! { where #(#bounds),* },
+        Some(mut existing) => {
+            existing.predicates.extend(bounds);
+
+            existing
+        }
+    };
+
+    let stop = match &options.stop {
+        Some(stop) => quote! { #stop },
+        None => quote! { () },
+    };
+
+    let started = options.started.map(|started| {
+        quote! {
+            async fn started(&mut self, mailbox: &xtra::Mailbox<Self>) -> ::std::result::Result<(), Self::Stop> {
+                #started(self, mailbox).await
+            }
+        }
+    });
+
+    let stopped = match options.stopped {
+        Some(stopped) => quote! {
+            async fn stopped(self) -> Self::Stop {
+                #stopped(self).await
+            }
+        },
+        None => quote! {
+            async fn stopped(self) { }
+        },
+    };
+
+    Ok(quote! {
+        impl #impl_generics xtra::Actor for #actor_ident #type_generics #where_clause {
+            type Stop = #stop;
+
+            #started
+
+            #stopped
+        }
+    })
+}
+
+/// The options given through `#[xtra(...)]` attributes.
+#[derive(Default)]
+struct Options {
+    stop: Option<Type>,
+    started: Option<Path>,
+    stopped: Option<Path>,
+    bound: Vec<WherePredicate>,
+}
+
+impl Options {
+    fn from_attributes(input: &DeriveInput) -> syn::Result<Self> {
+        let mut options = Options::default();
+        let mut stop_key = None;
+
+        for attr in input.attrs.iter().filter(|attr| attr.path.is_ident("xtra")) {
+            let args = attr.parse_args_with(Punctuated::<Arg, Token![,]>::parse_terminated)?;
+
+            for arg in args {
+                match arg {
+                    Arg::Stop(key, stop) => {
+                        set(&mut options.stop, key.clone(), stop)?;
+                        stop_key = Some(key);
+                    }
+                    Arg::Started(key, started) => set(&mut options.started, key, started)?,
+                    Arg::Stopped(key, stopped) => set(&mut options.stopped, key, stopped)?,
+                    Arg::Bound(bound) => options.bound.extend(bound),
+                }
+            }
+        }
+
+        if let (Some(key), None) = (stop_key, &options.stopped) {
+            return Err(syn::Error::new(
+                key.span(),
+                "`stop` requires a `stopped` hook which returns the stop value",
+            ));
+        }
+
+        Ok(options)
+    }
+}
+
+/// Set the given option, unless it was set already.
+fn set<T>(option: &mut Option<T>, key: Ident, value: T) -> syn::Result<()> {
+    if option.is_some() {
+        return Err(syn::Error::new(
+            key.span(),
+            format!("duplicate `{}` attribute", key),
+        ));
+    }
+
+    *option = Some(value);
+
+    Ok(())
+}
+
+/// A single `key = value` argument of an `#[xtra(...)]` attribute.
+enum Arg {
+    Stop(Ident, Type),
+    Started(Ident, Path),
+    Stopped(Ident, Path),
+    Bound(Punctuated<WherePredicate, Token![,]>),
+}
+
+impl Parse for Arg {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let key = input.parse::<Ident>()?;
+
+        match key.to_string().as_str() {
+            "stop" => {
+                input.parse::<Token![=]>()?;
+                Ok(Arg::Stop(key, input.parse()?))
+            }
+            "started" => {
+                input.parse::<Token![=]>()?;
+                Ok(Arg::Started(key, input.parse()?))
+            }
+            "stopped" => {
+                input.parse::<Token![=]>()?;
+                Ok(Arg::Stopped(key, input.parse()?))
+            }
+            "bound" => {
+                input.parse::<Token![=]>()?;
+                let bound = input.parse::<LitStr>()?;
+                Ok(Arg::Bound(bound.parse_with(Punctuated::parse_terminated)?))
+            }
+            _ => Err(syn::Error::new(
+                key.span(),
+                "unknown attribute, expected one of `stop`, `started`, `stopped` or `bound`",
+            )),
+        }
+    }
+}
+
+/// Generics a `: WasmSend + 'static` predicate for each type parameter present in the generics.
+fn send_and_static_bounds(input: &DeriveInput) -> Vec<WherePredicate> {
+    input
+        .generics
+        .params
+        .iter()
+        .filter_map(|gp| match gp {
+            GenericParam::Type(tp) => Some(&tp.ident),
+            GenericParam::Lifetime(_) => None,
+            GenericParam::Const(_) => None,
+        })
+        .map(|ident| {
+            parse_quote! {
+                #ident: ::xtra::WasmSend + 'static
+            }
+        })
+        .collect::<Vec<WherePredicate>>()
+}
@@ -0,0 +1,335 @@
+use proc_macro2::{Ident, TokenStream};
+use quote::{format_ident, quote};
+use syn::{
+    Attribute, Block, FnArg, ImplItem, ImplItemMethod, ItemImpl, Pat, ReturnType, Type, Visibility,
+};
+
+/// Expand `#[xtra::handler]` on the given inherent impl block.
+pub fn expand(mut item: ItemImpl) -> syn::Result<TokenStream> {
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(syn::Error::new_spanned(
+            path,
+            "#[xtra::handler] must be used on an inherent impl block",
+        ));
+    }
+
+    if !item.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &item.generics,
+            "#[xtra::handler] does not support generic impl blocks",
+        ));
+    }
+
+    let actor = item.self_ty.clone();
+    let actor_ident = match &*actor {
+        Type::Path(path) if path.qself.is_none() => path.path.segments.last().map(|s| &s.ident),
+        _ => None,
+    }
+    .ok_or_else(|| {
+        syn::Error::new_spanned(
+            &actor,
+            "#[xtra::handler] must be used on an impl block of a named type",
+        )
+    })?
+    .clone();
+
+    let mut handlers = Vec::new();
+    let mut items = Vec::new();
+
+    for impl_item in std::mem::take(&mut item.items) {
+        match impl_item {
+            ImplItem::Method(method) if method.sig.asyncness.is_some() => {
+                handlers.push(HandlerMethod::parse(method, &actor_ident)?)
+            }
+            other => items.push(other),
+        }
+    }
+
+    if handlers.is_empty() {
+        return Err(syn::Error::new_spanned(
+            &actor,
+            "#[xtra::handler] requires at least one `async fn` in the impl block",
+        ));
+    }
+
+    let messages = handlers.iter().map(|handler| handler.message(&actor));
+    let extension = extension_trait(&actor, &actor_ident, &handlers);
+    let remaining = (!items.is_empty()).then(|| {
+        item.items = items;
+        quote! { #item }
+    });
+
+    Ok(quote! {
+        #remaining
+        #(#messages)*
+        #extension
+    })
+}
+
+/// An `async fn` of the impl block, which is turned into a message and its handler.
+struct HandlerMethod {
+    attrs: Vec<Attribute>,
+    docs: Vec<Attribute>,
+    vis: Visibility,
+    name: Ident,
+    message: Ident,
+    fields: Vec<Field>,
+    context: Option<(Pat, Type)>,
+    output: TokenStream,
+    block: Block,
+}
+
+/// An argument of a handler method, which becomes a field of its message.
+struct Field {
+    ident: Ident,
+    mutability: Option<syn::token::Mut>,
+    ty: Type,
+}
+
+impl HandlerMethod {
+    fn parse(method: ImplItemMethod, actor_ident: &Ident) -> syn::Result<Self> {
+        let sig = method.sig;
+
+        if !sig.generics.params.is_empty() {
+            return Err(syn::Error::new_spanned(
+                &sig.generics,
+                "handler methods cannot be generic",
+            ));
+        }
+
+        let mut inputs = sig.inputs.into_iter();
+
+        match inputs.next() {
+            Some(FnArg::Receiver(receiver))
+                if receiver.reference.is_some() && receiver.mutability.is_some() => {}
+            _ => {
+                return Err(syn::Error::new_spanned(
+                    &sig.ident,
+                    "handler methods must take `&mut self` as their first argument",
+                ))
+            }
+        }
+
+        let mut fields = Vec::new();
+        let mut context = None;
+
+        for input in inputs {
+            let input = match input {
+                FnArg::Typed(input) => input,
+                FnArg::Receiver(receiver) => {
+                    return Err(syn::Error::new_spanned(receiver, "unexpected receiver"))
+                }
+            };
+
+            if context.is_some() {
+                return Err(syn::Error::new_spanned(
+                    input,
+                    "the context must be the last argument of a handler method",
+                ));
+            }
+
+            if is_context(&input.ty) {
+                context = Some((*input.pat, *input.ty));
+                continue;
+            }
+
+            match *input.pat {
+                Pat::Ident(pat) if pat.by_ref.is_none() && pat.subpat.is_none() => {
+                    fields.push(Field {
+                        ident: pat.ident,
+                        mutability: pat.mutability,
+                        ty: *input.ty,
+                    })
+                }
+                pat => {
+                    return Err(syn::Error::new_spanned(
+                        pat,
+                        "arguments of handler methods must be plain identifiers",
+                    ))
+                }
+            }
+        }
+
+        let output = match sig.output {
+            ReturnType::Default => quote! { () },
+            ReturnType::Type(_, ty) => quote! { #ty },
+        };
+
+        let (docs, attrs) = method
+            .attrs
+            .into_iter()
+            .partition(|attr| attr.path.is_ident("doc"));
+
+        Ok(HandlerMethod {
+            attrs,
+            docs,
+            vis: method.vis,
+            // Prefixed with the actor, so that actors in the same module can share method names.
+            message: format_ident!(
+                "{}{}",
+                actor_ident,
+                upper_camel_case(&sig.ident),
+                span = sig.ident.span()
+            ),
+            name: sig.ident,
+            fields,
+            context,
+            output,
+            block: method.block,
+        })
+    }
+
+    /// The message struct of this method and its [`Handler`] implementation.
+    fn message(&self, actor: &Type) -> TokenStream {
+        let HandlerMethod {
+            attrs,
+            docs,
+            vis,
+            message,
+            output,
+            block,
+            ..
+        } = self;
+
+        let idents = self.fields.iter().map(|f| &f.ident).collect::<Vec<_>>();
+        let types = self.fields.iter().map(|f| &f.ty);
+        let bindings = self.fields.iter().map(|f| {
+            let (mutability, ident) = (&f.mutability, &f.ident);
+            quote! { #mutability #ident }
+        });
+
+        let context = match &self.context {
+            Some((pat, ty)) => quote! { #pat: #ty },
+            None => quote! { _ctx: &mut ::xtra::Context<Self> },
+        };
+
+        let definition = if idents.is_empty() {
+            quote! { #vis struct #message; }
+        } else {
+            quote! { #vis struct #message { #(#vis #idents: #types,)* } }
+        };
+
+        quote! {
+            #(#docs)*
+            #definition
+
+            impl ::xtra::Handler<#message> for #actor {
+                type Return = #output;
+
+                #(#attrs)*
+                async fn handle(&mut self, message: #message, #context) -> #output {
+                    let #message { #(#bindings),* } = message;
+                    #block
+                }
+            }
+        }
+    }
+}
+
+/// The extension trait for [`Address`]es of the actor, which has a method for every handler.
+fn extension_trait(actor: &Type, actor_ident: &Ident, handlers: &[HandlerMethod]) -> TokenStream {
+    let ident = format_ident!("{}AddressExt", actor_ident);
+    let vis = widest_visibility(handlers.iter().map(|handler| &handler.vis));
+    let doc = format!(
+        "Methods for sending the messages handled by [`{}`] through its address.",
+        actor_ident
+    );
+
+    let signatures = handlers
+        .iter()
+        .map(|handler| {
+            let HandlerMethod {
+                name,
+                message,
+                output,
+                ..
+            } = handler;
+            let idents = handler.fields.iter().map(|f| &f.ident);
+            let types = handler.fields.iter().map(|f| &f.ty);
+
+            quote! {
+                fn #name(&self, #(#idents: #types),*) -> ::xtra::SendFuture<
+                    ::xtra::ActorNamedSending<#actor, __Rc>,
+                    ::xtra::ResolveToHandlerReturn<#output>,
+                >
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let declarations = handlers
+        .iter()
+        .zip(&signatures)
+        .map(|(handler, signature)| {
+            let docs = &handler.docs;
+            quote! { #(#docs)* #signature; }
+        });
+
+    let definitions = handlers
+        .iter()
+        .zip(&signatures)
+        .map(|(handler, signature)| {
+            let message = &handler.message;
+            let idents = handler.fields.iter().map(|f| &f.ident);
+            quote! { #signature { self.send(#message { #(#idents),* }) } }
+        });
+
+    quote! {
+        #[doc = #doc]
+        #vis trait #ident<__Rc: ::xtra::refcount::RefCounter> {
+            #(#declarations)*
+        }
+
+        impl<__Rc: ::xtra::refcount::RefCounter> #ident<__Rc> for ::xtra::Address<#actor, __Rc> {
+            #(#definitions)*
+        }
+    }
+}
+
+/// Returns whether the given type is `&mut Context<..>`.
+fn is_context(ty: &Type) -> bool {
+    let reference = match ty {
+        Type::Reference(reference) if reference.mutability.is_some() => reference,
+        _ => return false,
+    };
+
+    match &*reference.elem {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .map_or(false, |segment| segment.ident == "Context"),
+        _ => false,
+    }
+}
+
+/// The widest of the given visibilities, i.e. `pub` over `pub(crate)` over any other restricted
+/// visibility over private.
+fn widest_visibility<'a>(visibilities: impl Iterator<Item = &'a Visibility>) -> Visibility {
+    visibilities
+        .max_by_key(|vis| match vis {
+            Visibility::Public(_) => 3,
+            Visibility::Crate(_) => 2,
+            Visibility::Restricted(restricted) if restricted.path.is_ident("crate") => 2,
+            Visibility::Restricted(_) => 1,
+            Visibility::Inherited => 0,
+        })
+        .cloned()
+        .unwrap_or(Visibility::Inherited)
+}
+
+/// Convert a `snake_case` identifier to `UpperCamelCase`.
+fn upper_camel_case(ident: &Ident) -> String {
+    let ident = ident.to_string();
+
+    ident
+        .trim_start_matches("r#")
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
@@ -1,48 +1,32 @@
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_quote, DeriveInput, GenericParam, WherePredicate};
+use syn::{DeriveInput, ItemImpl};
 
-#[proc_macro_derive(Actor)]
+mod actor;
+mod handler;
+
+#[proc_macro_derive(Actor, attributes(xtra))]
 pub fn actor_derive(input: TokenStream) -> TokenStream {
     let derive_input = syn::parse::<DeriveInput>(input).expect("macro to be used as custom-derive");
 
-    let send_and_static_bounds = send_and_static_bounds(&derive_input);
-    let actor_ident = derive_input.ident;
-    let (impl_generics, type_generics, where_clause) = derive_input.generics.split_for_impl();
-    let where_clause = match where_clause.cloned() {
-        None => parse_quote! { where #(#send_and_static_bounds),* },
-        Some(mut existing) => {
-            existing.predicates.extend(send_and_static_bounds);
-
-            existing
-        }
-    };
-
-    quote! {
-        impl #impl_generics xtra::Actor for #actor_ident #type_generics #where_clause {
-            type Stop = ();
+    actor::expand(derive_input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
 
-            async fn stopped(self) { }
-        }
+#[proc_macro_attribute]
+pub fn handler(args: TokenStream, input: TokenStream) -> TokenStream {
+    if !args.is_empty() {
+        return syn::Error::new(
+            proc_macro2::Span::call_site(),
+            "#[xtra::handler] does not take any arguments",
+        )
+        .to_compile_error()
+        .into();
     }
-    .into()
-}
 
-/// Generics a `: WasmSend + 'static` predicate for each type parameter present in the generics.
-fn send_and_static_bounds(input: &DeriveInput) -> Vec<WherePredicate> {
-    input
-        .generics
-        .params
-        .iter()
-        .filter_map(|gp| match gp {
-            GenericParam::Type(tp) => Some(&tp.ident),
-            GenericParam::Lifetime(_) => None,
-            GenericParam::Const(_) => None,
-        })
-        .map(|ident| {
-            parse_quote! {
-                #ident: ::xtra::WasmSend + 'static
-            }
-        })
-        .collect::<Vec<WherePredicate>>()
+    let item = syn::parse_macro_input!(input as ItemImpl);
+
+    handler::expand(item)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
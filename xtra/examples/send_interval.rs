@@ -1,14 +1,12 @@
 use std::time::Duration;
 
-use futures_core::Stream;
-use futures_util::stream::repeat;
-use futures_util::StreamExt;
 use xtra::prelude::*;
-use xtra::Error;
+use xtra::timer::TokioTimer;
 
 #[derive(Default, xtra::Actor)]
 struct Greeter;
 
+#[derive(Clone)]
 struct Greet;
 
 impl Handler<Greet> for Greeter {
@@ -22,11 +20,8 @@ impl Handler<Greet> for Greeter {
 #[tokio::main]
 async fn main() {
     let addr = xtra::spawn_tokio(Greeter, Mailbox::unbounded());
-    greeter_stream(500).forward(addr.into_sink()).await.unwrap();
-}
+    let timer = addr.send_interval(Greet, Duration::from_millis(500), TokioTimer);
 
-fn greeter_stream(delay: u64) -> impl Stream<Item = Result<Greet, Error>> {
-    repeat(Duration::from_millis(delay))
-        .then(tokio::time::sleep)
-        .map(|_| Ok(Greet))
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    timer.cancel();
 }
@@ -2,18 +2,28 @@
 //! kind of message that it can receive.
 
 use std::cmp::Ordering;
+use std::collections::VecDeque;
 use std::fmt::{self, Debug, Formatter};
 use std::future::Future;
 use std::hash::{Hash, Hasher};
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use event_listener::EventListener;
+use futures_core::Stream;
+use futures_util::future::MaybeDone;
 use futures_util::FutureExt;
+use web_time::Instant;
 
+use crate::envelope::ReturningEnvelope;
+use crate::pool::PoolJoinHandle;
 use crate::refcount::{Either, RefCounter, Strong, Weak};
 use crate::send_future::{ActorNamedBroadcasting, Broadcast, ResolveToHandlerReturn};
-use crate::{chan, ActorNamedSending, Handler, SendFuture, WasmSend};
+use crate::timer::{self, Timer, TimerHandle};
+use crate::{
+    chan, ActorNamedSending, Coalesce, Error, Handler, Receiver, SendFuture, TrySendError, WasmSend,
+};
 
 /// An [`Address`] is a reference to an actor through which messages can be sent.
 ///
@@ -172,6 +182,14 @@ impl<A, Rc: RefCounter> Address<A, Rc> {
         self.len() == 0
     }
 
+    /// Take a snapshot of the metrics of the actor's mailbox. See [`xtra::metrics`](crate::metrics)
+    /// for what is recorded.
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+    pub fn metrics(&self) -> crate::metrics::MetricsSnapshot {
+        self.0.metrics()
+    }
+
     /// Send a message to the actor. The message will, by default, have a priority of 0 and be sent
     /// into the ordered queue. This can be configured through [`SendFuture::priority`].
     ///
@@ -191,6 +209,114 @@ impl<A, Rc: RefCounter> Address<A, Rc> {
         SendFuture::sending_named(message, self.0.clone())
     }
 
+    /// Send a message to the actor, merging it into a queued message with the same
+    /// [`coalesce_key`](Coalesce::coalesce_key) if there is one. See [`Coalesce`] for details.
+    ///
+    /// Like [`send`](Address::send), this function returns a [`SendFuture`] which resolves to the
+    /// [`Return`](crate::Handler::Return) value of the handler. If the message was merged, this is
+    /// the value returned for the merged message. Finding a queued message to merge with takes time
+    /// linear in the number of queued messages.
+    #[allow(clippy::type_complexity)]
+    pub fn send_coalesced<M>(
+        &self,
+        message: M,
+    ) -> SendFuture<ActorNamedSending<A, Rc>, ResolveToHandlerReturn<<A as Handler<M>>::Return>>
+    where
+        M: Coalesce + WasmSend + 'static,
+        A: Handler<M>,
+        A::Return: Clone,
+    {
+        SendFuture::sending_coalesced(message, self.0.clone())
+    }
+
+    /// Send a message to the actor and block the current thread until the handler returned. This
+    /// is a shorthand for [`send`](Address::send) followed by [`SendFuture::wait`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if called from within a [`Handler`], as blocking the thread there could deadlock the
+    /// actor. Handlers of actors spawned through [`spawn_thread`](crate::spawn_thread) may block
+    /// their own thread.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// # #[derive(Default)]
+    /// # struct Counter(usize);
+    /// # impl Actor for Counter { type Stop = (); async fn stopped(self) {} }
+    /// # struct Inc;
+    /// # impl Handler<Inc> for Counter {
+    /// #     type Return = usize;
+    /// #     async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) -> usize {
+    /// #         self.0 += 1;
+    /// #         self.0
+    /// #     }
+    /// # }
+    /// # #[cfg(feature = "tokio")]
+    /// # {
+    /// # let runtime = tokio::runtime::Runtime::new().unwrap();
+    /// # let _guard = runtime.enter();
+    /// let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    ///
+    /// let count = std::thread::spawn(move || address.send_blocking(Inc))
+    ///     .join()
+    ///     .unwrap();
+    /// assert_eq!(count, Ok(1));
+    /// # }
+    /// ```
+    pub fn send_blocking<M>(&self, message: M) -> Result<A::Return, Error>
+    where
+        M: WasmSend + 'static,
+        A: Handler<M>,
+    {
+        self.send(message).wait()
+    }
+
+    /// Send a message to the actor without waiting, e.g. from synchronous code. If the message
+    /// cannot be queued right away because the mailbox is full or the actor is disconnected, it is
+    /// handed back in the [`TrySendError`]. Otherwise, the returned [`Receiver`] resolves to the
+    /// [`Return`](crate::Handler::Return) value of the handler. It can be dropped if the return
+    /// value is not needed.
+    ///
+    /// The message is sent with the default priority of 0. If the
+    /// [`OverflowPolicy`](crate::OverflowPolicy) of the mailbox drops messages, it is applied as for
+    /// [`Address::send`]. Otherwise, the message is handed back if the mailbox is full.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// # use xtra::TrySendError;
+    /// # struct MyActor;
+    /// # impl Actor for MyActor { type Stop = (); async fn stopped(self) {} }
+    /// # struct Ping;
+    /// # impl Handler<Ping> for MyActor {
+    /// #     type Return = ();
+    /// #     async fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {}
+    /// # }
+    /// let (address, mailbox) = Mailbox::<MyActor>::bounded(1);
+    ///
+    /// assert!(address.try_send(Ping).is_ok());
+    /// assert!(matches!(address.try_send(Ping), Err(TrySendError::Full(Ping))));
+    ///
+    /// drop(mailbox);
+    /// assert!(address.try_send(Ping).unwrap_err().is_disconnected());
+    /// ```
+    pub fn try_send<M>(&self, message: M) -> Result<Receiver<A::Return>, TrySendError<M>>
+    where
+        M: WasmSend + 'static,
+        A: Handler<M>,
+    {
+        let (envelope, receiver) = ReturningEnvelope::<A, M, A::Return>::new(message, 0);
+
+        match self.0.try_send_to_one_now(Box::new(envelope)) {
+            Ok(()) => Ok(Receiver::new(receiver)),
+            Err(error) => Err(error.map(|envelope| {
+                *envelope
+                    .into_message()
+                    .downcast::<M>()
+                    .expect("envelope to carry the message")
+            })),
+        }
+    }
+
     /// Send a message to all actors on this address. The message will, by default, have a priority
     /// of 0. This can be configured through [`SendFuture::priority`].
     ///
@@ -208,7 +334,7 @@ impl<A, Rc: RefCounter> Address<A, Rc> {
     /// address, it will only ever trigger if the actor calls [`Context::stop_self`](crate::Context::stop_self),
     /// as the address would prevent the actor being dropped due to too few strong addresses.
     pub fn join(&self) -> ActorJoinHandle {
-        ActorJoinHandle(self.0.disconnect_listener())
+        ActorJoinHandle(Joining::Actor(self.0.disconnect_listener()))
     }
 
     /// Returns true if this address and the other address point to the same actor. This is
@@ -218,6 +344,100 @@ impl<A, Rc: RefCounter> Address<A, Rc> {
         self.0.inner_ptr() == other.0.inner_ptr()
     }
 
+    /// Send a message to the actor once the given delay has elapsed, using the given [`Timer`].
+    ///
+    /// The scheduled message does not keep the actor alive and is discarded if the actor stops
+    /// before the delay has elapsed. It can also be cancelled through the returned [`TimerHandle`].
+    /// The return value of the handler is discarded.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// # use std::time::Duration;
+    /// # struct MyActor;
+    /// # impl Actor for MyActor {type Stop = (); async fn stopped(self) -> Self::Stop {} }
+    /// struct Tick;
+    ///
+    /// impl Handler<Tick> for MyActor {
+    ///     type Return = ();
+    ///
+    ///     async fn handle(&mut self, _: Tick, _ctx: &mut Context<Self>) {
+    ///         println!("Tick!");
+    ///     }
+    /// }
+    ///
+    /// # #[cfg(feature = "tokio")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// use xtra::timer::TokioTimer;
+    ///
+    /// let addr = xtra::spawn_tokio(MyActor, Mailbox::unbounded());
+    /// let handle = addr.send_after(Tick, Duration::from_secs(1), TokioTimer);
+    /// handle.cancel();
+    /// # })
+    /// ```
+    pub fn send_after<M, T>(&self, message: M, delay: Duration, timer: T) -> TimerHandle
+    where
+        M: WasmSend + 'static,
+        A: Handler<M>,
+        T: Timer,
+    {
+        let address = Address(self.0.to_tx_weak());
+        let task = {
+            let sleep = timer.sleep(delay);
+            let address = address.clone();
+
+            async move {
+                sleep.await;
+                let _ = address.send(message).detach().await;
+            }
+        };
+
+        timer::spawn_scoped(&timer, &address, task)
+    }
+
+    /// Send a message to the actor at the given point in time, using the given [`Timer`]. If the
+    /// point in time has already passed, the message is sent immediately.
+    ///
+    /// See [`Address::send_after`] for how scheduled messages relate to the actor's lifetime.
+    pub fn send_at<M, T>(&self, message: M, at: Instant, timer: T) -> TimerHandle
+    where
+        M: WasmSend + 'static,
+        A: Handler<M>,
+        T: Timer,
+    {
+        self.send_after(message, at.saturating_duration_since(Instant::now()), timer)
+    }
+
+    /// Periodically send a clone of the given message to the actor, using the given [`Timer`].
+    /// The first message is sent once `period` has elapsed.
+    ///
+    /// Messages are sent until the returned [`TimerHandle`] is cancelled or the actor stops. If the
+    /// actor's mailbox is full, the next message is only scheduled once the current one could be
+    /// sent. See [`Address::send_after`] for how scheduled messages relate to the actor's lifetime.
+    pub fn send_interval<M, T>(&self, message: M, period: Duration, timer: T) -> TimerHandle
+    where
+        M: Clone + WasmSend + 'static,
+        A: Handler<M>,
+        T: Timer,
+    {
+        let address = Address(self.0.to_tx_weak());
+        let task = {
+            let timer = timer.clone();
+            let address = address.clone();
+
+            async move {
+                loop {
+                    timer.sleep(period).await;
+
+                    if address.send(message.clone()).detach().await.is_err() {
+                        break;
+                    }
+                }
+            }
+        };
+
+        timer::spawn_scoped(&timer, &address, task)
+    }
+
     /// Converts this address into a sink that can be used to send messages to the actor. These
     /// messages will have default priority and will be handled in send order.
     ///
@@ -243,30 +463,190 @@ impl<A, Rc: RefCounter> Address<A, Rc> {
     {
         futures_util::sink::unfold((), move |(), message| self.send(message))
     }
+
+    /// Converts this address into a stream of the replies of the actor to the given stream of
+    /// requests. This is the counterpart of [`Address::into_sink`] for messages which return a
+    /// value: every request is sent to the actor and the stream yields the
+    /// [`Return`](Handler::Return) value of its handler, or the [`Error`] of the send, in the order
+    /// of the requests.
+    ///
+    /// By default, the next request is only sent once the reply to the previous one was received.
+    /// To have more requests in flight at once, use [`ReplyStream::concurrency`].
+    ///
+    /// Just like with [`Address::into_sink`], a strong [`Address`] keeps the actor alive for as long
+    /// as the stream exists.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// use futures_util::StreamExt;
+    ///
+    /// # #[derive(xtra::Actor)]
+    /// struct Doubler;
+    ///
+    /// impl Handler<u32> for Doubler {
+    ///     type Return = u32;
+    ///
+    ///     async fn handle(&mut self, number: u32, _ctx: &mut Context<Self>) -> u32 {
+    ///         number * 2
+    ///     }
+    /// }
+    ///
+    /// # #[cfg(feature = "tokio")]
+    /// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+    /// let address = xtra::spawn_tokio(Doubler, Mailbox::unbounded());
+    /// let requests = futures_util::stream::iter([1, 2, 3]);
+    ///
+    /// let replies = address.into_stream_of(requests).concurrency(2);
+    /// let replies = replies.collect::<Vec<_>>().await;
+    /// assert_eq!(replies, vec![Ok(2), Ok(4), Ok(6)]);
+    /// # })
+    /// ```
+    pub fn into_stream_of<M, S>(self, requests: S) -> ReplyStream<A, M, Rc, S>
+    where
+        A: Handler<M>,
+        M: WasmSend + 'static,
+        S: Stream<Item = M>,
+    {
+        ReplyStream {
+            address: self,
+            requests: Some(Box::pin(requests)),
+            in_flight: VecDeque::new(),
+            concurrency: 1,
+        }
+    }
 }
 
 /// A future which will complete when the corresponding actor stops and its address becomes
 /// disconnected.
 #[must_use = "Futures do nothing unless polled"]
-pub struct ActorJoinHandle(Option<EventListener>);
+pub struct ActorJoinHandle(Joining);
+
+/// Module-private type of what an [`ActorJoinHandle`] waits for.
+enum Joining {
+    /// A single actor, which has already stopped if there is no listener.
+    Actor(Option<EventListener>),
+    /// All workers of a [`ActorPool`](crate::pool::ActorPool) that is used as a
+    /// [`MessageChannel`](crate::MessageChannel).
+    Pool(PoolJoinHandle),
+}
+
+impl ActorJoinHandle {
+    /// Create a handle which completes once all workers of a pool have stopped.
+    pub(crate) fn pool(handle: PoolJoinHandle) -> Self {
+        ActorJoinHandle(Joining::Pool(handle))
+    }
+}
 
 impl Future for ActorJoinHandle {
     type Output = ();
 
     fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-        match self.0.take() {
-            Some(mut listener) => match listener.poll_unpin(cx) {
-                Poll::Ready(()) => Poll::Ready(()),
-                Poll::Pending => {
-                    self.0 = Some(listener);
-                    Poll::Pending
-                }
+        match &mut self.0 {
+            Joining::Actor(slot) => match slot.take() {
+                Some(mut listener) => match listener.poll_unpin(cx) {
+                    Poll::Ready(()) => Poll::Ready(()),
+                    Poll::Pending => {
+                        *slot = Some(listener);
+                        Poll::Pending
+                    }
+                },
+                None => Poll::Ready(()),
             },
-            None => Poll::Ready(()),
+            Joining::Pool(pool) => pool.poll_unpin(cx),
+        }
+    }
+}
+
+/// A stream of the replies of an actor to a stream of requests, created through
+/// [`Address::into_stream_of`].
+#[must_use = "Streams do nothing unless polled"]
+pub struct ReplyStream<A, M, Rc, S>
+where
+    A: Handler<M>,
+    Rc: RefCounter,
+{
+    address: Address<A, Rc>,
+    requests: Option<Pin<Box<S>>>,
+    in_flight: VecDeque<MaybeDone<ReplyFuture<A, M, Rc>>>,
+    concurrency: usize,
+}
+
+type ReplyFuture<A, M, Rc> =
+    SendFuture<ActorNamedSending<A, Rc>, ResolveToHandlerReturn<<A as Handler<M>>::Return>>;
+
+impl<A, M, Rc, S> ReplyStream<A, M, Rc, S>
+where
+    A: Handler<M>,
+    Rc: RefCounter,
+{
+    /// Set the number of requests which may be in flight at once, i.e. sent to the actor without
+    /// their reply having been yielded yet. The replies are yielded in the order of the requests
+    /// regardless.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `concurrency` is 0.
+    pub fn concurrency(mut self, concurrency: usize) -> Self {
+        assert!(concurrency > 0, "concurrency must be at least 1");
+
+        self.concurrency = concurrency;
+        self
+    }
+}
+
+impl<A, M, Rc, S> Stream for ReplyStream<A, M, Rc, S>
+where
+    A: Handler<M>,
+    M: WasmSend + 'static,
+    Rc: RefCounter,
+    S: Stream<Item = M>,
+{
+    type Item = Result<<A as Handler<M>>::Return, Error>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        while this.in_flight.len() < this.concurrency {
+            let requests = match this.requests.as_mut() {
+                Some(requests) => requests,
+                None => break,
+            };
+
+            match requests.as_mut().poll_next(cx) {
+                Poll::Ready(Some(request)) => this
+                    .in_flight
+                    .push_back(MaybeDone::Future(this.address.send(request))),
+                Poll::Ready(None) => this.requests = None,
+                Poll::Pending => break,
+            }
+        }
+
+        for reply in &mut this.in_flight {
+            let _ = reply.poll_unpin(cx);
+        }
+
+        match this.in_flight.front_mut() {
+            Some(MaybeDone::Done(_)) => {
+                let mut reply = this.in_flight.pop_front().expect("front is done");
+                let reply = Pin::new(&mut reply).take_output().expect("reply is done");
+
+                Poll::Ready(Some(reply))
+            }
+            Some(_) => Poll::Pending,
+            None if this.requests.is_none() => Poll::Ready(None),
+            None => Poll::Pending,
         }
     }
 }
 
+// The requests are pinned on the heap and the replies are `Unpin`.
+impl<A, M, Rc, S> Unpin for ReplyStream<A, M, Rc, S>
+where
+    A: Handler<M>,
+    Rc: RefCounter,
+{
+}
+
 // Required because #[derive] adds an A: Clone bound
 impl<A, Rc: RefCounter> Clone for Address<A, Rc> {
     fn clone(&self) -> Self {
@@ -0,0 +1,96 @@
+//! Blocking the current thread on a future, for sending messages to actors from synchronous code.
+
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use event_listener::Event;
+use futures_util::task::{waker, ArcWake};
+
+thread_local! {
+    /// The number of handlers that are being polled on the current thread.
+    static HANDLERS: Cell<usize> = const { Cell::new(0) };
+    /// Whether the current thread runs a single actor, see [`spawn_thread`](crate::spawn_thread).
+    static DEDICATED: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Run the given future to completion, parking the current thread while it cannot make progress.
+///
+/// # Panics
+///
+/// Panics if called from within a handler, unless the actor runs on a thread of its own. Blocking
+/// there would keep the actor, and any other task of the executor thread, from making progress,
+/// which deadlocks if the future waits on them.
+pub(crate) fn block_on<F>(future: F) -> F::Output
+where
+    F: Future,
+{
+    assert!(
+        HANDLERS.with(Cell::get) == 0,
+        "Cannot block the thread from within a handler, as this could deadlock the actor"
+    );
+
+    let mut future = pin!(future);
+    let signal = Arc::new(Signal(Event::new()));
+    let waker = waker(signal.clone());
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        // Listen before polling, so that a wake-up while polling is not missed.
+        let listener = signal.0.listen();
+
+        if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+            return output;
+        }
+
+        listener.wait();
+    }
+}
+
+/// Run the task of an actor to completion on the current thread, which is dedicated to it. Its
+/// handlers may block the thread, as no other task runs on it.
+#[cfg(not(target_family = "wasm"))]
+pub(crate) fn block_on_dedicated<F>(task: F) -> F::Output
+where
+    F: Future,
+{
+    DEDICATED.with(|dedicated| dedicated.set(true));
+
+    block_on(task)
+}
+
+struct Signal(Event);
+
+impl ArcWake for Signal {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.notify(1);
+    }
+}
+
+/// Marks the current thread as polling a handler until it is dropped. Threads dedicated to a
+/// single actor are not marked.
+pub(crate) struct HandlerGuard {
+    marked: bool,
+}
+
+impl HandlerGuard {
+    pub(crate) fn enter() -> Self {
+        let marked = !DEDICATED.with(Cell::get);
+
+        if marked {
+            HANDLERS.with(|handlers| handlers.set(handlers.get() + 1));
+        }
+
+        HandlerGuard { marked }
+    }
+}
+
+impl Drop for HandlerGuard {
+    fn drop(&mut self) {
+        if self.marked {
+            HANDLERS.with(|handlers| handlers.set(handlers.get() - 1));
+        }
+    }
+}
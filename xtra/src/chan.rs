@@ -3,33 +3,37 @@
 
 mod priority;
 mod ptr;
+mod unicast_queue;
 mod waiting_receiver;
 mod waiting_sender;
 
 use std::cmp::Ordering;
 use std::collections::{BinaryHeap, VecDeque};
-use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::{AtomicBool, AtomicUsize};
 use std::sync::{atomic, Mutex};
 use std::{cmp, mem};
 
 use event_listener::{Event, EventListener};
 pub use priority::{ByPriority, HasPriority, Priority};
 pub use ptr::{Ptr, RefCounter, Rx, TxEither, TxStrong, TxWeak};
+use unicast_queue::{Queued, UnicastQueue};
 pub use waiting_receiver::WaitingReceiver;
 pub use waiting_sender::WaitingSender;
+use web_time::Instant;
 
+use crate::dead_letter::{self, DeadLetter, Reason, TypeNamed};
 use crate::envelope::{BroadcastEnvelope, MessageEnvelope, Shutdown};
-use crate::{Actor, Error, WasmRc, WasmWeak};
+#[cfg(feature = "metrics")]
+use crate::metrics::{HandlerTimer, MetricsSnapshot, QueueDepth, Recorder};
+use crate::{Actor, Error, OverflowPolicy, TrySendError, WasmRc, WasmWeak};
 
 pub type MessageToOne<A> = Box<dyn MessageEnvelope<Actor = A>>;
 pub type MessageToAll<A> = WasmRc<dyn BroadcastEnvelope<Actor = A>>;
 pub type BroadcastQueue<A> = spin::Mutex<BinaryHeap<ByPriority<MessageToAll<A>>>>;
 
 /// Create an actor mailbox, returning a sender and receiver for it.
-///
-/// The given capacity is applied separately for unicast and broadcast messages.
-pub fn new<A>(capacity: Option<usize>) -> (Ptr<A, TxStrong>, Ptr<A, Rx>) {
-    let inner = WasmRc::new(Chan::new(capacity));
+pub fn new<A>(options: Options) -> (Ptr<A, TxStrong>, Ptr<A, Rx>) {
+    let inner = WasmRc::new(Chan::new(options));
 
     let tx = Ptr::<A, TxStrong>::new(inner.clone());
     let rx = Ptr::<A, Rx>::new(inner);
@@ -37,21 +41,47 @@ pub fn new<A>(capacity: Option<usize>) -> (Ptr<A, TxStrong>, Ptr<A, Rx>) {
     (tx, rx)
 }
 
+/// The configuration of a channel.
+#[derive(Default)]
+pub struct Options {
+    /// The capacity, which is applied separately for unicast and broadcast messages.
+    pub capacity: Option<usize>,
+    /// What to do with unicast messages sent while the channel is at capacity.
+    pub overflow: OverflowPolicy,
+    /// Where to report messages that are dropped without being handled.
+    pub dead_letters: Option<dead_letter::Sink>,
+    /// Where to report messages that are dropped because their time-to-live passed.
+    pub on_expired: Option<dead_letter::Sink>,
+    /// The name to export the metrics of the channel under, if they should be exported.
+    #[cfg(feature = "metrics")]
+    pub export_metrics: Option<String>,
+}
+
 // Public because of private::RefCounterInner. This should never actually be exported, though.
 pub struct Chan<A> {
     chan: Mutex<Inner<A>>,
     on_shutdown: Event,
     sender_count: AtomicUsize,
     receiver_count: AtomicUsize,
+    restart_requested: AtomicBool,
+    dead_letters: Option<dead_letter::Sink>,
+    on_expired: Option<dead_letter::Sink>,
+    #[cfg(feature = "metrics")]
+    metrics: Recorder,
 }
 
 impl<A> Chan<A> {
-    pub fn new(capacity: Option<usize>) -> Self {
+    pub fn new(options: Options) -> Self {
         Self {
-            chan: Mutex::new(Inner::new(capacity)),
+            chan: Mutex::new(Inner::new(options.capacity, options.overflow)),
             on_shutdown: Event::new(),
             sender_count: AtomicUsize::new(0),
             receiver_count: AtomicUsize::new(0),
+            restart_requested: AtomicBool::new(false),
+            dead_letters: options.dead_letters,
+            on_expired: options.on_expired,
+            #[cfg(feature = "metrics")]
+            metrics: Recorder::new::<A>(options.export_metrics),
         }
     }
 
@@ -107,29 +137,62 @@ impl<A> Chan<A> {
         mut message: MessageToOne<A>,
     ) -> Result<Result<(), MailboxFull<MessageToOne<A>>>, Error> {
         if !self.is_connected() {
+            self.report_dead_letters(DeadLetter::new::<A, _>(&*message, Reason::Disconnected));
             return Err(Error::Disconnected);
         }
 
         message.start_span();
 
         let mut inner = self.chan.lock().unwrap();
+        let result = match inner.try_send_to_one(message) {
+            Ok(()) => Ok(Ok(())),
+            Err(msg) if inner.overflow == OverflowPolicy::Reject => {
+                inner.withdraw(msg, Reason::DroppedByPolicy);
+                Err(Error::MailboxFull)
+            }
+            Err(msg) => {
+                let (handle, waiting) = WaitingSender::new(msg);
+                inner.waiting_send_to_one.push_back(handle);
 
-        let unfulfilled_msg = if let Err(msg) = inner.try_fulfill_receiver(message) {
-            msg
-        } else {
-            return Ok(Ok(()));
+                Ok(Err(MailboxFull(waiting)))
+            }
         };
+        let dead_letters = mem::take(&mut inner.dead_letters);
+        #[cfg(feature = "metrics")]
+        let depth = inner.queue_depth();
+        drop(inner);
 
-        if inner.is_unicast_full() {
-            let (handle, waiting) = WaitingSender::new(unfulfilled_msg);
-            inner.waiting_send_to_one.push_back(handle);
+        self.report_dead_letters(dead_letters);
+        #[cfg(feature = "metrics")]
+        self.metrics.export_queue_depth(depth);
 
-            return Ok(Err(MailboxFull(waiting)));
+        result
+    }
+
+    /// Send the given message if it can be queued without waiting for space, handing it back
+    /// otherwise.
+    pub fn try_send_to_one_now(
+        &self,
+        mut message: MessageToOne<A>,
+    ) -> Result<(), TrySendError<MessageToOne<A>>> {
+        if !self.is_connected() {
+            return Err(TrySendError::Disconnected(message));
         }
 
-        inner.unicast_queue.push(ByPriority(unfulfilled_msg));
+        message.start_span();
+
+        let mut inner = self.chan.lock().unwrap();
+        let result = inner.try_send_to_one(message).map_err(TrySendError::Full);
+        let dead_letters = mem::take(&mut inner.dead_letters);
+        #[cfg(feature = "metrics")]
+        let depth = inner.queue_depth();
+        drop(inner);
+
+        self.report_dead_letters(dead_letters);
+        #[cfg(feature = "metrics")]
+        self.metrics.export_queue_depth(depth);
 
-        Ok(Ok(()))
+        result
     }
 
     pub fn try_send_to_all(
@@ -137,6 +200,7 @@ impl<A> Chan<A> {
         mut message: MessageToAll<A>,
     ) -> Result<Result<(), MailboxFull<MessageToAll<A>>>, Error> {
         if !self.is_connected() {
+            self.report_dead_letters(DeadLetter::new::<A, _>(&*message, Reason::Disconnected));
             return Err(Error::Disconnected);
         }
 
@@ -146,16 +210,25 @@ impl<A> Chan<A> {
 
         let mut inner = self.chan.lock().unwrap();
 
-        if inner.is_broadcast_full() {
+        let result = if inner.is_broadcast_full() {
             let (handle, waiting) = WaitingSender::new(message);
             inner.waiting_send_to_all.push_back(handle);
 
-            return Ok(Err(MailboxFull(waiting)));
-        }
+            Err(MailboxFull(waiting))
+        } else {
+            inner.send_broadcast(message);
+
+            Ok(())
+        };
 
-        inner.send_broadcast(message);
+        #[cfg(feature = "metrics")]
+        let depth = inner.queue_depth();
+        drop(inner);
 
-        Ok(Ok(()))
+        #[cfg(feature = "metrics")]
+        self.metrics.export_queue_depth(depth);
+
+        Ok(result)
     }
 
     pub fn try_recv(
@@ -165,6 +238,8 @@ impl<A> Chan<A> {
         // Lock `ChanInner` as the first thing. This avoids race conditions in modifying the broadcast mailbox.
         let mut inner = self.chan.lock().unwrap();
 
+        inner.withdraw_expired_head();
+
         // lock broadcast mailbox for as short as possible
         let broadcast_priority = {
             // Peek priorities in order to figure out which channel should be taken from
@@ -174,7 +249,7 @@ impl<A> Chan<A> {
         let shared_priority: Option<Priority> = inner.unicast_queue.peek().map(|it| it.priority());
 
         // Choose which priority channel to take from
-        match shared_priority.cmp(&broadcast_priority) {
+        let result = match shared_priority.cmp(&broadcast_priority) {
             // Shared priority is greater or equal (and it is not empty)
             Ordering::Greater | Ordering::Equal if shared_priority.is_some() => {
                 Ok(inner.pop_unicast().unwrap().into())
@@ -182,18 +257,53 @@ impl<A> Chan<A> {
             // Shared priority is less - take from broadcast
             Ordering::Less => Ok(inner.pop_broadcast(broadcast_mailbox).unwrap().into()),
             // Equal, but both are empty, so wait or exit if shutdown
+            // on_shutdown is only notified with inner locked, and it's locked here, so no race
+            _ if self.sender_count.load(atomic::Ordering::SeqCst) == 0 => {
+                Ok(ActorMessage::Shutdown)
+            }
             _ => {
-                // on_shutdown is only notified with inner locked, and it's locked here, so no race
-                if self.sender_count.load(atomic::Ordering::SeqCst) == 0 {
-                    return Ok(ActorMessage::Shutdown);
-                }
-
                 let (receiver, handle) = WaitingReceiver::new();
 
                 inner.waiting_receivers_handles.push_back(handle);
                 Err(receiver)
             }
-        }
+        };
+
+        let dead_letters = mem::take(&mut inner.dead_letters);
+        #[cfg(feature = "metrics")]
+        let depth = inner.queue_depth();
+        drop(inner);
+
+        self.report_dead_letters(dead_letters);
+        #[cfg(feature = "metrics")]
+        self.metrics.export_queue_depth(depth);
+
+        result
+    }
+
+    /// Describe the messages queued for the receiver with the given broadcast mailbox, in the
+    /// order in which they will be received.
+    pub fn queued(&self, broadcast_mailbox: &BroadcastQueue<A>) -> Vec<QueuedMessage> {
+        let inner = self.chan.lock().unwrap();
+
+        let mut queued = inner
+            .unicast_queue
+            .iter()
+            .filter_map(|queued| QueuedMessage::new(&*queued.msg, false))
+            .collect::<Vec<_>>();
+        queued.extend(
+            broadcast_mailbox
+                .lock()
+                .iter()
+                .filter_map(|ByPriority(msg)| QueuedMessage::new(&**msg, true)),
+        );
+
+        drop(inner);
+
+        // Unicast messages are received first if their priority is the same. The sort is stable, so
+        // unicast messages of the same priority stay in the order in which they were sent.
+        queued.sort_by_key(|msg| (cmp::Reverse(msg.priority), msg.broadcast));
+        queued
     }
 
     pub fn is_connected(&self) -> bool {
@@ -210,6 +320,20 @@ impl<A> Chan<A> {
         self.chan.lock().unwrap().capacity
     }
 
+    /// Take a snapshot of the metrics of this channel.
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> MetricsSnapshot {
+        let depth = self.chan.lock().unwrap().queue_depth();
+
+        self.metrics.snapshot(depth)
+    }
+
+    /// Record that a message of the given type was handled by an actor on this channel.
+    #[cfg(feature = "metrics")]
+    pub fn record_handled(&self, message: &'static str, timer: HandlerTimer) {
+        self.metrics.record_handled(message, timer);
+    }
+
     /// Shutdown all [`WaitingReceiver`]s in this channel.
     fn shutdown_waiting_receivers(&self) {
         let waiting_rx = {
@@ -242,20 +366,83 @@ impl<A> Chan<A> {
 
     /// Shutdown all [`WaitingSender`]s in this channel.
     fn shutdown_waiting_senders(&self) {
-        let mut inner = match self.chan.lock() {
-            Ok(lock) => lock,
-            Err(_) => return, // Poisoned, ignore
+        let dead_letters = {
+            let mut inner = match self.chan.lock() {
+                Ok(lock) => lock,
+                Err(_) => return, // Poisoned, ignore
+            };
+
+            self.on_shutdown.notify(usize::MAX);
+
+            let mut dead_letters = mem::take(&mut inner.dead_letters);
+
+            // Let any outstanding messages drop
+            for Queued { msg, .. } in inner.unicast_queue.drain() {
+                dead_letters.extend(DeadLetter::new::<A, _>(&*msg, Reason::Shutdown));
+            }
+            inner.expiring = 0;
+
+            // Queued broadcasts stay with their receivers, which may still handle them.
+            inner.broadcast_queues.clear();
+
+            // Close (and potentially wake) outstanding waiting senders
+            for handle in inner.waiting_send_to_one.drain(..) {
+                if let Some(msg) = handle.close() {
+                    dead_letters.extend(DeadLetter::new::<A, _>(&*msg, Reason::Disconnected));
+                }
+            }
+
+            for handle in inner.waiting_send_to_all.drain(..) {
+                if let Some(msg) = handle.close() {
+                    dead_letters.extend(DeadLetter::new::<A, _>(&*msg, Reason::Disconnected));
+                }
+            }
+
+            dead_letters
         };
 
-        self.on_shutdown.notify(usize::MAX);
+        self.report_dead_letters(dead_letters);
+    }
+
+    /// Report the given dead letters to the sink of this channel, if there is one. Expired
+    /// messages are reported to the expiry hook as well.
+    ///
+    /// This must not be called while holding the lock on the inner channel, as the sinks run user
+    /// code.
+    fn report_dead_letters(&self, dead_letters: impl IntoIterator<Item = DeadLetter>) {
+        let on_expired = match &self.on_expired {
+            Some(on_expired) => on_expired,
+            None => {
+                if let Some(sink) = &self.dead_letters {
+                    sink.report(dead_letters);
+                }
+
+                return;
+            }
+        };
 
-        // Let any outstanding messages drop
-        inner.unicast_queue.clear();
-        inner.broadcast_queues.clear();
+        let dead_letters = dead_letters.into_iter().collect::<Vec<_>>();
 
-        // Close (and potentially wake) outstanding waiting senders
-        inner.waiting_send_to_one.clear();
-        inner.waiting_send_to_all.clear();
+        on_expired.report(
+            dead_letters
+                .iter()
+                .copied()
+                .filter(|letter| letter.reason == Reason::Expired),
+        );
+
+        if let Some(sink) = &self.dead_letters {
+            sink.report(dead_letters);
+        }
+    }
+
+    /// Record that the actor on this channel asked to be restarted by its supervisor.
+    pub fn request_restart(&self) {
+        self.restart_requested.store(true, atomic::Ordering::SeqCst);
+    }
+
+    /// Returns whether a restart was requested since the last call, resetting the request.
+    pub fn take_restart_request(&self) -> bool {
+        self.restart_requested.swap(false, atomic::Ordering::SeqCst)
     }
 
     pub fn disconnect_listener(&self) -> Option<EventListener> {
@@ -290,7 +477,8 @@ impl<A> Chan<A> {
         };
 
         if let Err(msg) = inner.try_fulfill_receiver(msg) {
-            inner.unicast_queue.push(ByPriority(msg));
+            // The message was sent before all other queued messages of the same priority.
+            inner.push_unicast_front(msg);
         }
     }
 
@@ -304,33 +492,137 @@ impl<A> Chan<A> {
 
 struct Inner<A> {
     capacity: Option<usize>,
+    overflow: OverflowPolicy,
     waiting_send_to_one: VecDeque<waiting_sender::Handle<MessageToOne<A>>>,
     waiting_send_to_all: VecDeque<waiting_sender::Handle<MessageToAll<A>>>,
     waiting_receivers_handles: VecDeque<waiting_receiver::Handle<A>>,
-    unicast_queue: BinaryHeap<ByPriority<MessageToOne<A>>>,
+    unicast_queue: UnicastQueue<A>,
+    /// The number of messages in the unicast queue which had a deadline or a time-to-live when they
+    /// were queued, so that full queues are only searched for expired messages if there may be any.
+    expiring: usize,
     broadcast_queues: Vec<WasmWeak<BroadcastQueue<A>>>,
     broadcast_tail: usize,
+    /// Messages dropped while the lock was held, to be reported once it is released.
+    dead_letters: Vec<DeadLetter>,
 }
 
 impl<A> Inner<A> {
-    fn new(capacity: Option<usize>) -> Self {
+    fn new(capacity: Option<usize>, overflow: OverflowPolicy) -> Self {
         Self {
             capacity,
+            overflow,
             waiting_send_to_one: VecDeque::default(),
             waiting_send_to_all: VecDeque::default(),
             waiting_receivers_handles: VecDeque::default(),
-            unicast_queue: BinaryHeap::default(),
+            unicast_queue: UnicastQueue::default(),
+            expiring: 0,
             broadcast_queues: Vec::default(),
             broadcast_tail: 0,
+            dead_letters: Vec::default(),
+        }
+    }
+
+    /// Queue the given message, applying the overflow policy if the queue is full. Hands the
+    /// message back if it can only be queued once there is space, i.e. if the overflow policy does
+    /// not drop any message.
+    fn try_send_to_one(&mut self, message: MessageToOne<A>) -> Result<(), MessageToOne<A>> {
+        let unfulfilled_msg = match self.try_fulfill_receiver(message) {
+            Ok(()) => return Ok(()),
+            Err(msg) => msg,
+        };
+
+        let unfulfilled_msg = match self.try_coalesce(unfulfilled_msg) {
+            Ok(()) => return Ok(()),
+            Err(msg) => msg,
+        };
+
+        if self.is_unicast_full() {
+            self.withdraw_expired();
+        }
+
+        if self.is_unicast_full() {
+            match self.overflow {
+                OverflowPolicy::Block | OverflowPolicy::Reject => return Err(unfulfilled_msg),
+                OverflowPolicy::DropNewest => {
+                    self.withdraw(unfulfilled_msg, Reason::DroppedByPolicy);
+                    return Ok(());
+                }
+                OverflowPolicy::DropOldest => {
+                    match self.unicast_queue.pop_oldest() {
+                        Some(oldest) => {
+                            let oldest = self.dequeued(oldest);
+                            self.withdraw(oldest, Reason::DroppedByPolicy);
+                        }
+                        None => {
+                            // Nothing is queued, so the new message is the oldest.
+                            self.withdraw(unfulfilled_msg, Reason::DroppedByPolicy);
+                            return Ok(());
+                        }
+                    }
+                }
+                OverflowPolicy::DropLowestPriority => {
+                    let lowest = self.unicast_queue.lowest_priority();
+
+                    // The new message would be handled after all queued messages of the same
+                    // priority, so it is dropped in favour of them.
+                    if lowest.map_or(true, |lowest| unfulfilled_msg.priority() <= lowest) {
+                        self.withdraw(unfulfilled_msg, Reason::DroppedByPolicy);
+                        return Ok(());
+                    }
+
+                    let lowest = self.unicast_queue.pop_back().expect("queue is not empty");
+                    let lowest = self.dequeued(lowest);
+                    self.withdraw(lowest, Reason::DroppedByPolicy);
+                }
+            }
         }
+
+        self.push_unicast(unfulfilled_msg);
+
+        Ok(())
+    }
+
+    /// Merge the given message into a queued unicast message with the same coalescing key, handing
+    /// it back if there is none.
+    fn try_coalesce(&mut self, msg: MessageToOne<A>) -> Result<(), MessageToOne<A>> {
+        if !msg.is_coalescing() {
+            return Err(msg);
+        }
+
+        self.unicast_queue.coalesce(msg)
+    }
+
+    fn push_unicast(&mut self, msg: MessageToOne<A>) {
+        let expiring = self.count_expiring(&msg);
+        self.unicast_queue.push_back(msg, expiring);
+    }
+
+    fn push_unicast_front(&mut self, msg: MessageToOne<A>) {
+        let expiring = self.count_expiring(&msg);
+        self.unicast_queue.push_front(msg, expiring);
+    }
+
+    /// Account for a message which is about to be queued, returning whether it has a deadline or a
+    /// time-to-live.
+    fn count_expiring(&mut self, msg: &MessageToOne<A>) -> bool {
+        let expiring = msg.deadline().is_some() || msg.expires_at().is_some();
+        self.expiring += usize::from(expiring);
+        expiring
+    }
+
+    /// Account for a message which was taken out of the unicast queue.
+    fn dequeued(&mut self, queued: Queued<A>) -> MessageToOne<A> {
+        self.expiring -= usize::from(queued.expiring);
+        queued.msg
     }
 
     fn pop_unicast(&mut self) -> Option<Box<dyn MessageEnvelope<Actor = A>>> {
-        let msg = self.unicast_queue.pop()?.0;
+        let queued = self.unicast_queue.pop()?;
+        let msg = self.dequeued(queued);
 
         if !self.is_unicast_full() {
             if let Some(msg) = self.try_take_waiting_unicast_message() {
-                self.unicast_queue.push(ByPriority(msg))
+                self.push_unicast(msg)
             }
         }
 
@@ -395,10 +687,76 @@ impl<A> Inner<A> {
 
     fn try_take_waiting_unicast_message(&mut self) -> Option<MessageToOne<A>> {
         loop {
-            if let Some(msg) =
-                find_remove_highest_priority(&mut self.waiting_send_to_one)?.take_message()
-            {
-                return Some(msg);
+            let handle = find_remove_highest_priority(&mut self.waiting_send_to_one)?;
+            let msg = match handle.take_message() {
+                Some(msg) => msg,
+                None => continue,
+            };
+
+            match expiry(&msg) {
+                Some(reason) => self.withdraw(msg, reason),
+                None => return Some(msg),
+            }
+        }
+    }
+
+    /// Withdraw all queued unicast messages whose deadline or time-to-live has passed, making room
+    /// for waiting senders.
+    fn withdraw_expired(&mut self) {
+        if self.expiring == 0 {
+            return;
+        }
+
+        let expired = self
+            .unicast_queue
+            .remove_where(|queued| expiry(&queued.msg).is_some());
+
+        for queued in expired {
+            let msg = self.dequeued(queued);
+            let reason = expiry(&msg).expect("message expired");
+            self.withdraw(msg, reason);
+        }
+
+        self.refill_unicast_queue();
+    }
+
+    /// Withdraw expired unicast messages from the head of the queue, so that the next message to
+    /// be dispatched has not expired yet.
+    fn withdraw_expired_head(&mut self) {
+        let mut withdrawn = false;
+
+        while let Some(reason) = self
+            .unicast_queue
+            .peek()
+            .and_then(|queued| expiry(&queued.msg))
+        {
+            let queued = self.unicast_queue.pop().unwrap();
+            let msg = self.dequeued(queued);
+            self.withdraw(msg, reason);
+            withdrawn = true;
+        }
+
+        if withdrawn {
+            self.refill_unicast_queue();
+        }
+    }
+
+    /// Drop the given message, recording it as a dead letter. Expired messages resolve their
+    /// sender to [`Error::Expired`].
+    fn withdraw(&mut self, msg: MessageToOne<A>, reason: Reason) {
+        self.dead_letters
+            .extend(DeadLetter::new::<A, _>(&*msg, reason));
+
+        if reason == Reason::Expired {
+            msg.expire();
+        }
+    }
+
+    fn refill_unicast_queue(&mut self) {
+        while !self.is_unicast_full() {
+            match self.try_take_waiting_unicast_message() {
+                Some(msg) => self.push_unicast(msg),
+                None => break,
             }
         }
     }
@@ -422,6 +780,24 @@ impl<A> Inner<A> {
         self.capacity
             .map_or(false, |cap| self.unicast_queue.len() >= cap)
     }
+
+    #[cfg(feature = "metrics")]
+    fn queue_depth(&self) -> QueueDepth {
+        QueueDepth {
+            unicast: self.unicast_queue.len(),
+            broadcast: self.broadcast_tail,
+            waiting_senders: self
+                .waiting_send_to_one
+                .iter()
+                .filter(|handle| handle.is_active())
+                .count()
+                + self
+                    .waiting_send_to_all
+                    .iter()
+                    .filter(|handle| handle.is_active())
+                    .count(),
+        }
+    }
 }
 
 fn find_remove_highest_priority<M>(
@@ -441,6 +817,56 @@ where
     queue.remove(pos)
 }
 
+/// A description of a message in the mailbox of an actor, as returned by
+/// [`TestMailbox::queued`](crate::testing::TestMailbox::queued).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct QueuedMessage {
+    /// The type name of the message.
+    pub message: &'static str,
+    /// The priority the message was sent with.
+    pub priority: u32,
+    /// Whether the message was broadcast to all actors on the mailbox.
+    pub broadcast: bool,
+}
+
+impl QueuedMessage {
+    /// Describe the given envelope. Returns `None` for internal messages, such as shutdown
+    /// notifications.
+    pub(crate) fn new<E>(envelope: &E, broadcast: bool) -> Option<Self>
+    where
+        E: HasPriority + TypeNamed + ?Sized,
+    {
+        match envelope.priority() {
+            Priority::Valued(priority) => Some(QueuedMessage {
+                message: envelope.message_type(),
+                priority,
+                broadcast,
+            }),
+            Priority::Shutdown => None,
+        }
+    }
+}
+
+/// Why the given message must be withdrawn before it is dispatched, if its deadline or its
+/// time-to-live has passed.
+fn expiry<A>(msg: &MessageToOne<A>) -> Option<Reason> {
+    if msg
+        .deadline()
+        .map_or(false, |deadline| deadline <= Instant::now())
+    {
+        return Some(Reason::Timeout);
+    }
+
+    if msg
+        .expires_at()
+        .map_or(false, |expires_at| expires_at <= Instant::now())
+    {
+        return Some(Reason::Expired);
+    }
+
+    None
+}
+
 /// An error returned in case the mailbox of an actor is full.
 pub struct MailboxFull<M>(pub WaitingSender<M>);
 
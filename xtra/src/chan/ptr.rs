@@ -305,6 +305,7 @@ mod tests {
   use std::mem::size_of;
 
   use super::*;
+  use crate::chan::Options;
 
   #[test]
   fn size_of_ptr() {
@@ -316,7 +317,7 @@ mod tests {
 
   #[test]
   fn starts_with_rc_count_one() {
-    let inner = WasmRc::new(Chan::new(None));
+    let inner = WasmRc::new(Chan::new(Options::default()));
 
     let _ptr1 = Ptr::<Foo, TxStrong>::new(inner.clone());
 
@@ -325,7 +326,7 @@ mod tests {
 
   #[test]
   fn clone_increments_count() {
-    let inner = WasmRc::new(Chan::new(None));
+    let inner = WasmRc::new(Chan::new(Options::default()));
 
     let ptr1 = Ptr::<Foo, TxStrong>::new(inner.clone());
     #[allow(clippy::redundant_clone)]
@@ -336,7 +337,7 @@ mod tests {
 
   #[test]
   fn dropping_last_reference_calls_on_last_drop() {
-    let inner = WasmRc::new(Chan::new(None));
+    let inner = WasmRc::new(Chan::new(Options::default()));
 
     let ptr1 = Ptr::<Foo, TxStrong>::new(inner.clone());
     std::mem::drop(ptr1);
@@ -346,7 +347,7 @@ mod tests {
 
   #[test]
   fn can_convert_tx_strong_into_weak() {
-    let inner = WasmRc::new(Chan::new(None));
+    let inner = WasmRc::new(Chan::new(Options::default()));
 
     let strong_ptr = Ptr::<Foo, TxStrong>::new(inner.clone());
     let _weak_ptr = strong_ptr.to_tx_weak();
@@ -356,7 +357,7 @@ mod tests {
 
   #[test]
   fn can_clone_either() {
-    let inner = WasmRc::new(Chan::new(None));
+    let inner = WasmRc::new(Chan::new(Options::default()));
 
     let strong_ptr = Ptr::<Foo, TxStrong>::new(inner.clone());
     let either_ptr_1 = strong_ptr.to_tx_either();
@@ -368,7 +369,7 @@ mod tests {
 
   #[test]
   fn either_is_strong() {
-    let inner = WasmRc::new(Chan::new(None));
+    let inner = WasmRc::new(Chan::new(Options::default()));
 
     let strong_ptr = Ptr::<Foo, TxStrong>::new(inner);
     let either_ptr = strong_ptr.to_tx_either();
@@ -0,0 +1,182 @@
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::mem;
+
+use crate::chan::{HasPriority, MessageToOne, Priority};
+
+/// The unicast messages queued in a mailbox. They are received in order of priority first and then
+/// in the order in which they were sent. Besides the next message, the oldest message and the
+/// message which would be received last can be removed in logarithmic time as well.
+pub struct UnicastQueue<A> {
+    /// The queued messages, ordered such that the message which is received next comes last.
+    by_priority: BTreeMap<Key, Queued<A>>,
+    /// The priorities of the queued messages by sequence number, to find the oldest message.
+    by_seq: BTreeMap<i64, Priority>,
+    /// The sequence number of the last message pushed to the back of the queue.
+    last_seq: i64,
+    /// The sequence number of the last message pushed to the front of the queue.
+    first_seq: i64,
+}
+
+/// Higher priorities are received first, and older messages before newer ones of the same priority.
+type Key = (Priority, Reverse<i64>);
+
+impl<A> UnicastQueue<A> {
+    pub fn len(&self) -> usize {
+        self.by_priority.len()
+    }
+
+    /// Queue the given message behind all queued messages of the same priority.
+    pub fn push_back(&mut self, msg: MessageToOne<A>, expiring: bool) {
+        self.last_seq += 1;
+        self.insert(Queued {
+            msg,
+            seq: self.last_seq,
+            expiring,
+        });
+    }
+
+    /// Queue the given message ahead of all queued messages of the same priority, as if it was sent
+    /// before them.
+    pub fn push_front(&mut self, msg: MessageToOne<A>, expiring: bool) {
+        self.first_seq -= 1;
+        self.insert(Queued {
+            msg,
+            seq: self.first_seq,
+            expiring,
+        });
+    }
+
+    fn insert(&mut self, queued: Queued<A>) {
+        let priority = queued.priority();
+
+        self.by_seq.insert(queued.seq, priority);
+        self.by_priority
+            .insert((priority, Reverse(queued.seq)), queued);
+    }
+
+    /// The message which is received next.
+    pub fn peek(&self) -> Option<&Queued<A>> {
+        self.by_priority.last_key_value().map(|(_, queued)| queued)
+    }
+
+    /// Remove the message which is received next.
+    pub fn pop(&mut self) -> Option<Queued<A>> {
+        let (_, queued) = self.by_priority.pop_last()?;
+        self.by_seq.remove(&queued.seq);
+
+        Some(queued)
+    }
+
+    /// Remove the message which was sent first.
+    pub fn pop_oldest(&mut self) -> Option<Queued<A>> {
+        let (seq, priority) = self.by_seq.pop_first()?;
+
+        self.by_priority.remove(&(priority, Reverse(seq)))
+    }
+
+    /// Remove the message which is received last, i.e. the newest message of the lowest priority.
+    pub fn pop_back(&mut self) -> Option<Queued<A>> {
+        let (_, queued) = self.by_priority.pop_first()?;
+        self.by_seq.remove(&queued.seq);
+
+        Some(queued)
+    }
+
+    /// The lowest priority among the queued messages.
+    pub fn lowest_priority(&self) -> Option<Priority> {
+        self.by_priority
+            .first_key_value()
+            .map(|((priority, _), _)| *priority)
+    }
+
+    /// Iterate over the queued messages in the order in which they are received.
+    pub fn iter(&self) -> impl Iterator<Item = &Queued<A>> {
+        self.by_priority.values().rev()
+    }
+
+    /// Remove all queued messages, in the order in which they are received.
+    pub fn drain(&mut self) -> impl Iterator<Item = Queued<A>> {
+        self.by_seq.clear();
+
+        mem::take(&mut self.by_priority).into_values().rev()
+    }
+
+    /// Remove all queued messages which match the given predicate.
+    pub fn remove_where(
+        &mut self,
+        mut predicate: impl FnMut(&Queued<A>) -> bool,
+    ) -> Vec<Queued<A>> {
+        let keys = self
+            .by_priority
+            .iter()
+            .filter(|(_, queued)| predicate(queued))
+            .map(|(key, _)| *key)
+            .collect::<Vec<_>>();
+
+        keys.into_iter()
+            .filter_map(|key| {
+                let (_, Reverse(seq)) = key;
+                self.by_seq.remove(&seq);
+                self.by_priority.remove(&key)
+            })
+            .collect()
+    }
+
+    /// Merge the given message into a queued message with the same coalescing key, handing it back
+    /// if there is none. The merged message keeps its place in the queue, unless it was given a
+    /// higher priority.
+    pub fn coalesce(&mut self, mut msg: MessageToOne<A>) -> Result<(), MessageToOne<A>> {
+        let mut entries = self.by_priority.iter_mut();
+
+        let key = loop {
+            let (key, queued) = match entries.next() {
+                Some(entry) => entry,
+                None => return Err(msg),
+            };
+
+            match msg.coalesce_into(&mut *queued.msg) {
+                Ok(()) => break *key,
+                Err(unmerged) => msg = unmerged,
+            }
+        };
+
+        let (priority, _) = key;
+
+        if self.by_priority[&key].priority() != priority {
+            let queued = self
+                .by_priority
+                .remove(&key)
+                .expect("merged message is queued");
+            self.insert(queued);
+        }
+
+        Ok(())
+    }
+}
+
+impl<A> Default for UnicastQueue<A> {
+    fn default() -> Self {
+        Self {
+            by_priority: BTreeMap::new(),
+            by_seq: BTreeMap::new(),
+            last_seq: 0,
+            first_seq: 0,
+        }
+    }
+}
+
+/// A message in the unicast queue.
+pub struct Queued<A> {
+    pub msg: MessageToOne<A>,
+    /// The order in which the message was queued relative to the other messages.
+    seq: i64,
+    /// Whether the message had a deadline or a time-to-live when it was queued.
+    pub expiring: bool,
+}
+
+impl<A> HasPriority for Queued<A> {
+    fn priority(&self) -> Priority {
+        self.msg.priority()
+    }
+}
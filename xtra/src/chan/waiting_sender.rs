@@ -42,6 +42,29 @@ impl<M> Handle<M> {
             Inner::Delivered | Inner::Closed => None,
         }
     }
+
+    /// Close the paired [`WaitingSender`], returning the message it was waiting to send.
+    ///
+    /// This may return `None` in case the [`WaitingSender`] is no longer active or the message was already taken.
+    pub fn close(self) -> Option<M> {
+        let inner = self.0.upgrade()?;
+        let mut this = inner.lock();
+
+        match mem::replace(&mut *this, Inner::Closed) {
+            Inner::Active { mut waker, message } => {
+                if let Some(waker) = waker.take() {
+                    waker.wake();
+                }
+
+                Some(message)
+            }
+            Inner::Delivered => {
+                *this = Inner::Delivered;
+                None
+            }
+            Inner::Closed => None,
+        }
+    }
 }
 
 impl<M> Drop for Handle<M> {
@@ -1,3 +1,5 @@
+use std::ops::ControlFlow;
+
 use crate::{Actor, Mailbox};
 
 /// `Context` is used to control how the actor is managed and to get the actor's address from inside
@@ -39,3 +41,14 @@ impl<A: Actor> Context<A> {
         &self.mailbox
     }
 }
+
+impl<A> Context<A> {
+    /// Whether the actor should keep running after the current handler returned.
+    pub(crate) fn control_flow(&self) -> ControlFlow<()> {
+        if self.running {
+            ControlFlow::Continue(())
+        } else {
+            ControlFlow::Break(())
+        }
+    }
+}
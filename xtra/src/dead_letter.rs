@@ -0,0 +1,145 @@
+//! Records of messages which could not be delivered to an actor or were dropped from its mailbox.
+//!
+//! A sink for dead letters is configured when constructing a [`Mailbox`](crate::Mailbox) through
+//! [`MailboxBuilder::dead_letters`](crate::MailboxBuilder::dead_letters).
+
+use std::cell::RefCell;
+use std::fmt;
+use std::sync::{Mutex, PoisonError};
+
+use crate::chan::{HasPriority, Priority};
+use crate::WasmSend;
+
+/// A record of a message that was not handled by the actor it was sent to.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DeadLetter {
+    /// The type name of the actor the message was sent to.
+    pub actor: &'static str,
+    /// The type name of the message.
+    pub message: &'static str,
+    /// The priority the message was sent with.
+    pub priority: u32,
+    /// Why the message was not handled.
+    pub reason: Reason,
+}
+
+/// The reason why a message became a [`DeadLetter`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Reason {
+    /// The message was sent to an actor that was no longer running, or the actor stopped while the
+    /// sender was waiting for space in its mailbox.
+    Disconnected,
+    /// The message was still queued in the mailbox when the actor stopped.
+    Shutdown,
+    /// The deadline of the message passed before it was dispatched to the actor. See
+    /// [`SendFuture::deadline`](crate::SendFuture::deadline).
+    Timeout,
+    /// The message was dropped because of the mailbox's overflow policy.
+    DroppedByPolicy,
+    /// The time-to-live of the message passed before it was dispatched to the actor. See
+    /// [`SendFuture::ttl`](crate::SendFuture::ttl).
+    Expired,
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Reason::Disconnected => f.write_str("actor disconnected"),
+            Reason::Shutdown => f.write_str("actor shut down"),
+            Reason::Timeout => f.write_str("message timed out"),
+            Reason::DroppedByPolicy => f.write_str("dropped by overflow policy"),
+            Reason::Expired => f.write_str("message expired"),
+        }
+    }
+}
+
+impl DeadLetter {
+    /// Create the record of an envelope that is about to be dropped.
+    ///
+    /// Returns `None` for internal messages, such as shutdown notifications.
+    pub(crate) fn new<A, E>(envelope: &E, reason: Reason) -> Option<Self>
+    where
+        E: HasPriority + TypeNamed + ?Sized,
+    {
+        let priority = match envelope.priority() {
+            Priority::Valued(priority) => priority,
+            Priority::Shutdown => return None,
+        };
+
+        Some(DeadLetter {
+            actor: std::any::type_name::<A>(),
+            message: envelope.message_type(),
+            priority,
+            reason,
+        })
+    }
+}
+
+/// Envelopes which know the type name of the message they carry.
+pub(crate) trait TypeNamed {
+    fn message_type(&self) -> &'static str;
+}
+
+/// The sink that [`DeadLetter`]s of a mailbox are reported to.
+pub(crate) struct Sink(Mutex<Box<dyn SinkFn>>);
+
+/// A closure which [`DeadLetter`]s are reported to. Only auto traits can be added to trait objects,
+/// so this trait bundles the closure with [`WasmSend`].
+trait SinkFn: FnMut(DeadLetter) + WasmSend {}
+impl<F: FnMut(DeadLetter) + WasmSend> SinkFn for F {}
+
+impl Sink {
+    pub(crate) fn new(sink: impl FnMut(DeadLetter) + WasmSend + 'static) -> Self {
+        Self(Mutex::new(Box::new(sink)))
+    }
+
+    /// Report the given dead letters to the sink.
+    ///
+    /// Dead letters which are reported from within the sink itself, e.g. because it failed to send
+    /// to the same mailbox, are dropped, as the sink cannot be called re-entrantly. A sink which
+    /// panicked before keeps receiving dead letters.
+    pub(crate) fn report(&self, dead_letters: impl IntoIterator<Item = DeadLetter>) {
+        let _reporting = match Reporting::enter(self) {
+            Some(reporting) => reporting,
+            None => return,
+        };
+
+        let mut sink = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+
+        for dead_letter in dead_letters {
+            (sink)(dead_letter);
+        }
+    }
+}
+
+thread_local! {
+    /// The addresses of the sinks which are being reported to on the current thread.
+    static REPORTING: RefCell<Vec<usize>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Marks a sink as being reported to on the current thread until it is dropped.
+struct Reporting(usize);
+
+impl Reporting {
+    /// Returns `None` if the given sink is already being reported to on the current thread.
+    fn enter(sink: &Sink) -> Option<Self> {
+        let address = sink as *const Sink as usize;
+
+        REPORTING.with(|reporting| {
+            let mut reporting = reporting.borrow_mut();
+
+            if reporting.contains(&address) {
+                return None;
+            }
+
+            reporting.push(address);
+            Some(Reporting(address))
+        })
+    }
+}
+
+impl Drop for Reporting {
+    fn drop(&mut self) {
+        REPORTING.with(|reporting| reporting.borrow_mut().retain(|&address| address != self.0));
+    }
+}
@@ -7,6 +7,7 @@ use std::task::{Context, Poll};
 
 use futures_util::FutureExt;
 
+use crate::blocking::HandlerGuard;
 use crate::chan::ActorMessage;
 use crate::envelope::Shutdown;
 use crate::instrumentation::Span;
@@ -133,6 +134,8 @@ impl<'a, A> Future for DispatchFuture<'a, A> {
                 self.poll(cx)
             }
             State::Running { mut fut, phantom } => {
+                let _guard = HandlerGuard::enter();
+
                 match self.span.in_scope(|| fut.poll_unpin(cx)) {
                     Poll::Ready(flow) => Poll::Ready(flow),
                     Poll::Pending => {
@@ -1,13 +1,21 @@
+use std::any::Any;
 use std::marker::PhantomData;
+use std::mem;
 use std::ops::ControlFlow;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
 
 use catty::{Receiver, Sender};
 use futures_util::FutureExt;
+use web_time::Instant;
 
 use crate::chan::{HasPriority, MessageToAll, MessageToOne, Priority};
 use crate::context::Context;
+use crate::dead_letter::TypeNamed;
 use crate::instrumentation::{Instrumentation, Span};
-use crate::{Actor, Handler, Mailbox, WasmBoxFuture, WasmRc, WasmSend};
+#[cfg(feature = "metrics")]
+use crate::metrics::HandlerTimer;
+use crate::{Actor, Coalesce, Error, Handler, Mailbox, PanicAction, WasmBoxFuture, WasmRc, WasmSend};
 
 /// A message envelope is a struct that encapsulates a message and its return channel sender (if applicable).
 /// Firstly, this allows us to be generic over returning and non-returning messages (as all use the
@@ -15,15 +23,52 @@ use crate::{Actor, Handler, Mailbox, WasmBoxFuture, WasmRc, WasmSend};
 /// allows us to erase the type of the message when this is in dyn Trait format, thereby being able to
 /// use only one channel to send all the kinds of messages that the actor can receives. This does,
 /// however, induce a bit of allocation (as envelopes have to be boxed).
-pub trait MessageEnvelope: HasPriority + WasmSend {
+pub trait MessageEnvelope: HasPriority + TypeNamed + WasmSend {
   /// The type of actor that this envelope carries a message for
   type Actor;
 
   fn set_priority(&mut self, new_priority: u32);
 
-  /// Starts the instrumentation of this message request. This will create the request span.
+  /// Set the point in time after which this message is withdrawn from the mailbox if it has not
+  /// been dispatched to the actor yet.
+  fn set_deadline(&mut self, deadline: Instant);
+
+  /// The point in time after which this message is withdrawn from the mailbox, if any.
+  fn deadline(&self) -> Option<Instant>;
+
+  /// Set how long this message may wait in the mailbox before it expires, counted from when it is
+  /// sent.
+  fn set_ttl(&mut self, ttl: Duration);
+
+  /// The point in time after which this message expires if it has not been dispatched to the
+  /// actor yet. This is only known once the message was sent.
+  fn expires_at(&self) -> Option<Instant>;
+
+  /// Drop this message because it expired, resolving its sender to [`Error::Expired`].
+  fn expire(self: Box<Self>);
+
+  /// Starts the instrumentation of this message request. This will create the request span and
+  /// record when the message was sent.
   fn start_span(&mut self);
 
+  /// Take the message out of this envelope, in order to hand it back to its sender if it could not
+  /// be sent.
+  fn into_message(self: Box<Self>) -> Box<dyn Any>;
+
+  /// Whether this envelope carries a message that may be merged into queued messages through
+  /// [`MessageEnvelope::coalesce_into`].
+  fn is_coalescing(&self) -> bool;
+
+  /// Merge this envelope into the given queued one if both carry [`Coalesce`] messages of the same
+  /// type and with the same key, handing it back otherwise.
+  fn coalesce_into(
+    self: Box<Self>,
+    queued: &mut dyn MessageEnvelope<Actor = Self::Actor>,
+  ) -> Result<(), MessageToOne<Self::Actor>>;
+
+  /// Access the concrete envelope, in order to merge other envelopes into it.
+  fn as_any_mut(&mut self) -> &mut dyn Any;
+
   /// Handle the message inside of the box by calling the relevant [`Handler::handle`] method,
   /// returning its result over a return channel if applicable. This also takes `Box<Self>` as the
   /// `self` parameter because `Envelope`s always appear as `Box<dyn Envelope<Actor = ...>>`,
@@ -38,21 +83,31 @@ pub trait MessageEnvelope: HasPriority + WasmSend {
 /// An envelope that returns a result from a message. Constructed by the `AddressExt::do_send` method.
 pub struct ReturningEnvelope<A, M, R> {
   message: M,
-  result_sender: Sender<R>,
+  result_sender: Sender<Result<R, Error>>,
   priority: u32,
+  deadline: Option<Instant>,
+  ttl: Option<Duration>,
+  expires_at: Option<Instant>,
   phantom: PhantomData<for<'a> fn(&'a A)>,
   instrumentation: Instrumentation,
+  #[cfg(feature = "metrics")]
+  sent_at: Option<Instant>,
 }
 
 impl<A, M, R: WasmSend + 'static> ReturningEnvelope<A, M, R> {
-  pub fn new(message: M, priority: u32) -> (Self, Receiver<R>) {
+  pub fn new(message: M, priority: u32) -> (Self, Receiver<Result<R, Error>>) {
     let (tx, rx) = catty::oneshot();
     let envelope = ReturningEnvelope {
       message,
       result_sender: tx,
       priority,
+      deadline: None,
+      ttl: None,
+      expires_at: None,
       phantom: PhantomData,
       instrumentation: Instrumentation::empty(),
+      #[cfg(feature = "metrics")]
+      sent_at: None,
     };
 
     (envelope, rx)
@@ -65,6 +120,12 @@ impl<A, M, R> HasPriority for ReturningEnvelope<A, M, R> {
   }
 }
 
+impl<A, M, R> TypeNamed for ReturningEnvelope<A, M, R> {
+  fn message_type(&self) -> &'static str {
+    std::any::type_name::<M>()
+  }
+}
+
 impl<A> HasPriority for MessageToOne<A> {
   fn priority(&self) -> Priority {
     self.as_ref().priority()
@@ -83,9 +144,51 @@ where
     self.priority = new_priority;
   }
 
+  fn set_deadline(&mut self, deadline: Instant) {
+    self.deadline = Some(deadline);
+  }
+
+  fn deadline(&self) -> Option<Instant> {
+    self.deadline
+  }
+
+  fn set_ttl(&mut self, ttl: Duration) {
+    self.ttl = Some(ttl);
+  }
+
+  fn expires_at(&self) -> Option<Instant> {
+    self.expires_at
+  }
+
+  fn expire(self: Box<Self>) {
+    let _ = self.result_sender.send(Err(Error::Expired));
+  }
+
+  fn into_message(self: Box<Self>) -> Box<dyn Any> {
+    Box::new(self.message)
+  }
+
+  fn is_coalescing(&self) -> bool {
+    false
+  }
+
+  fn coalesce_into(self: Box<Self>, _: &mut dyn MessageEnvelope<Actor = A>) -> Result<(), MessageToOne<A>> {
+    Err(self)
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
   fn start_span(&mut self) {
     assert!(self.instrumentation.is_parent_none());
     self.instrumentation = Instrumentation::started::<A, M>();
+    self.expires_at = self.ttl.map(|ttl| Instant::now() + ttl);
+
+    #[cfg(feature = "metrics")]
+    {
+      self.sent_at = Some(Instant::now());
+    }
   }
 
   fn handle(
@@ -97,34 +200,202 @@ where
       message,
       result_sender,
       instrumentation,
+      #[cfg(feature = "metrics")]
+      sent_at,
       ..
     } = *self;
 
     let fut = async move {
       let mut ctx = Context { running: true, mailbox };
-      let r = act.handle(message, &mut ctx).await;
 
-      if ctx.running {
-        (r, ControlFlow::Continue(()))
-      } else {
-        (r, ControlFlow::Break(()))
+      #[cfg(feature = "metrics")]
+      let timer = HandlerTimer::start(sent_at);
+
+      let result = AssertUnwindSafe(act.handle(message, &mut ctx)).catch_unwind().await;
+
+      #[cfg(feature = "metrics")]
+      ctx.mailbox.record_handled(std::any::type_name::<M>(), timer);
+
+      match result {
+        Ok(r) => {
+          // We don't actually care if the receiver is listening
+          let _ = result_sender.send(Ok(r));
+          ctx.control_flow()
+        }
+        Err(payload) => {
+          let _ = result_sender.send(Err(Error::HandlerPanicked));
+          handle_panic(act, &ctx.mailbox, payload).await
+        }
       }
     };
 
     let (fut, span) = instrumentation.apply::<_>(fut);
 
-    let fut = Box::pin(fut.map(move |(r, flow)| {
-      // We don't actually care if the receiver is listening
-      let _ = result_sender.send(r);
-      flow
-    }));
+    (Box::pin(fut), span)
+  }
+}
+
+/// An envelope for a [`Coalesce`] message, which may absorb newer messages with the same key while
+/// it is queued. The result of the handler is sent to the senders of all absorbed messages.
+pub struct CoalescingEnvelope<A, M, R> {
+  inner: ReturningEnvelope<A, M, R>,
+  /// The result senders of the messages merged into this one.
+  coalesced: Vec<Sender<Result<R, Error>>>,
+}
+
+impl<A, M, R: WasmSend + 'static> CoalescingEnvelope<A, M, R> {
+  pub fn new(message: M, priority: u32) -> (Self, Receiver<Result<R, Error>>) {
+    let (inner, rx) = ReturningEnvelope::new(message, priority);
+    let envelope = CoalescingEnvelope {
+      inner,
+      coalesced: Vec::new(),
+    };
+
+    (envelope, rx)
+  }
+}
+
+impl<A, M, R> HasPriority for CoalescingEnvelope<A, M, R> {
+  fn priority(&self) -> Priority {
+    self.inner.priority()
+  }
+}
+
+impl<A, M, R> TypeNamed for CoalescingEnvelope<A, M, R> {
+  fn message_type(&self) -> &'static str {
+    self.inner.message_type()
+  }
+}
+
+impl<A, M, R> MessageEnvelope for CoalescingEnvelope<A, M, R>
+where
+  A: Handler<M, Return = R>,
+  M: Coalesce + WasmSend + 'static,
+  R: Clone + WasmSend + 'static,
+{
+  type Actor = A;
+
+  fn set_priority(&mut self, new_priority: u32) {
+    self.inner.set_priority(new_priority);
+  }
+
+  fn set_deadline(&mut self, deadline: Instant) {
+    self.inner.set_deadline(deadline);
+  }
+
+  fn deadline(&self) -> Option<Instant> {
+    self.inner.deadline()
+  }
+
+  fn set_ttl(&mut self, ttl: Duration) {
+    self.inner.set_ttl(ttl);
+  }
+
+  fn expires_at(&self) -> Option<Instant> {
+    self.inner.expires_at()
+  }
+
+  fn expire(self: Box<Self>) {
+    for sender in self.coalesced {
+      let _ = sender.send(Err(Error::Expired));
+    }
+
+    Box::new(self.inner).expire();
+  }
+
+  fn start_span(&mut self) {
+    self.inner.start_span();
+  }
+
+  fn into_message(self: Box<Self>) -> Box<dyn Any> {
+    Box::new(self.inner.message)
+  }
+
+  fn is_coalescing(&self) -> bool {
+    true
+  }
+
+  fn coalesce_into(self: Box<Self>, queued: &mut dyn MessageEnvelope<Actor = A>) -> Result<(), MessageToOne<A>> {
+    let queued = match queued.as_any_mut().downcast_mut::<Self>() {
+      Some(queued) if queued.inner.message.coalesce_key() == self.inner.message.coalesce_key() => queued,
+      _ => return Err(self),
+    };
+
+    let CoalescingEnvelope { inner, coalesced } = *self;
+
+    queued.inner.message.coalesce(inner.message);
+    queued.inner.priority = queued.inner.priority.max(inner.priority);
+    // The merged message may only be withdrawn once neither of the senders is waiting for it.
+    queued.inner.deadline = match (queued.inner.deadline, inner.deadline) {
+      (Some(queued), Some(newer)) => Some(queued.max(newer)),
+      _ => None,
+    };
+    queued.inner.expires_at = match (queued.inner.expires_at, inner.expires_at) {
+      (Some(queued), Some(newer)) => Some(queued.max(newer)),
+      _ => None,
+    };
+    queued.coalesced.push(inner.result_sender);
+    queued.coalesced.extend(coalesced);
+
+    Ok(())
+  }
+
+  fn as_any_mut(&mut self) -> &mut dyn Any {
+    self
+  }
+
+  fn handle(
+    self: Box<Self>,
+    act: &mut Self::Actor,
+    mailbox: Mailbox<Self::Actor>,
+  ) -> (WasmBoxFuture<ControlFlow<(), ()>>, Span) {
+    let CoalescingEnvelope { mut inner, coalesced } = *self;
+
+    // Intercept the result, in order to send it to the senders of all merged messages.
+    let (result_sender, result_receiver) = catty::oneshot();
+    let original_sender = mem::replace(&mut inner.result_sender, result_sender);
+
+    let mut forward = ForwardResult {
+      receiver: Some(result_receiver),
+      senders: coalesced,
+    };
+    forward.senders.push(original_sender);
+
+    let (fut, span) = Box::new(inner).handle(act, mailbox);
+
+    let fut = async move {
+      let _forward = forward;
+      fut.await
+    };
+
+    (Box::pin(fut), span)
+  }
+}
+
+/// Sends the result of a coalesced message to the senders of all messages merged into it once
+/// dropped. This way they receive [`Error::HandlerPanicked`] even if [`Actor::on_panic`] resumed the
+/// panic of the handler.
+struct ForwardResult<R: Clone> {
+  receiver: Option<Receiver<Result<R, Error>>>,
+  senders: Vec<Sender<Result<R, Error>>>,
+}
+
+impl<R: Clone> Drop for ForwardResult<R> {
+  fn drop(&mut self) {
+    // If the message was not handled, dropping the senders interrupts them.
+    let result = match self.receiver.take().and_then(FutureExt::now_or_never) {
+      Some(Ok(result)) => result,
+      _ => return,
+    };
 
-    (fut, span)
+    for sender in self.senders.drain(..) {
+      let _ = sender.send(result.clone());
+    }
   }
 }
 
 /// Like MessageEnvelope, but with an Arc instead of Box
-pub trait BroadcastEnvelope: HasPriority + WasmSend + Sync {
+pub trait BroadcastEnvelope: HasPriority + TypeNamed + WasmSend + Sync {
   type Actor;
 
   fn set_priority(&mut self, new_priority: u32);
@@ -151,6 +422,8 @@ pub struct BroadcastEnvelopeConcrete<A, M> {
   priority: u32,
   phantom: PhantomData<for<'a> fn(&'a A)>,
   instrumentation: Instrumentation,
+  #[cfg(feature = "metrics")]
+  sent_at: Option<Instant>,
 }
 
 impl<A: Actor, M> BroadcastEnvelopeConcrete<A, M> {
@@ -160,6 +433,8 @@ impl<A: Actor, M> BroadcastEnvelopeConcrete<A, M> {
       priority,
       phantom: PhantomData,
       instrumentation: Instrumentation::empty(),
+      #[cfg(feature = "metrics")]
+      sent_at: None,
     }
   }
 }
@@ -178,6 +453,11 @@ where
   fn start_span(&mut self) {
     assert!(self.instrumentation.is_parent_none());
     self.instrumentation = Instrumentation::started::<A, M>();
+
+    #[cfg(feature = "metrics")]
+    {
+      self.sent_at = Some(Instant::now());
+    }
   }
 
   fn handle(
@@ -186,15 +466,23 @@ where
     mailbox: Mailbox<Self::Actor>,
   ) -> (WasmBoxFuture<ControlFlow<(), ()>>, Span) {
     let (msg, instrumentation) = (self.message.clone(), self.instrumentation.clone());
+    #[cfg(feature = "metrics")]
+    let sent_at = self.sent_at;
     drop(self); // Drop ASAP to end the message waiting for actor span
     let fut = async move {
       let mut ctx = Context { running: true, mailbox };
-      act.handle(msg, &mut ctx).await;
 
-      if ctx.running {
-        ControlFlow::Continue(())
-      } else {
-        ControlFlow::Break(())
+      #[cfg(feature = "metrics")]
+      let timer = HandlerTimer::start(sent_at);
+
+      let result = AssertUnwindSafe(act.handle(msg, &mut ctx)).catch_unwind().await;
+
+      #[cfg(feature = "metrics")]
+      ctx.mailbox.record_handled(std::any::type_name::<M>(), timer);
+
+      match result {
+        Ok(()) => ctx.control_flow(),
+        Err(payload) => handle_panic(act, &ctx.mailbox, payload).await,
       }
     };
     let (fut, span) = instrumentation.apply::<_>(fut);
@@ -208,6 +496,24 @@ impl<A, M> HasPriority for BroadcastEnvelopeConcrete<A, M> {
   }
 }
 
+impl<A, M> TypeNamed for BroadcastEnvelopeConcrete<A, M> {
+  fn message_type(&self) -> &'static str {
+    std::any::type_name::<M>()
+  }
+}
+
+/// Let the actor decide how to proceed after one of its handlers panicked.
+async fn handle_panic<A: Actor>(act: &mut A, mailbox: &Mailbox<A>, payload: Box<dyn Any + Send>) -> ControlFlow<()> {
+  match act.on_panic(payload).await {
+    PanicAction::Continue => ControlFlow::Continue(()),
+    PanicAction::Stop => ControlFlow::Break(()),
+    PanicAction::Restart => {
+      mailbox.request_restart();
+      ControlFlow::Break(())
+    }
+  }
+}
+
 #[derive(Copy, Clone, Default)]
 pub struct Shutdown<A>(PhantomData<for<'a> fn(&'a A)>);
 
@@ -229,6 +535,12 @@ impl<A> HasPriority for Shutdown<A> {
   }
 }
 
+impl<A> TypeNamed for Shutdown<A> {
+  fn message_type(&self) -> &'static str {
+    std::any::type_name::<Self>()
+  }
+}
+
 impl<A> BroadcastEnvelope for Shutdown<A>
 where
   A: Actor,
@@ -3,6 +3,7 @@
 #![cfg_attr(docsrs, feature(doc_cfg))]
 #![deny(unsafe_code, missing_docs)]
 
+use std::any::Any;
 use std::fmt;
 use std::future::Future;
 use std::ops::ControlFlow;
@@ -12,26 +13,46 @@ use futures_util::{future, FutureExt};
 
 pub use self::address::{Address, WeakAddress};
 pub use self::context::Context;
-pub use self::mailbox::Mailbox;
-pub use self::scoped_task::scoped;
-pub use self::send_future::{ActorErasedSending, ActorNamedSending, Receiver, SendFuture};
+pub use self::mailbox::{Mailbox, MailboxBuilder, OverflowPolicy};
+pub use self::scoped_task::{attach_stream, scoped, StreamFinished};
+pub use self::send_future::{
+  ActorErasedSending, ActorNamedSending, Receiver, ResolveToHandlerReturn, SendFuture,
+};
 #[allow(unused_imports)]
 pub use self::spawn::*; // Star export so we don't have to write `cfg` attributes here.
 
 pub mod address;
+mod blocking;
 mod chan;
 mod context;
+pub mod dead_letter;
 mod dispatch_future;
 mod envelope;
 mod instrumentation;
+pub mod local;
 mod mailbox;
 pub mod message_channel;
+#[cfg(feature = "metrics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+pub mod metrics;
+pub mod pool;
+pub mod pubsub;
 mod recv_future;
+pub mod registry;
+#[cfg(feature = "remote")]
+#[cfg_attr(docsrs, doc(cfg(feature = "remote")))]
+pub mod remote;
+#[cfg(feature = "timeout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "timeout")))]
+pub mod resilience;
 /// This module contains a way to scope a future to the lifetime of an actor, stopping it before it
 /// completes if the actor it is associated with stops too.
 pub mod scoped_task;
 mod send_future;
 mod spawn;
+pub mod supervisor;
+pub mod testing;
+pub mod timer;
 
 /// Commonly used types from xtra
 pub mod prelude {
@@ -114,11 +135,133 @@ pub mod refcount {
 /// }
 /// ```
 ///
+/// The generated implementation can be configured through `#[xtra(...)]` attributes:
+///
+/// - `stop = Type` sets the [`Stop`](Actor::Stop) type. It requires a `stopped` hook to produce the stop value.
+/// - `started = path` calls the given `async fn(&mut Self, &Mailbox<Self>) -> Result<(), Self::Stop>` in [`Actor::started`].
+/// - `stopped = path` calls the given `async fn(Self) -> Self::Stop` in [`Actor::stopped`].
+/// - `bound = "T: Bound"` adds the given predicates to the `where` clause of the implementation, e.g. for bounds required by the hooks.
+///
+/// ```rust
+/// # use xtra::prelude::*;
+/// #[derive(xtra::Actor)]
+/// #[xtra(stop = usize, started = Self::init, stopped = Self::report)]
+/// pub struct MyActor {
+///     handled: usize,
+/// }
+///
+/// impl MyActor {
+///     async fn init(&mut self, _mailbox: &Mailbox<Self>) -> Result<(), usize> {
+///         println!("Started");
+///         Ok(())
+///     }
+///
+///     async fn report(self) -> usize {
+///         self.handled
+///     }
+/// }
+/// #
+/// # fn assert_actor<T: xtra::Actor<Stop = usize>>() { }
+/// #
+/// # fn main() {
+/// #    assert_actor::<MyActor>()
+/// # }
+/// ```
+///
 /// Please note that implementing the [`Actor`] trait is still very easy and this macro purposely does not support a plethora of usecases but is meant to handle the most common ones.
 /// For example, whilst it does support actors with type parameters, lifetimes are entirely unsupported.
 #[cfg(feature = "macros")]
 pub use macros::Actor;
 
+/// Generates a message and a [`Handler`] implementation for every `async fn` of an inherent `impl`
+/// block of an actor, as well as an extension trait for its [`Address`] to send these messages.
+///
+/// Every `async fn` must take `&mut self`, followed by any number of arguments and optionally the
+/// [`Context`] as its last argument. Its arguments become the fields of a message struct named
+/// after the actor and the function in `UpperCamelCase`, e.g. `CounterIncrement` for `increment`
+/// of `Counter`, with the visibility of the function. Any other items of the `impl` block are kept
+/// as they are.
+///
+/// ```rust
+/// # use xtra::prelude::*;
+/// #[derive(Default, xtra::Actor)]
+/// struct Counter {
+///     count: u32,
+/// }
+///
+/// #[xtra::handler]
+/// impl Counter {
+///     /// Increment the counter, returning the new count.
+///     async fn increment(&mut self, by: u32) -> u32 {
+///         self.count += by;
+///         self.count
+///     }
+///
+///     async fn stop(&mut self, ctx: &mut Context<Self>) {
+///         ctx.stop_self();
+///     }
+/// }
+///
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+///
+/// // Through the generated extension trait ...
+/// assert_eq!(address.increment(2).await, Ok(2));
+/// // ... or through the generated message.
+/// assert_eq!(address.send(CounterIncrement { by: 3 }).await, Ok(5));
+///
+/// address.stop().await.unwrap();
+/// # })
+/// ```
+///
+/// This macro will generate the following code for `increment`:
+///
+/// ```rust,no_run
+/// # use xtra::prelude::*;
+/// # struct Counter { count: u32 }
+/// # impl Actor for Counter { type Stop = (); async fn stopped(self) {} }
+/// /// Increment the counter, returning the new count.
+/// struct CounterIncrement {
+///     by: u32,
+/// }
+///
+/// impl Handler<CounterIncrement> for Counter {
+///     type Return = u32;
+///
+///     async fn handle(&mut self, message: CounterIncrement, _ctx: &mut Context<Self>) -> u32 {
+///         let CounterIncrement { by } = message;
+///         self.count += by;
+///         self.count
+///     }
+/// }
+///
+/// /// Methods for sending the messages handled by [`Counter`] through its address.
+/// trait CounterAddressExt<Rc: xtra::refcount::RefCounter> {
+///     /// Increment the counter, returning the new count.
+///     fn increment(
+///         &self,
+///         by: u32,
+///     ) -> xtra::SendFuture<xtra::ActorNamedSending<Counter, Rc>, xtra::ResolveToHandlerReturn<u32>>;
+/// }
+///
+/// impl<Rc: xtra::refcount::RefCounter> CounterAddressExt<Rc> for Address<Counter, Rc> {
+///     fn increment(
+///         &self,
+///         by: u32,
+///     ) -> xtra::SendFuture<xtra::ActorNamedSending<Counter, Rc>, xtra::ResolveToHandlerReturn<u32>> {
+///         self.send(CounterIncrement { by })
+///     }
+/// }
+/// ```
+///
+/// The extension trait has the widest visibility of the `async fn`s. Methods of [`Address`] take
+/// precedence over the methods of the extension trait, so an `async fn` which shares its name with
+/// one of them, e.g. `len` or `join`, can only be sent through its message. Generic `impl` blocks
+/// and generic functions are not supported.
+#[cfg(feature = "macros")]
+pub use macros::handler;
+
 use crate::recv_future::Message;
 
 /// Defines that an [`Actor`] can handle a given message `M`.
@@ -156,6 +299,75 @@ pub trait Handler<M>: Actor {
   fn handle(&mut self, message: M, ctx: &mut Context<Self>) -> impl Future<Output = Self::Return> + WasmSend;
 }
 
+/// A message which may be merged with other messages of the same type and with the same key while
+/// it is queued in a mailbox.
+///
+/// Messages sent through [`Address::send_coalesced`] do not queue up behind each other: if a
+/// message with the same [`coalesce_key`](Coalesce::coalesce_key) is still waiting in the mailbox,
+/// the new message is merged into it through [`Coalesce::coalesce`] instead, keeping its place in
+/// the queue. The actor then handles the merged message once, and its return value is sent to the
+/// senders of all merged messages. This is useful for notifications such as "refresh" or "state
+/// changed", where handling several of them in a row is redundant.
+///
+/// Messages are only merged while they are queued. Messages sent through [`Address::send`] are
+/// never merged, and neither are messages waiting for space in a full mailbox.
+///
+/// Looking for a queued message to merge with scans the whole mailbox, so sending a coalescing
+/// message takes time linear in the number of queued messages.
+///
+/// # Example
+///
+/// ```rust
+/// # use xtra::prelude::*;
+/// # use xtra::Coalesce;
+/// # #[derive(Default, xtra::Actor)]
+/// # struct Renderer { rendered: Vec<u32> }
+/// struct Redraw {
+///     window: u32,
+/// }
+///
+/// impl Coalesce for Redraw {
+///     type Key = u32;
+///
+///     fn coalesce_key(&self) -> u32 {
+///         self.window
+///     }
+/// }
+///
+/// impl Handler<Redraw> for Renderer {
+///     type Return = ();
+///
+///     async fn handle(&mut self, redraw: Redraw, _ctx: &mut Context<Self>) {
+///         self.rendered.push(redraw.window);
+///     }
+/// }
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let (address, mailbox) = Mailbox::<Renderer>::unbounded();
+///
+/// let first = address.send_coalesced(Redraw { window: 1 }).detach().await.unwrap();
+/// let second = address.send_coalesced(Redraw { window: 1 }).detach().await.unwrap();
+/// assert_eq!(address.len(), 1);
+///
+/// xtra::spawn_tokio(Renderer::default(), (address, mailbox));
+/// assert_eq!(first.await, Ok(()));
+/// assert_eq!(second.await, Ok(()));
+/// # })
+/// ```
+pub trait Coalesce: Sized {
+  /// The key which identifies messages that may be merged with each other.
+  type Key: PartialEq;
+
+  /// The key of this message. It is merged with a queued message only if their keys are equal.
+  fn coalesce_key(&self) -> Self::Key;
+
+  /// Merge a newer message with the same key into this queued one. By default, the newer message
+  /// replaces this one.
+  fn coalesce(&mut self, newer: Self) {
+    *self = newer;
+  }
+}
+
 /// An actor which can handle message one at a time. Actors can only be
 /// communicated with by sending messages through their [`Address`]es.
 /// They can modify their private state, respond to messages, and spawn other actors. They can also
@@ -223,11 +435,55 @@ pub trait Actor: 'static + WasmSend + Sized {
   /// - The actor called [`Context::stop_self`].
   /// - An actor called [`Context::stop_all`].
   /// - The last [`Address`] with a [`Strong`](crate::refcount::Strong) reference count was dropped.
+  /// - [`Actor::on_panic`] returned [`PanicAction::Stop`] or [`PanicAction::Restart`].
   fn stopped(self) -> impl Future<Output = Self::Stop> + WasmSend;
+
+  /// Called when a [`Handler`] of this actor panicked, with the payload of the panic.
+  ///
+  /// Panics in handlers are caught for all actors, whether or not they override this function:
+  /// the sender of the message that caused the panic is always notified with
+  /// [`Error::HandlerPanicked`] before this function is called. The returned [`PanicAction`] then
+  /// decides how the actor proceeds.
+  ///
+  /// The default implementation resumes unwinding with the given payload, which tears down the
+  /// task running the actor. Overriding this function opts the actor into panic isolation, i.e.
+  /// into surviving the panic. Keep in mind that the actor's state may have been left inconsistent
+  /// by the panicking handler.
+  fn on_panic(&mut self, payload: Box<dyn Any + Send>) -> impl Future<Output = PanicAction> + WasmSend {
+    async move { std::panic::resume_unwind(payload) }
+  }
+
+  /// Spawn this actor onto the given [`Spawner`], running it on the given [`Mailbox`].
+  ///
+  /// Returns an [`ActorHandle`] which resolves to the stop value of the actor. Dropping it does not
+  /// stop the actor.
+  fn spawn_on<S>(self, spawner: &S, mailbox: Mailbox<Self>) -> ActorHandle<Self::Stop>
+  where
+    S: Spawner,
+  {
+    let (task, handle) = spawn::with_handle(self, mailbox);
+    spawner.spawn(Box::pin(task));
+
+    handle
+  }
+}
+
+/// Decides how an actor proceeds after one of its handlers panicked. See [`Actor::on_panic`].
+#[derive(Clone, Copy, Eq, PartialEq, Debug)]
+pub enum PanicAction {
+  /// Keep handling messages as if the handler had returned normally.
+  Continue,
+  /// Stop the actor as if it had called [`Context::stop_self`].
+  Stop,
+  /// Stop the actor and ask its [`Supervisor`](supervisor::Supervisor) to restart it, regardless
+  /// of the restart policy of the child. Outside of a supervisor, this behaves like
+  /// [`PanicAction::Stop`].
+  Restart,
 }
 
 /// An error related to the actor system
 #[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(feature = "remote", derive(serde::Serialize, serde::Deserialize))]
 pub enum Error {
   /// The actor is no longer running and disconnected from the sending address.
   Disconnected,
@@ -237,6 +493,25 @@ pub enum Error {
   /// Unlike [`Error::Disconnected`], it does not necessarily imply that any retries or further
   /// attempts to interact with the actor will result in an error.
   Interrupted,
+  /// The handler panicked while handling the message. This is reported for every actor, including
+  /// those which do not override [`Actor::on_panic`]. Whether the actor is still running depends
+  /// on the [`PanicAction`] returned from [`Actor::on_panic`].
+  HandlerPanicked,
+  /// The deadline of the message request passed before the actor returned a result. See
+  /// [`SendFuture::deadline`].
+  Timeout,
+  /// The mailbox of the actor was full and its [`OverflowPolicy`] is [`OverflowPolicy::Reject`].
+  MailboxFull,
+  /// The message was not dispatched to the actor within its time-to-live and was discarded
+  /// without being handled. See [`SendFuture::ttl`].
+  Expired,
+  /// The message was not sent because the [`CircuitBreaker`](resilience::CircuitBreaker) guarding
+  /// the actor is open.
+  CircuitOpen,
+  /// The message or the return value of its handler could not be serialized for sending it to or
+  /// from a remote actor, for example because it was longer than [`MAX_FRAME_LEN`](remote::MAX_FRAME_LEN)
+  /// once serialized.
+  Serialization,
 }
 
 impl fmt::Display for Error {
@@ -244,12 +519,83 @@ impl fmt::Display for Error {
     match self {
       Error::Disconnected => f.write_str("Actor address disconnected"),
       Error::Interrupted => f.write_str("Message request interrupted"),
+      Error::HandlerPanicked => f.write_str("Message handler panicked"),
+      Error::Timeout => f.write_str("Message request timed out"),
+      Error::MailboxFull => f.write_str("Actor mailbox full"),
+      Error::Expired => f.write_str("Message expired before it was handled"),
+      Error::CircuitOpen => f.write_str("Circuit breaker open"),
+      Error::Serialization => f.write_str("Message could not be serialized"),
     }
   }
 }
 
 impl std::error::Error for Error {}
 
+/// An error returned from [`Address::try_send`] and [`MessageChannel::try_send`](message_channel::MessageChannel::try_send)
+/// if the message could not be sent right away. The message is handed back so it is not lost.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum TrySendError<M> {
+  /// The mailbox of the actor is full.
+  Full(M),
+  /// The actor is no longer running and disconnected from the sending address.
+  Disconnected(M),
+}
+
+impl<M> TrySendError<M> {
+  /// Take the message that could not be sent out of this error.
+  pub fn into_inner(self) -> M {
+    match self {
+      TrySendError::Full(message) | TrySendError::Disconnected(message) => message,
+    }
+  }
+
+  /// Returns whether the message could not be sent because the mailbox of the actor is full.
+  pub fn is_full(&self) -> bool {
+    matches!(self, TrySendError::Full(_))
+  }
+
+  /// Returns whether the message could not be sent because the actor is disconnected.
+  pub fn is_disconnected(&self) -> bool {
+    matches!(self, TrySendError::Disconnected(_))
+  }
+
+  pub(crate) fn map<N>(self, f: impl FnOnce(M) -> N) -> TrySendError<N> {
+    match self {
+      TrySendError::Full(message) => TrySendError::Full(f(message)),
+      TrySendError::Disconnected(message) => TrySendError::Disconnected(f(message)),
+    }
+  }
+}
+
+impl<M> fmt::Debug for TrySendError<M> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TrySendError::Full(_) => f.write_str("Full(..)"),
+      TrySendError::Disconnected(_) => f.write_str("Disconnected(..)"),
+    }
+  }
+}
+
+impl<M> fmt::Display for TrySendError<M> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      TrySendError::Full(_) => f.write_str("Actor mailbox full"),
+      TrySendError::Disconnected(_) => f.write_str("Actor address disconnected"),
+    }
+  }
+}
+
+impl<M> std::error::Error for TrySendError<M> {}
+
+impl<M> From<TrySendError<M>> for Error {
+  fn from(error: TrySendError<M>) -> Self {
+    match error {
+      TrySendError::Full(_) => Error::MailboxFull,
+      TrySendError::Disconnected(_) => Error::Disconnected,
+    }
+  }
+}
+
 /// Run the provided actor.
 ///
 /// This is the primary event loop of an actor which takes messages out of the mailbox and hands
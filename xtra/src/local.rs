@@ -0,0 +1,305 @@
+//! Actors which are not [`Send`], running on a local executor.
+//!
+//! [`Actor`] requires its implementors to be [`Send`] on native targets, so that they can be
+//! spawned onto multi-threaded runtimes. A [`LocalActor`] may instead hold `Rc`s, `RefCell`s and
+//! other thread-bound state, and is spawned onto a single-threaded executor through a
+//! [`LocalSpawner`], such as a tokio [`LocalSet`](tokio::task::LocalSet).
+//!
+//! Messages are still sent to local actors through a regular [`Address`](crate::Address) and
+//! received in a regular [`Mailbox`], of the [`Local`] actor which forwards them to the local actor.
+//! Both stay [`Send`], so other threads can still message the actor as long as the messages and
+//! their return values are [`Send`].
+//!
+//! ```rust
+//! # use std::cell::RefCell;
+//! # use std::rc::Rc;
+//! # use xtra::prelude::*;
+//! use xtra::local::{Local, LocalActor, LocalHandler};
+//!
+//! struct Counter {
+//!     count: Rc<RefCell<u32>>,
+//! }
+//!
+//! impl LocalActor for Counter {
+//!     type Stop = ();
+//!
+//!     async fn stopped(self) {}
+//! }
+//!
+//! struct Increment;
+//!
+//! impl LocalHandler<Increment> for Counter {
+//!     type Return = u32;
+//!
+//!     async fn handle(&mut self, _: Increment, _ctx: &mut Context<Local<Self>>) -> u32 {
+//!         *self.count.borrow_mut() += 1;
+//!         *self.count.borrow()
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let local_set = tokio::task::LocalSet::new();
+//! let (address, mailbox) = Mailbox::unbounded();
+//! let _ = Counter { count: Rc::default() }.spawn_local(&local_set, mailbox);
+//!
+//! // The address is `Send`, so the actor can be messaged from other threads.
+//! let increment = tokio::spawn(async move { address.send(Increment).await });
+//!
+//! local_set
+//!     .run_until(async { assert_eq!(increment.await.unwrap(), Ok(1)) })
+//!     .await;
+//! # })
+//! ```
+
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Mutex;
+use std::thread;
+
+use futures_core::future::LocalBoxFuture;
+use futures_util::{future, FutureExt};
+
+use crate::blocking::HandlerGuard;
+use crate::{Actor, ActorHandle, Context, Handler, Mailbox, WasmRc, WasmSend};
+
+/// An actor which does not need to be [`Send`], as it runs on a local executor. See the
+/// [module documentation](self) for details.
+///
+/// Local actors handle messages through [`LocalHandler`]. Their [`Context`] and [`Mailbox`] are
+/// those of the [`Local`] actor which forwards messages to them.
+pub trait LocalActor: 'static + Sized {
+    /// Value returned from the actor when [`LocalActor::stopped`] is called.
+    type Stop: 'static;
+
+    /// Called as soon as the actor has been started.
+    #[allow(unused_variables)]
+    fn started(
+        &mut self,
+        mailbox: &Mailbox<Local<Self>>,
+    ) -> impl Future<Output = Result<(), Self::Stop>> {
+        async { Ok(()) }
+    }
+
+    /// Called at the end of an actor's event loop. See [`Actor::stopped`] for the reasons an actor
+    /// may stop.
+    fn stopped(self) -> impl Future<Output = Self::Stop>;
+
+    /// Spawn this actor onto the given [`LocalSpawner`], running it on the given [`Mailbox`].
+    ///
+    /// Returns an [`ActorHandle`] which resolves to the stop value of the actor. Dropping it does not
+    /// stop the actor.
+    fn spawn_local<S>(self, spawner: &S, mailbox: Mailbox<Local<Self>>) -> ActorHandle<Self::Stop>
+    where
+        S: LocalSpawner,
+    {
+        let (task, handle) = crate::spawn::task_with_handle(run(mailbox, self));
+        LocalSpawner::spawn_local(spawner, Box::pin(task));
+
+        handle
+    }
+}
+
+/// Defines that a [`LocalActor`] can handle a given message. This is the counterpart of
+/// [`Handler`] for local actors.
+///
+/// Unlike with [`Handler`], the future returned from [`LocalHandler::handle`] does not need to be
+/// [`Send`]. The message and its return value still do, as they are sent from and to other threads.
+pub trait LocalHandler<M>: LocalActor {
+    /// The return value of this handler.
+    type Return: WasmSend + 'static;
+
+    /// Handle a given message, returning its result.
+    fn handle(
+        &mut self,
+        message: M,
+        ctx: &mut Context<Local<Self>>,
+    ) -> impl Future<Output = Self::Return>;
+}
+
+/// The [`Actor`] which forwards messages to the [`LocalActor`] `A`.
+///
+/// Addresses and mailboxes of a local actor are those of its [`Local`] actor, i.e.
+/// `Address<Local<A>>` and `Mailbox<Local<A>>`. A [`Local`] actor cannot be created on its own; it
+/// only exists within the event loop of its local actor, see [`run`].
+pub struct Local<A> {
+    pending: WasmRc<Mutex<Option<Box<dyn Pending<A>>>>>,
+}
+
+impl<A: LocalActor> Actor for Local<A> {
+    type Stop = ();
+
+    async fn stopped(self) {}
+}
+
+impl<A, M> Handler<M> for Local<A>
+where
+    A: LocalHandler<M>,
+    M: WasmSend + 'static,
+{
+    type Return = A::Return;
+
+    fn handle(
+        &mut self,
+        message: M,
+        _ctx: &mut Context<Self>,
+    ) -> impl Future<Output = A::Return> + WasmSend {
+        let (result, receiver) = catty::oneshot();
+        *self.pending.lock().unwrap() = Some(Box::new(PendingMessage { message, result }));
+
+        async move {
+            match receiver.await {
+                Ok(Ok(result)) => result,
+                Ok(Err(payload)) => std::panic::resume_unwind(payload),
+                Err(_) => unreachable!("the event loop of a local actor handles every message"),
+            }
+        }
+    }
+}
+
+/// A message handed to a [`Local`] actor, waiting to be handled by its local actor.
+trait Pending<A>: WasmSend {
+    fn handle<'a>(
+        self: Box<Self>,
+        actor: &'a mut A,
+        ctx: &'a mut Context<Local<A>>,
+    ) -> LocalBoxFuture<'a, ()>;
+}
+
+struct PendingMessage<M, R> {
+    message: M,
+    result: catty::Sender<thread::Result<R>>,
+}
+
+impl<A, M> Pending<A> for PendingMessage<M, A::Return>
+where
+    A: LocalHandler<M>,
+    M: WasmSend + 'static,
+{
+    fn handle<'a>(
+        self: Box<Self>,
+        actor: &'a mut A,
+        ctx: &'a mut Context<Local<A>>,
+    ) -> LocalBoxFuture<'a, ()> {
+        let PendingMessage { message, result } = *self;
+
+        Box::pin(async move {
+            let handled = AssertUnwindSafe(actor.handle(message, ctx))
+                .catch_unwind()
+                .await;
+
+            // The panic is resumed by `Local::handle`, which notifies the sender as usual.
+            let _ = result.send(handled);
+        })
+    }
+}
+
+/// Run the provided local actor. This is the counterpart of [`run`](crate::run) for local actors,
+/// which returns a future that does not need to be [`Send`].
+///
+/// Each message is first dispatched to the [`Local`] actor of the mailbox, which hands it over to
+/// the local actor and resolves once the local actor has handled it.
+pub async fn run<A>(mailbox: Mailbox<Local<A>>, mut actor: A) -> A::Stop
+where
+    A: LocalActor,
+{
+    let pending = WasmRc::new(Mutex::new(None));
+    let mut local = Local {
+        pending: pending.clone(),
+    };
+    let mut ctx = Context {
+        running: true,
+        mailbox,
+    };
+
+    if let Err(stop) = actor.started(&ctx.mailbox).await {
+        return stop;
+    }
+
+    while ctx.running {
+        let mut dispatch = ctx.mailbox.next().await.dispatch_to(&mut local);
+
+        // Polling the dispatch once calls `Local::handle`, which leaves the message in `pending`.
+        let flow = match (&mut dispatch).now_or_never() {
+            Some(flow) => flow,
+            None => {
+                let message = pending.lock().unwrap().take();
+
+                if let Some(message) = message {
+                    let mut handle = message.handle(&mut actor, &mut ctx);
+
+                    future::poll_fn(|cx| {
+                        let _guard = HandlerGuard::enter();
+                        handle.poll_unpin(cx)
+                    })
+                    .await;
+                }
+
+                dispatch.await
+            }
+        };
+
+        if flow.is_break() {
+            break;
+        }
+    }
+
+    actor.stopped().await
+}
+
+/// A single-threaded executor onto which local actors can be spawned through
+/// [`LocalActor::spawn_local`].
+///
+/// Implementations are provided for tokio's [`LocalSet`](tokio::task::LocalSet) with the `tokio`
+/// feature, smol's `LocalExecutor` with the `smol` feature, `futures::executor::LocalSpawner` with
+/// the `local_pool` feature, and for closures which spawn the given task themselves, e.g.
+/// `|task: LocalBoxFuture<'static, ()>| { tokio::task::spawn_local(task); }`.
+pub trait LocalSpawner {
+    /// Spawn the given task onto this executor, running it to completion in the background.
+    fn spawn_local(&self, task: LocalBoxFuture<'static, ()>);
+}
+
+#[cfg(all(feature = "tokio", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+impl LocalSpawner for tokio::task::LocalSet {
+    fn spawn_local(&self, task: LocalBoxFuture<'static, ()>) {
+        tokio::task::LocalSet::spawn_local(self, task);
+    }
+}
+
+#[cfg(all(feature = "smol", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+impl LocalSpawner for smol::LocalExecutor<'_> {
+    fn spawn_local(&self, task: LocalBoxFuture<'static, ()>) {
+        self.spawn(task).detach();
+    }
+}
+
+#[cfg(all(feature = "local_pool", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "local_pool")))]
+impl LocalSpawner for futures_executor::LocalSpawner {
+    fn spawn_local(&self, task: LocalBoxFuture<'static, ()>) {
+        use futures_util::task::LocalSpawn;
+
+        // This only fails if the pool was dropped, in which case the actor handle is interrupted.
+        let _ = self.spawn_local_obj(task.into());
+    }
+}
+
+#[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm_bindgen")))]
+impl LocalSpawner for crate::WasmBindgenSpawner {
+    fn spawn_local(&self, task: LocalBoxFuture<'static, ()>) {
+        wasm_bindgen_futures::spawn_local(task);
+    }
+}
+
+/// Any closure which spawns the given task is a [`LocalSpawner`].
+impl<F> LocalSpawner for F
+where
+    F: Fn(LocalBoxFuture<'static, ()>),
+{
+    fn spawn_local(&self, task: LocalBoxFuture<'static, ()>) {
+        self(task);
+    }
+}
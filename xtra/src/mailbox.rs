@@ -1,6 +1,11 @@
-use crate::chan::{self, BroadcastQueue, Rx};
+use std::marker::PhantomData;
+
+use crate::chan::{self, BroadcastQueue, HasPriority, Priority, QueuedMessage, Rx};
+use crate::dead_letter::{self, DeadLetter};
+#[cfg(feature = "metrics")]
+use crate::metrics::HandlerTimer;
 use crate::recv_future::ReceiveFuture;
-use crate::{Address, WasmRc, WeakAddress};
+use crate::{Address, WasmRc, WasmSend, WeakAddress};
 
 /// A [`Mailbox`] is the counter-part to an [`Address`].
 ///
@@ -14,30 +19,23 @@ pub struct Mailbox<A> {
 impl<A> Mailbox<A> {
     /// Creates a new [`Mailbox`] with the given capacity.
     pub fn bounded(capacity: usize) -> (Address<A>, Mailbox<A>) {
-        let (sender, receiver) = chan::new(Some(capacity));
-
-        let address = Address(sender);
-        let mailbox = Mailbox {
-            broadcast_mailbox: receiver.new_broadcast_mailbox(),
-            inner: receiver,
-        };
-
-        (address, mailbox)
+        Self::builder().capacity(capacity).build()
     }
 
     /// Creates a new, unbounded [`Mailbox`].
     ///
     /// Unbounded mailboxes will not perform an back-pressure and can result in potentially unbounded memory growth. Use with care.
     pub fn unbounded() -> (Address<A>, Mailbox<A>) {
-        let (sender, receiver) = crate::chan::new(None);
-
-        let address = Address(sender);
-        let mailbox = Mailbox {
-            broadcast_mailbox: receiver.new_broadcast_mailbox(),
-            inner: receiver,
-        };
+        Self::builder().build()
+    }
 
-        (address, mailbox)
+    /// Creates a [`MailboxBuilder`] to configure a new [`Mailbox`]. By default, the [`Mailbox`] is
+    /// unbounded.
+    pub fn builder() -> MailboxBuilder<A> {
+        MailboxBuilder {
+            options: chan::Options::default(),
+            phantom: PhantomData,
+        }
     }
 
     /// Obtain a [`WeakAddress`] to this [`Mailbox`].
@@ -53,6 +51,46 @@ impl<A> Mailbox<A> {
         ReceiveFuture::new(self.inner.clone(), self.broadcast_mailbox.clone())
     }
 
+    /// Create another handle to this [`Mailbox`] which shares its broadcast queue.
+    ///
+    /// Unlike [`Clone`], this does not register a new broadcast queue with the channel. Only one
+    /// of the two handles should be used to receive messages at any point in time.
+    pub(crate) fn share(&self) -> Self {
+        Self::from_parts(self.inner.clone(), self.broadcast_mailbox.clone())
+    }
+
+    /// Discard any shutdown notifications still pending in this [`Mailbox`]'s broadcast queue.
+    ///
+    /// This is used before an actor is restarted on the same [`Mailbox`] so that it does not
+    /// immediately stop again due to a shutdown that was meant for its predecessor.
+    pub(crate) fn discard_pending_shutdown(&self) {
+        self.broadcast_mailbox
+            .lock()
+            .retain(|msg| msg.priority() != Priority::Shutdown);
+    }
+
+    /// Ask the [`Supervisor`](crate::supervisor::Supervisor) of this actor to restart it once it
+    /// has stopped.
+    pub(crate) fn request_restart(&self) {
+        self.inner.request_restart();
+    }
+
+    /// Returns whether a restart was requested by the actor that last ran on this [`Mailbox`].
+    pub(crate) fn take_restart_request(&self) -> bool {
+        self.inner.take_restart_request()
+    }
+
+    /// Describe the messages which are queued for this [`Mailbox`].
+    pub(crate) fn queued(&self) -> Vec<QueuedMessage> {
+        self.inner.queued(&self.broadcast_mailbox)
+    }
+
+    /// Record that a message of the given type was handled by the actor on this [`Mailbox`].
+    #[cfg(feature = "metrics")]
+    pub(crate) fn record_handled(&self, message: &'static str, timer: HandlerTimer) {
+        self.inner.record_handled(message, timer);
+    }
+
     pub(crate) fn from_parts(
         chan: chan::Ptr<A, Rx>,
         broadcast_mailbox: WasmRc<BroadcastQueue<A>>,
@@ -64,6 +102,146 @@ impl<A> Mailbox<A> {
     }
 }
 
+/// A builder for a [`Mailbox`], created through [`Mailbox::builder`].
+pub struct MailboxBuilder<A> {
+    options: chan::Options,
+    phantom: PhantomData<for<'a> fn(&'a A)>,
+}
+
+impl<A> MailboxBuilder<A> {
+    /// Bound the [`Mailbox`] to the given capacity. See [`Address`] for how the capacity applies
+    /// to the different kinds of messages.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.options.capacity = Some(capacity);
+        self
+    }
+
+    /// Decide what happens to messages sent to a single actor while the [`Mailbox`] is full. By
+    /// default, senders wait for space, see [`OverflowPolicy::Block`].
+    ///
+    /// This only has an effect on bounded mailboxes. Broadcasts always wait for space.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// # use xtra::OverflowPolicy;
+    /// # struct MyActor;
+    /// # impl Actor for MyActor {type Stop = (); async fn stopped(self) -> Self::Stop {} }
+    /// let (address, mailbox) = Mailbox::<MyActor>::builder()
+    ///     .capacity(100)
+    ///     .overflow_policy(OverflowPolicy::DropOldest)
+    ///     .build();
+    /// # drop((address, mailbox));
+    /// ```
+    pub fn overflow_policy(mut self, policy: OverflowPolicy) -> Self {
+        self.options.overflow = policy;
+        self
+    }
+
+    /// Report every message which is dropped from the [`Mailbox`] without being handled, or which
+    /// could not be delivered to it, to the given sink.
+    ///
+    /// The sink is called synchronously from within xtra, for example while a message is being
+    /// sent. It should therefore return quickly and not block, e.g. by forwarding the
+    /// [`DeadLetter`] into a channel. Dead letters which arise while the sink runs, e.g. because
+    /// it sends to this same [`Mailbox`] and fails to do so, are dropped instead of being reported
+    /// to the sink again. A sink which panicked keeps receiving dead letters.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// # struct MyActor;
+    /// # impl Actor for MyActor {type Stop = (); async fn stopped(self) -> Self::Stop {} }
+    /// let (address, mailbox) = Mailbox::<MyActor>::builder()
+    ///     .dead_letters(|letter| eprintln!("{} was not handled: {}", letter.message, letter.reason))
+    ///     .build();
+    /// # drop((address, mailbox));
+    /// ```
+    pub fn dead_letters<F>(mut self, sink: F) -> Self
+    where
+        F: FnMut(DeadLetter) + WasmSend + 'static,
+    {
+        self.options.dead_letters = Some(dead_letter::Sink::new(sink));
+        self
+    }
+
+    /// Call the given hook for every message which is discarded from the [`Mailbox`] because its
+    /// [time-to-live](crate::SendFuture::ttl) passed before it was dispatched to the actor.
+    ///
+    /// Expired messages are reported as [`DeadLetter`]s with
+    /// [`Reason::Expired`](dead_letter::Reason::Expired), both to this hook and to the sink
+    /// configured through [`MailboxBuilder::dead_letters`]. Like that sink, the hook is called
+    /// synchronously from within xtra and should return quickly.
+    ///
+    /// ```rust
+    /// # use xtra::prelude::*;
+    /// # struct MyActor;
+    /// # impl Actor for MyActor {type Stop = (); async fn stopped(self) -> Self::Stop {} }
+    /// let (address, mailbox) = Mailbox::<MyActor>::builder()
+    ///     .on_expired(|letter| eprintln!("{} expired in the mailbox", letter.message))
+    ///     .build();
+    /// # drop((address, mailbox));
+    /// ```
+    pub fn on_expired<F>(mut self, hook: F) -> Self
+    where
+        F: FnMut(DeadLetter) + WasmSend + 'static,
+    {
+        self.options.on_expired = Some(dead_letter::Sink::new(hook));
+        self
+    }
+
+    /// Export the metrics of the [`Mailbox`] through the facade of the
+    /// [`metrics`](https://docs.rs/metrics) crate, labelled with the given name as `actor`. See
+    /// [`xtra::metrics`](crate::metrics) for the exported metrics.
+    #[cfg(feature = "metrics")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "metrics")))]
+    pub fn export_metrics(mut self, name: impl Into<String>) -> Self {
+        self.options.export_metrics = Some(name.into());
+        self
+    }
+
+    /// Create the [`Mailbox`], returning it together with the initial [`Address`] to it.
+    pub fn build(self) -> (Address<A>, Mailbox<A>) {
+        let (sender, receiver) = chan::new(self.options);
+
+        let address = Address(sender);
+        let mailbox = Mailbox {
+            broadcast_mailbox: receiver.new_broadcast_mailbox(),
+            inner: receiver,
+        };
+
+        (address, mailbox)
+    }
+}
+
+/// What happens to a message sent to a single actor while its bounded [`Mailbox`] is full. See
+/// [`MailboxBuilder::overflow_policy`].
+///
+/// Messages dropped because of the policy are reported as [`DeadLetter`]s with
+/// [`Reason::DroppedByPolicy`](dead_letter::Reason::DroppedByPolicy). Awaiting the result of a
+/// dropped message resolves to [`Error::Interrupted`](crate::Error::Interrupted).
+///
+/// [`DropOldest`](OverflowPolicy::DropOldest) and
+/// [`DropLowestPriority`](OverflowPolicy::DropLowestPriority) search all queued messages for the
+/// one to drop and then restore the order of the queue, so handling an overflow takes time linear
+/// in the capacity of the mailbox with them. The other policies take constant time.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum OverflowPolicy {
+    /// Wait until there is space in the mailbox, exercising backpressure on the sender.
+    #[default]
+    Block,
+    /// Drop the message being sent. Sending it still succeeds.
+    DropNewest,
+    /// Drop the message that has been in the mailbox the longest, regardless of its priority, to
+    /// make space for the message being sent.
+    DropOldest,
+    /// Drop the message being sent and resolve the sending to
+    /// [`Error::MailboxFull`](crate::Error::MailboxFull).
+    Reject,
+    /// Drop the message which would be handled last, out of the queued messages and the message
+    /// being sent. This is the message with the lowest priority, or the most recently sent of
+    /// several messages with the lowest priority.
+    DropLowestPriority,
+}
+
 impl<A> Clone for Mailbox<A> {
     fn clone(&self) -> Self {
         Mailbox {
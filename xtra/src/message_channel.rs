@@ -9,9 +9,9 @@ use crate::address::{ActorJoinHandle, Address};
 use crate::chan::RefCounter;
 use crate::refcount::{Either, Strong, Weak};
 use crate::send_future::{ActorErasedSending, ResolveToHandlerReturn, SendFuture};
-use crate::{Handler, WasmSend, WasmSendSync};
+use crate::{Handler, Receiver, TrySendError, WasmSend, WasmSendSync};
 
-trait MessageChannelTraitWasm<M, Rc, R>: MessageChannelTrait<M, Rc, Return = R> + WasmSendSync {}
+pub(crate) trait MessageChannelTraitWasm<M, Rc, R>: MessageChannelTrait<M, Rc, Return = R> + WasmSendSync {}
 impl<M, Rc, R, T: MessageChannelTrait<M, Rc, Return = R> + WasmSendSync> MessageChannelTraitWasm<M, Rc, R> for T {}
 
 /// A message channel is a channel through which you can send only one kind of message, but to
@@ -86,9 +86,16 @@ where
     A: Handler<M, Return = R>,
     Rc: RefCounter + Into<Either>,
   {
-    Self {
-      inner: Box::new(address),
-    }
+    Self::from_trait(address)
+  }
+
+  /// Construct a new [`MessageChannel`] from anything that can send messages of type `M`, such as
+  /// an [`ActorPool`](crate::pool::ActorPool).
+  pub(crate) fn from_trait<T>(inner: T) -> Self
+  where
+    T: MessageChannelTrait<M, Rc, Return = R> + WasmSendSync + 'static,
+  {
+    Self { inner: Box::new(inner) }
   }
 
   /// Returns whether the actor referred to by this message channel is running and accepting messages.
@@ -126,6 +133,11 @@ where
     self.inner.send(message)
   }
 
+  /// Send a message to the actor without waiting. See [`Address::try_send`] for details.
+  pub fn try_send(&self, message: M) -> Result<Receiver<R>, TrySendError<M>> {
+    self.inner.try_send(message)
+  }
+
   /// Waits until this [`MessageChannel`] becomes disconnected.
   pub fn join(&self) -> ActorJoinHandle {
     self.inner.join()
@@ -271,7 +283,7 @@ where
   }
 }
 
-trait MessageChannelTrait<M, Rc> {
+pub(crate) trait MessageChannelTrait<M, Rc> {
   type Return: WasmSend + 'static;
 
   fn is_connected(&self) -> bool;
@@ -282,6 +294,8 @@ trait MessageChannelTrait<M, Rc> {
 
   fn send(&self, message: M) -> SendFuture<ActorErasedSending, ResolveToHandlerReturn<Self::Return>>;
 
+  fn try_send(&self, message: M) -> Result<Receiver<Self::Return>, TrySendError<M>>;
+
   fn clone_channel(&self) -> Box<dyn MessageChannelTraitWasm<M, Rc, Self::Return> + 'static>;
 
   fn join(&self) -> ActorJoinHandle;
@@ -328,6 +342,10 @@ where
     SendFuture::sending_erased(message, self.0.clone())
   }
 
+  fn try_send(&self, message: M) -> Result<Receiver<R>, TrySendError<M>> {
+    self.try_send(message)
+  }
+
   fn clone_channel(&self) -> Box<dyn MessageChannelTraitWasm<M, Rc, Self::Return> + 'static> {
     Box::new(self.clone())
   }
@@ -0,0 +1,348 @@
+//! Metrics about the mailbox of an actor and the messages it handles.
+//!
+//! While the `metrics` feature is enabled, every mailbox records how long messages wait before
+//! they are dispatched to the actor, how long their handlers take and how many messages of each
+//! type were handled. Together with the depth of its queues, these can be read at any point in
+//! time through [`Address::metrics`](crate::Address::metrics).
+//!
+//! ```rust
+//! # use xtra::prelude::*;
+//! # struct MyActor;
+//! # impl Actor for MyActor { type Stop = (); async fn stopped(self) {} }
+//! # struct Ping;
+//! # impl Handler<Ping> for MyActor {
+//! #     type Return = ();
+//! #     async fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {}
+//! # }
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let address = xtra::spawn_tokio(MyActor, Mailbox::unbounded());
+//! address.send(Ping).await.unwrap();
+//!
+//! let metrics = address.metrics();
+//! assert_eq!(metrics.message::<Ping>().unwrap().handled, 1);
+//! assert_eq!(metrics.queue.unicast, 0);
+//! # })
+//! ```
+//!
+//! Additionally, the metrics of a mailbox can be exported continuously through the facade of the
+//! [`metrics`](https://docs.rs/metrics) crate by configuring it with
+//! [`MailboxBuilder::export_metrics`](crate::MailboxBuilder::export_metrics). The following
+//! metrics are recorded, all labelled with the name of the actor as `actor`:
+//!
+//! - `xtra_mailbox_messages` (gauge): the number of queued messages, labelled with `queue` as
+//!   either `unicast`, `broadcast` or `waiting_senders`.
+//! - `xtra_messages_handled_total` (counter): the number of handled messages, labelled with the
+//!   type name of the message as `message`.
+//! - `xtra_message_latency_seconds` (histogram): the time between sending a message and
+//!   dispatching it to the actor, labelled with `message`.
+//! - `xtra_handler_duration_seconds` (histogram): the time spent in
+//!   [`Handler::handle`](crate::Handler::handle), labelled with `message`.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use web_time::Instant;
+
+/// The number of buckets of a [`Histogram`].
+const BUCKETS: usize = 32;
+
+/// A point-in-time view of the metrics of an actor's mailbox.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MetricsSnapshot {
+    /// The type name of the actor.
+    pub actor: &'static str,
+    /// The number of messages queued in the mailbox.
+    pub queue: QueueDepth,
+    /// The metrics of every type of message handled by the actor so far, ordered by type name.
+    pub messages: Vec<MessageMetrics>,
+}
+
+impl MetricsSnapshot {
+    /// The metrics of messages of type `M`, or `None` if no such message was handled yet.
+    pub fn message<M>(&self) -> Option<&MessageMetrics> {
+        let name = std::any::type_name::<M>();
+
+        self.messages.iter().find(|metrics| metrics.message == name)
+    }
+
+    /// The total number of messages handled by the actor.
+    pub fn handled(&self) -> u64 {
+        self.messages.iter().map(|metrics| metrics.handled).sum()
+    }
+}
+
+/// The number of messages queued in a mailbox.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct QueueDepth {
+    /// The number of messages sent to a single actor.
+    pub unicast: usize,
+    /// The number of broadcast messages not yet handled by the slowest actor on the mailbox.
+    pub broadcast: usize,
+    /// The number of senders waiting for space in a bounded mailbox.
+    pub waiting_senders: usize,
+}
+
+/// The metrics of one type of message handled by an actor.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MessageMetrics {
+    /// The type name of the message.
+    pub message: &'static str,
+    /// The number of messages of this type that were handled.
+    pub handled: u64,
+    /// The time between sending a message and dispatching it to the actor. This includes the time
+    /// a sender spent waiting for space in a bounded mailbox.
+    pub latency: Histogram,
+    /// The time spent in [`Handler::handle`](crate::Handler::handle).
+    pub handler_duration: Histogram,
+}
+
+impl MessageMetrics {
+    fn new(message: &'static str) -> Self {
+        MessageMetrics {
+            message,
+            handled: 0,
+            latency: Histogram::default(),
+            handler_duration: Histogram::default(),
+        }
+    }
+}
+
+/// A histogram of durations with exponentially growing buckets.
+///
+/// The first bucket holds durations below one microsecond. Every following bucket holds durations
+/// of up to twice the upper bound of the previous one, up to the last bucket, which holds every
+/// duration longer than about 18 minutes.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Histogram {
+    buckets: [u64; BUCKETS],
+    count: u64,
+    sum: Duration,
+    max: Duration,
+}
+
+impl Histogram {
+    /// Record a single duration.
+    pub fn record(&mut self, duration: Duration) {
+        self.buckets[bucket(duration)] += 1;
+        self.count += 1;
+        self.sum = self.sum.saturating_add(duration);
+        self.max = self.max.max(duration);
+    }
+
+    /// The number of recorded durations.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// The sum of all recorded durations.
+    pub fn sum(&self) -> Duration {
+        self.sum
+    }
+
+    /// The longest recorded duration.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// The mean of all recorded durations, or `None` if none were recorded.
+    pub fn mean(&self) -> Option<Duration> {
+        let count = u32::try_from(self.count).unwrap_or(u32::MAX);
+
+        (count > 0).then(|| self.sum / count)
+    }
+
+    /// An upper bound of the given quantile, or `None` if no durations were recorded.
+    ///
+    /// The result is the upper bound of the bucket which contains the quantile, but never longer
+    /// than the [longest recorded duration](Histogram::max).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `quantile` is not between 0 and 1.
+    pub fn quantile(&self, quantile: f64) -> Option<Duration> {
+        assert!(
+            (0.0..=1.0).contains(&quantile),
+            "quantile must be between 0 and 1"
+        );
+
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((self.count as f64 * quantile).ceil() as u64).max(1);
+        let mut seen = 0;
+
+        self.buckets()
+            .find(|(_, count)| {
+                seen += count;
+                seen >= rank
+            })
+            .map(|(upper_bound, _)| upper_bound.min(self.max))
+    }
+
+    /// The buckets of this histogram as pairs of their exclusive upper bound and the number of
+    /// durations recorded in them. The upper bound of the last bucket is [`Duration::MAX`].
+    pub fn buckets(&self) -> impl Iterator<Item = (Duration, u64)> + '_ {
+        self.buckets
+            .iter()
+            .enumerate()
+            .map(|(i, count)| (upper_bound(i), *count))
+    }
+}
+
+/// The index of the bucket the given duration falls into.
+fn bucket(duration: Duration) -> usize {
+    let micros = duration.as_micros();
+    let bucket = (u128::BITS - micros.leading_zeros()) as usize;
+
+    bucket.min(BUCKETS - 1)
+}
+
+/// The exclusive upper bound of the bucket with the given index.
+fn upper_bound(bucket: usize) -> Duration {
+    if bucket == BUCKETS - 1 {
+        return Duration::MAX;
+    }
+
+    Duration::from_micros(1 << bucket)
+}
+
+/// The time at which the handling of a message started, as well as how long it waited to be
+/// dispatched.
+pub(crate) struct HandlerTimer {
+    latency: Duration,
+    started: Instant,
+}
+
+impl HandlerTimer {
+    /// Start timing the handler of a message that was sent at the given point in time.
+    pub(crate) fn start(sent_at: Option<Instant>) -> Self {
+        let started = Instant::now();
+
+        HandlerTimer {
+            latency: sent_at.map_or(Duration::ZERO, |sent_at| started - sent_at),
+            started,
+        }
+    }
+}
+
+/// Records the metrics of a single mailbox.
+pub(crate) struct Recorder {
+    actor: &'static str,
+    messages: Mutex<HashMap<&'static str, Entry>>,
+    exporter: Option<Exporter>,
+}
+
+struct Entry {
+    metrics: MessageMetrics,
+    exported: Option<ExportedMessage>,
+}
+
+impl Recorder {
+    /// Create a recorder for the given actor, exporting its metrics under the given name if set.
+    pub(crate) fn new<A>(export_as: Option<String>) -> Self {
+        Recorder {
+            actor: std::any::type_name::<A>(),
+            messages: Mutex::new(HashMap::new()),
+            exporter: export_as.map(Exporter::new),
+        }
+    }
+
+    /// Record that a message of the given type was handled.
+    pub(crate) fn record_handled(&self, message: &'static str, timer: HandlerTimer) {
+        let duration = timer.started.elapsed();
+
+        let mut messages = match self.messages.lock() {
+            Ok(messages) => messages,
+            Err(_) => return, // Poisoned, ignore
+        };
+
+        let entry = messages.entry(message).or_insert_with(|| Entry {
+            metrics: MessageMetrics::new(message),
+            exported: self
+                .exporter
+                .as_ref()
+                .map(|exporter| exporter.message(message)),
+        });
+
+        entry.metrics.handled += 1;
+        entry.metrics.latency.record(timer.latency);
+        entry.metrics.handler_duration.record(duration);
+
+        if let Some(exported) = &entry.exported {
+            exported.handled.increment(1);
+            exported.latency.record(timer.latency);
+            exported.handler_duration.record(duration);
+        }
+    }
+
+    /// Export the given depth of the queues, if exporting is enabled.
+    pub(crate) fn export_queue_depth(&self, depth: QueueDepth) {
+        if let Some(exporter) = &self.exporter {
+            exporter.unicast.set(depth.unicast as f64);
+            exporter.broadcast.set(depth.broadcast as f64);
+            exporter.waiting_senders.set(depth.waiting_senders as f64);
+        }
+    }
+
+    /// Take a snapshot of the recorded metrics together with the given depth of the queues.
+    pub(crate) fn snapshot(&self, queue: QueueDepth) -> MetricsSnapshot {
+        let mut messages = match self.messages.lock() {
+            Ok(messages) => messages
+                .values()
+                .map(|entry| entry.metrics.clone())
+                .collect::<Vec<_>>(),
+            Err(_) => Vec::new(),
+        };
+
+        messages.sort_by_key(|metrics| metrics.message);
+
+        MetricsSnapshot {
+            actor: self.actor,
+            queue,
+            messages,
+        }
+    }
+}
+
+/// Handles to the metrics of a mailbox in the facade of the `metrics` crate.
+struct Exporter {
+    name: String,
+    unicast: ::metrics::Gauge,
+    broadcast: ::metrics::Gauge,
+    waiting_senders: ::metrics::Gauge,
+}
+
+struct ExportedMessage {
+    handled: ::metrics::Counter,
+    latency: ::metrics::Histogram,
+    handler_duration: ::metrics::Histogram,
+}
+
+impl Exporter {
+    fn new(name: String) -> Self {
+        let gauge = |queue: &'static str| ::metrics::gauge!("xtra_mailbox_messages", "actor" => name.clone(), "queue" => queue);
+
+        Exporter {
+            unicast: gauge("unicast"),
+            broadcast: gauge("broadcast"),
+            waiting_senders: gauge("waiting_senders"),
+            name,
+        }
+    }
+
+    fn message(&self, message: &'static str) -> ExportedMessage {
+        let labels = [
+            ::metrics::Label::new("actor", self.name.clone()),
+            ::metrics::Label::new("message", message),
+        ];
+
+        ExportedMessage {
+            handled: ::metrics::counter!("xtra_messages_handled_total", &labels),
+            latency: ::metrics::histogram!("xtra_message_latency_seconds", &labels),
+            handler_duration: ::metrics::histogram!("xtra_handler_duration_seconds", &labels),
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Pools of actors which share the work of handling messages.
+//!
+//! Unlike multiple actors running on the same [`Mailbox`](crate::Mailbox), which take messages out
+//! of one shared queue, every worker of an [`ActorPool`] has its own mailbox. A [`Router`] decides
+//! which worker each message is sent to. The following routers are provided:
+//!
+//! - [`RoundRobin`] sends messages to each worker in turn.
+//! - [`LeastLoaded`] sends messages to the worker with the fewest messages in its mailbox.
+//! - [`Random`] sends messages to a randomly chosen worker.
+//! - [`ConsistentHash`] sends all messages with the same [`RoutingKey`] to the same worker.
+//!
+//! ```rust
+//! # use xtra::prelude::*;
+//! use xtra::pool::{ActorPool, RoundRobin};
+//!
+//! # #[derive(xtra::Actor)]
+//! struct Worker(usize);
+//!
+//! struct WhoAmI;
+//!
+//! impl Handler<WhoAmI> for Worker {
+//!     type Return = usize;
+//!
+//!     async fn handle(&mut self, _: WhoAmI, _ctx: &mut Context<Self>) -> usize {
+//!         self.0
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let workers = (0..3).map(|id| xtra::spawn_tokio(Worker(id), Mailbox::unbounded()));
+//! let pool = ActorPool::new(RoundRobin::default(), workers);
+//!
+//! assert_eq!(pool.send(WhoAmI).await, Ok(0));
+//! assert_eq!(pool.send(WhoAmI).await, Ok(1));
+//! assert_eq!(pool.send(WhoAmI).await, Ok(2));
+//! assert_eq!(pool.send(WhoAmI).await, Ok(0));
+//! # })
+//! ```
+//!
+//! A worker which stops is not removed from the pool, so messages routed to it fail with
+//! [`Error::Disconnected`]. Run workers under a [`Supervisor`](crate::supervisor::Supervisor) to
+//! restart them on their existing mailbox instead.
+
+use std::collections::hash_map::{DefaultHasher, RandomState};
+use std::fmt;
+use std::future::Future;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::task::{Context, Poll};
+
+use futures_util::FutureExt;
+
+use crate::address::ActorJoinHandle;
+use crate::message_channel::{MessageChannelTrait, MessageChannelTraitWasm};
+use crate::refcount::{Either, RefCounter, Strong, Weak};
+use crate::send_future::{ActorErasedSending, ResolveToHandlerReturn};
+use crate::{
+    ActorNamedSending, Address, Error, Handler, MessageChannel, Receiver, SendFuture, TrySendError,
+    WasmRc, WasmSend,
+};
+
+/// Decides which worker of an [`ActorPool`] a message of type `M` is sent to.
+pub trait Router<M>: WasmSend + Sync + 'static {
+    /// Returns the index of the worker in `workers` which should handle the given message. The
+    /// index must be less than `workers.len()`, which is never zero.
+    fn route<A, Rc: RefCounter>(&self, message: &M, workers: &[Address<A, Rc>]) -> usize;
+}
+
+/// A [`Router`] which sends messages to each worker in turn.
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    next: AtomicUsize,
+}
+
+impl<M> Router<M> for RoundRobin {
+    fn route<A, Rc: RefCounter>(&self, _: &M, workers: &[Address<A, Rc>]) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed) % workers.len()
+    }
+}
+
+/// A [`Router`] which sends messages to the connected worker with the fewest messages in its
+/// mailbox, as per [`Address::len`]. Ties are broken in a round-robin fashion.
+#[derive(Debug, Default)]
+pub struct LeastLoaded {
+    next: AtomicUsize,
+}
+
+impl<M> Router<M> for LeastLoaded {
+    fn route<A, Rc: RefCounter>(&self, _: &M, workers: &[Address<A, Rc>]) -> usize {
+        let start = self.next.fetch_add(1, Ordering::Relaxed);
+
+        (0..workers.len())
+            .map(|offset| start.wrapping_add(offset) % workers.len())
+            .filter(|&index| workers[index].is_connected())
+            .min_by_key(|&index| workers[index].len())
+            .unwrap_or(start % workers.len())
+    }
+}
+
+/// A [`Router`] which sends messages to a randomly chosen worker.
+///
+/// The random numbers are not cryptographically secure.
+#[derive(Debug)]
+pub struct Random {
+    state: AtomicU64,
+}
+
+impl Random {
+    /// Create a [`Random`] router which chooses workers in a sequence determined by the given
+    /// seed. This is mostly useful for tests.
+    pub fn with_seed(seed: u64) -> Self {
+        Random {
+            // Xorshift never leaves the state zero, so make sure it is not zero to begin with.
+            state: AtomicU64::new(seed | 1),
+        }
+    }
+}
+
+impl Default for Random {
+    fn default() -> Self {
+        Self::with_seed(RandomState::new().build_hasher().finish())
+    }
+}
+
+impl<M> Router<M> for Random {
+    fn route<A, Rc: RefCounter>(&self, _: &M, workers: &[Address<A, Rc>]) -> usize {
+        let previous = self
+            .state
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |x| Some(xorshift(x)))
+            .unwrap_or_else(|x| x);
+
+        (xorshift(previous) % workers.len() as u64) as usize
+    }
+}
+
+fn xorshift(mut x: u64) -> u64 {
+    x ^= x << 13;
+    x ^= x >> 7;
+    x ^= x << 17;
+    x
+}
+
+/// Messages which can be routed through the [`ConsistentHash`] router.
+pub trait RoutingKey {
+    /// The type of the key.
+    type Key: Hash;
+
+    /// Returns the key of this message. Messages with equal keys are sent to the same worker.
+    fn routing_key(&self) -> Self::Key;
+}
+
+/// A [`Router`] which sends all messages with the same [`RoutingKey`] to the same worker.
+///
+/// Keys are mapped to workers through jump consistent hashing, so pools of the same size always
+/// map a key to the same worker within a process.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConsistentHash;
+
+impl<M: RoutingKey> Router<M> for ConsistentHash {
+    fn route<A, Rc: RefCounter>(&self, message: &M, workers: &[Address<A, Rc>]) -> usize {
+        let mut hasher = DefaultHasher::new();
+        message.routing_key().hash(&mut hasher);
+
+        jump_consistent_hash(hasher.finish(), workers.len())
+    }
+}
+
+/// Map the given key to one of `buckets` buckets, as described in "A Fast, Minimal Memory,
+/// Consistent Hash Algorithm" by Lamping and Veach.
+fn jump_consistent_hash(mut key: u64, buckets: usize) -> usize {
+    let mut bucket = 0;
+    let mut next = 0;
+
+    while next < buckets as u64 {
+        bucket = next;
+        key = key.wrapping_mul(2862933555777941757).wrapping_add(1);
+        next = ((bucket + 1) as f64 * ((1u64 << 31) as f64 / ((key >> 33) + 1) as f64)) as u64;
+    }
+
+    bucket as usize
+}
+
+/// A pool of actors of the same type, each with their own mailbox, which messages are distributed
+/// over by a [`Router`]. See the [module level documentation](self) for more.
+///
+/// Like [`Address`], an [`ActorPool`] is strong by default and can be downgraded to a weak one.
+/// Cloning an [`ActorPool`] returns another handle to the same pool, sharing the router's state.
+pub struct ActorPool<A, R = RoundRobin, Rc: RefCounter = Strong> {
+    workers: WasmRc<[Address<A, Rc>]>,
+    router: WasmRc<R>,
+}
+
+impl<A, R> ActorPool<A, R, Strong> {
+    /// Create a weak handle to the pool, which does not keep its workers alive.
+    pub fn downgrade(&self) -> ActorPool<A, R, Weak> {
+        self.map_workers(|worker| worker.downgrade())
+    }
+}
+
+impl<A, R, Rc: RefCounter> ActorPool<A, R, Rc> {
+    /// Create a pool of the actors behind the given addresses, which distributes messages using
+    /// the given [`Router`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if `workers` is empty.
+    pub fn new(router: R, workers: impl IntoIterator<Item = Address<A, Rc>>) -> Self {
+        let workers = workers.into_iter().collect::<WasmRc<[_]>>();
+        assert!(
+            !workers.is_empty(),
+            "An actor pool needs at least one worker"
+        );
+
+        ActorPool {
+            workers,
+            router: WasmRc::new(router),
+        }
+    }
+
+    /// The addresses of the workers in this pool.
+    pub fn workers(&self) -> &[Address<A, Rc>] {
+        &self.workers
+    }
+
+    /// Returns whether any of the workers in this pool is running and accepting messages.
+    pub fn is_connected(&self) -> bool {
+        self.workers.iter().any(Address::is_connected)
+    }
+
+    /// Returns the total number of messages in the mailboxes of all workers.
+    pub fn len(&self) -> usize {
+        self.workers.iter().map(Address::len).sum()
+    }
+
+    /// The total capacity of the mailboxes of all workers, or `None` if any of them is unbounded.
+    pub fn capacity(&self) -> Option<usize> {
+        self.workers.iter().map(Address::capacity).sum()
+    }
+
+    /// Returns whether the mailboxes of all workers are empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Send a message to the worker chosen by the pool's [`Router`]. This otherwise behaves like
+    /// [`Address::send`].
+    #[allow(clippy::type_complexity)]
+    pub fn send<M>(
+        &self,
+        message: M,
+    ) -> SendFuture<ActorNamedSending<A, Rc>, ResolveToHandlerReturn<<A as Handler<M>>::Return>>
+    where
+        M: WasmSend + 'static,
+        A: Handler<M>,
+        R: Router<M>,
+    {
+        self.route(&message).send(message)
+    }
+
+    /// Send a message to the worker chosen by the pool's [`Router`] without waiting. This
+    /// otherwise behaves like [`Address::try_send`].
+    pub fn try_send<M>(&self, message: M) -> Result<Receiver<A::Return>, TrySendError<M>>
+    where
+        M: WasmSend + 'static,
+        A: Handler<M>,
+        R: Router<M>,
+    {
+        self.route(&message).try_send(message)
+    }
+
+    /// Send a message to all actors of every worker in the pool, as per [`Address::broadcast`].
+    ///
+    /// The returned future resolves once the message was queued for every worker. It resolves to
+    /// [`Error::Disconnected`] only if none of the workers is connected.
+    pub fn broadcast<M>(&self, message: M) -> impl Future<Output = Result<(), Error>>
+    where
+        M: Clone + WasmSend + Sync + 'static,
+        A: Handler<M, Return = ()>,
+    {
+        let sends = self
+            .workers
+            .iter()
+            .map(|worker| worker.broadcast(message.clone()))
+            .collect::<Vec<_>>();
+
+        async move {
+            let results = futures_util::future::join_all(sends).await;
+
+            if results.iter().any(Result::is_ok) {
+                Ok(())
+            } else {
+                Err(Error::Disconnected)
+            }
+        }
+    }
+
+    /// Waits until all workers of this pool are disconnected. See [`Address::join`].
+    pub fn join(&self) -> PoolJoinHandle {
+        PoolJoinHandle(self.workers.iter().map(Address::join).collect())
+    }
+
+    /// Returns true if this and the other handle refer to the same pool, irrespective of their
+    /// reference count type.
+    pub fn same_pool<Rc2: RefCounter>(&self, other: &ActorPool<A, R, Rc2>) -> bool {
+        WasmRc::ptr_eq(&self.router, &other.router)
+    }
+
+    fn route<M>(&self, message: &M) -> &Address<A, Rc>
+    where
+        R: Router<M>,
+    {
+        &self.workers[self.router.route(message, &self.workers)]
+    }
+
+    fn map_workers<Rc2: RefCounter>(
+        &self,
+        f: impl FnMut(&Address<A, Rc>) -> Address<A, Rc2>,
+    ) -> ActorPool<A, R, Rc2> {
+        ActorPool {
+            workers: self.workers.iter().map(f).collect(),
+            router: self.router.clone(),
+        }
+    }
+}
+
+// Required because #[derive] adds A: Clone and R: Clone bounds
+impl<A, R, Rc: RefCounter> Clone for ActorPool<A, R, Rc> {
+    fn clone(&self) -> Self {
+        ActorPool {
+            workers: self.workers.clone(),
+            router: self.router.clone(),
+        }
+    }
+}
+
+impl<A, R, Rc: RefCounter> fmt::Debug for ActorPool<A, R, Rc>
+where
+    R: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ActorPool")
+            .field("workers", &self.workers)
+            .field("router", &self.router)
+            .finish()
+    }
+}
+
+/// A future which completes once all workers of an [`ActorPool`] have stopped. See
+/// [`ActorPool::join`].
+#[must_use = "Futures do nothing unless polled"]
+pub struct PoolJoinHandle(Vec<ActorJoinHandle>);
+
+impl Future for PoolJoinHandle {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0
+            .retain_mut(|handle| handle.poll_unpin(cx).is_pending());
+
+        if self.0.is_empty() {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<A, M, R, Ret, Rc> From<ActorPool<A, R, Rc>> for MessageChannel<M, Ret, Rc>
+where
+    A: Handler<M, Return = Ret>,
+    M: WasmSend + 'static,
+    R: Router<M>,
+    Ret: WasmSend + 'static,
+    Rc: RefCounter + Into<Either>,
+{
+    fn from(pool: ActorPool<A, R, Rc>) -> Self {
+        MessageChannel::from_trait(pool)
+    }
+}
+
+impl<A, M, R, Rc> MessageChannelTrait<M, Rc> for ActorPool<A, R, Rc>
+where
+    A: Handler<M>,
+    M: WasmSend + 'static,
+    R: Router<M>,
+    Rc: RefCounter + Into<Either>,
+{
+    type Return = A::Return;
+
+    fn is_connected(&self) -> bool {
+        self.is_connected()
+    }
+
+    fn len(&self) -> usize {
+        self.len()
+    }
+
+    fn capacity(&self) -> Option<usize> {
+        self.capacity()
+    }
+
+    fn send(
+        &self,
+        message: M,
+    ) -> SendFuture<ActorErasedSending, ResolveToHandlerReturn<A::Return>> {
+        let worker = self.route(&message);
+
+        SendFuture::sending_erased(message, worker.0.clone())
+    }
+
+    fn try_send(&self, message: M) -> Result<Receiver<A::Return>, TrySendError<M>> {
+        self.try_send(message)
+    }
+
+    fn clone_channel(&self) -> Box<dyn MessageChannelTraitWasm<M, Rc, Self::Return> + 'static> {
+        Box::new(self.clone())
+    }
+
+    fn join(&self) -> ActorJoinHandle {
+        ActorJoinHandle::pool(self.join())
+    }
+
+    fn to_inner_ptr(&self) -> *const () {
+        WasmRc::as_ptr(&self.router) as *const ()
+    }
+
+    fn is_strong(&self) -> bool {
+        self.workers[0].0.is_strong()
+    }
+
+    fn to_weak(&self) -> Box<dyn MessageChannelTraitWasm<M, Weak, Self::Return> + 'static> {
+        Box::new(self.map_workers(|worker| Address(worker.0.to_tx_weak())))
+    }
+
+    fn sender_count(&self) -> usize {
+        self.workers
+            .iter()
+            .map(|worker| worker.0.sender_count())
+            .sum()
+    }
+
+    fn receiver_count(&self) -> usize {
+        self.workers
+            .iter()
+            .map(|worker| worker.0.receiver_count())
+            .sum()
+    }
+
+    fn actor_type(&self) -> &str {
+        std::any::type_name::<A>()
+    }
+
+    fn to_either(&self) -> Box<dyn MessageChannelTraitWasm<M, Either, Self::Return> + 'static> {
+        Box::new(self.map_workers(Address::as_either))
+    }
+
+    fn hash(&self, state: &mut dyn Hasher) {
+        state.write_usize(WasmRc::as_ptr(&self.router) as *const () as usize);
+        state.write_u8(self.is_strong() as u8);
+        let _ = state.finish();
+    }
+}
@@ -0,0 +1,310 @@
+//! Typed publish/subscribe topics.
+//!
+//! Unlike [`Address::broadcast`](crate::Address::broadcast), which reaches the actors sharing one
+//! mailbox, a [`Topic`] delivers every published message to any number of subscribers, each with
+//! its own mailbox. Subscribers may be of different actor types, as long as they handle the
+//! message type of the topic. They are subscribed through a [`MessageChannel`], of which the topic
+//! only keeps a weak reference: subscribers which stop are removed from the topic automatically.
+//!
+//! ```rust
+//! # use xtra::prelude::*;
+//! use xtra::pubsub::Topic;
+//!
+//! #[derive(Clone)]
+//! struct PriceChanged(u32);
+//!
+//! # #[derive(Default, xtra::Actor)]
+//! struct Ticker(Vec<u32>);
+//! # #[derive(Default, xtra::Actor)]
+//! struct Alerts(usize);
+//!
+//! impl Handler<PriceChanged> for Ticker {
+//!     type Return = ();
+//!
+//!     async fn handle(&mut self, price: PriceChanged, _ctx: &mut Context<Self>) {
+//!         self.0.push(price.0);
+//!     }
+//! }
+//!
+//! impl Handler<PriceChanged> for Alerts {
+//!     type Return = ();
+//!
+//!     async fn handle(&mut self, price: PriceChanged, _ctx: &mut Context<Self>) {
+//!         if price.0 > 100 {
+//!             self.0 += 1;
+//!         }
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let ticker = xtra::spawn_tokio(Ticker::default(), Mailbox::unbounded());
+//! let alerts = xtra::spawn_tokio(Alerts::default(), Mailbox::unbounded());
+//!
+//! let topic = Topic::new();
+//! topic.subscribe(ticker.clone());
+//! topic.subscribe(alerts.clone());
+//!
+//! assert_eq!(topic.publish(PriceChanged(120)).await, 2);
+//! # })
+//! ```
+
+use std::fmt;
+use std::sync::{Mutex, MutexGuard};
+#[cfg(feature = "timeout")]
+use std::time::Duration;
+
+#[cfg(feature = "timeout")]
+use futures_timer::Delay;
+use futures_util::future;
+#[cfg(feature = "timeout")]
+use futures_util::future::Either;
+use futures_util::FutureExt;
+
+use crate::refcount::Weak;
+use crate::{Error, MessageChannel, TrySendError, WasmRc, WasmSend};
+
+/// A topic which delivers published messages of type `M` to all of its subscribers. See the
+/// [module documentation](self) for details.
+///
+/// Cloning a [`Topic`] returns another handle to the same topic.
+pub struct Topic<M> {
+    subscribers: WasmRc<Mutex<Subscribers<M>>>,
+    backpressure: Backpressure,
+}
+
+struct Subscribers<M> {
+    next_id: u64,
+    list: Vec<Subscriber<M>>,
+}
+
+struct Subscriber<M> {
+    id: SubscriberId,
+    channel: MessageChannel<M, (), Weak>,
+    dropped: u64,
+}
+
+impl<M> Topic<M>
+where
+    M: Clone + WasmSend + 'static,
+{
+    /// Create a topic without any subscribers, which waits for space in the mailboxes of its
+    /// subscribers when publishing. See [`Backpressure::Wait`].
+    pub fn new() -> Self {
+        Self::with_backpressure(Backpressure::default())
+    }
+
+    /// Create a topic without any subscribers, which applies the given [`Backpressure`] when
+    /// publishing.
+    pub fn with_backpressure(backpressure: Backpressure) -> Self {
+        Topic {
+            subscribers: WasmRc::new(Mutex::new(Subscribers {
+                next_id: 0,
+                list: Vec::new(),
+            })),
+            backpressure,
+        }
+    }
+
+    /// Subscribe the given actor to this topic, returning an id to identify it by. The topic only
+    /// keeps a weak reference to the actor, which is removed once the actor stops.
+    ///
+    /// Subscribing the same actor twice delivers every message to it twice.
+    pub fn subscribe<Rc>(&self, subscriber: impl Into<MessageChannel<M, (), Rc>>) -> SubscriberId {
+        let channel = subscriber.into().as_either().downgrade();
+
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let id = SubscriberId(subscribers.next_id);
+        subscribers.next_id += 1;
+        subscribers.list.push(Subscriber {
+            id,
+            channel,
+            dropped: 0,
+        });
+
+        id
+    }
+
+    /// Unsubscribe the subscriber with the given id. Returns whether it was subscribed.
+    pub fn unsubscribe(&self, id: SubscriberId) -> bool {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let len = subscribers.list.len();
+        subscribers.list.retain(|subscriber| subscriber.id != id);
+
+        subscribers.list.len() != len
+    }
+
+    /// The number of subscribers which are still running.
+    pub fn subscriber_count(&self) -> usize {
+        self.prune().list.len()
+    }
+
+    /// Report how far each subscriber which is still running lags behind the topic.
+    pub fn lag(&self) -> Vec<SubscriberLag> {
+        self.prune()
+            .list
+            .iter()
+            .map(|subscriber| SubscriberLag {
+                subscriber: subscriber.id,
+                queued: subscriber.channel.len(),
+                dropped: subscriber.dropped,
+            })
+            .collect()
+    }
+
+    /// Publish the given message to all subscribers, applying the [`Backpressure`] of this topic.
+    /// Resolves to the number of subscribers the message was delivered to, i.e. queued for.
+    ///
+    /// Subscribers which stopped are removed from the topic. Subscribers which were skipped
+    /// because of the backpressure are recorded in their [`SubscriberLag::dropped`] count.
+    pub async fn publish(&self, message: M) -> usize {
+        let channels = self
+            .subscribers
+            .lock()
+            .unwrap()
+            .list
+            .iter()
+            .map(|subscriber| (subscriber.id, subscriber.channel.clone()))
+            .collect::<Vec<_>>();
+
+        let deliveries = channels.iter().map(|(id, channel)| {
+            deliver(channel, message.clone(), self.backpressure)
+                .map(move |delivery| (*id, delivery))
+        });
+        let deliveries = future::join_all(deliveries).await;
+
+        let mut subscribers = self.subscribers.lock().unwrap();
+        let mut delivered = 0;
+
+        for (id, delivery) in deliveries {
+            match delivery {
+                Delivery::Delivered => delivered += 1,
+                Delivery::Skipped => {
+                    if let Some(subscriber) = subscribers.list.iter_mut().find(|s| s.id == id) {
+                        subscriber.dropped += 1;
+                    }
+                }
+                Delivery::Disconnected => subscribers.list.retain(|s| s.id != id),
+            }
+        }
+
+        delivered
+    }
+
+    /// Remove all subscribers which stopped, returning the remaining ones.
+    fn prune(&self) -> MutexGuard<'_, Subscribers<M>> {
+        let mut subscribers = self.subscribers.lock().unwrap();
+        subscribers
+            .list
+            .retain(|subscriber| subscriber.channel.is_connected());
+
+        subscribers
+    }
+}
+
+impl<M> Default for Topic<M>
+where
+    M: Clone + WasmSend + 'static,
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M> Clone for Topic<M> {
+    fn clone(&self) -> Self {
+        Topic {
+            subscribers: self.subscribers.clone(),
+            backpressure: self.backpressure,
+        }
+    }
+}
+
+impl<M> fmt::Debug for Topic<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let subscribers = self
+            .subscribers
+            .lock()
+            .map_or(0, |subscribers| subscribers.list.len());
+
+        f.debug_struct("Topic")
+            .field("message", &std::any::type_name::<M>())
+            .field("subscribers", &subscribers)
+            .field("backpressure", &self.backpressure)
+            .finish()
+    }
+}
+
+/// What [`Topic::publish`] does if the mailbox of a subscriber is full.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Backpressure {
+    /// Wait until there is space in the mailboxes of all subscribers. The slowest subscriber
+    /// decides how fast messages can be published.
+    #[default]
+    Wait,
+    /// Wait at most the given duration for space in the mailbox of each subscriber, skipping the
+    /// subscribers which are still full. Messages which were delivered are handled however long
+    /// the subscriber takes to get to them.
+    #[cfg(feature = "timeout")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "timeout")))]
+    WaitFor(Duration),
+    /// Skip subscribers whose mailbox is full without waiting.
+    Skip,
+}
+
+/// Identifies a subscriber of a [`Topic`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SubscriberId(u64);
+
+/// How far a subscriber of a [`Topic`] lags behind. See [`Topic::lag`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SubscriberLag {
+    /// The subscriber this report is about.
+    pub subscriber: SubscriberId,
+    /// The number of messages queued in the mailbox of the subscriber, including messages which
+    /// were not published through this topic.
+    pub queued: usize,
+    /// The number of published messages which were not delivered to the subscriber because of the
+    /// [`Backpressure`] of the topic.
+    pub dropped: u64,
+}
+
+/// The outcome of delivering a published message to a single subscriber.
+enum Delivery {
+    Delivered,
+    Skipped,
+    Disconnected,
+}
+
+/// Deliver the given message to a single subscriber, applying the given backpressure.
+async fn deliver<M>(
+    channel: &MessageChannel<M, (), Weak>,
+    message: M,
+    backpressure: Backpressure,
+) -> Delivery
+where
+    M: WasmSend + 'static,
+{
+    let result = match backpressure {
+        Backpressure::Wait => channel.send(message).detach().await.map(drop),
+        // Only waiting for space is bounded, not handling the message once it is queued.
+        #[cfg(feature = "timeout")]
+        Backpressure::WaitFor(duration) => {
+            match future::select(channel.send(message).detach(), Delay::new(duration)).await {
+                Either::Left((result, _)) => result.map(drop),
+                Either::Right(_) => return Delivery::Skipped,
+            }
+        }
+        Backpressure::Skip => match channel.try_send(message) {
+            Ok(_) => Ok(()),
+            Err(TrySendError::Full(_)) => return Delivery::Skipped,
+            Err(TrySendError::Disconnected(_)) => Err(Error::Disconnected),
+        },
+    };
+
+    match result {
+        Ok(()) => Delivery::Delivered,
+        Err(Error::Disconnected) => Delivery::Disconnected,
+        Err(_) => Delivery::Skipped,
+    }
+}
@@ -0,0 +1,299 @@
+//! A registry of actors by name, so that loosely coupled parts of an application can discover each
+//! other at runtime without passing [`Address`]es around.
+//!
+//! Actors are registered under a key, which is a [`String`] by default. The registry only keeps
+//! [`Weak`] references to registered actors, so it never keeps an actor alive. An actor is
+//! deregistered automatically once it stops, i.e. when [`Address::join`] resolves.
+//!
+//! ```rust
+//! # use xtra::prelude::*;
+//! # use xtra::registry::Registry;
+//! # #[derive(Default)]
+//! # struct Counter(usize);
+//! # impl Actor for Counter { type Stop = (); async fn stopped(self) {} }
+//! struct Get;
+//!
+//! impl Handler<Get> for Counter {
+//!     type Return = usize;
+//!
+//!     async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+//!         self.0
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let registry = Registry::new();
+//! let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+//!
+//! registry.register("counter", &address).unwrap().expose::<Get>();
+//!
+//! let counter = registry.lookup::<Counter, _>("counter").unwrap();
+//! assert_eq!(counter.send(Get).await, Ok(0));
+//!
+//! let get = registry.lookup_channel::<Get, usize, _>("counter").unwrap();
+//! assert_eq!(get.send(Get).await, Ok(0));
+//! # })
+//! ```
+
+use std::any::{Any, TypeId};
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::Hash;
+use std::marker::PhantomData;
+use std::sync::{Mutex, MutexGuard};
+
+use futures_util::FutureExt;
+
+use crate::address::ActorJoinHandle;
+use crate::refcount::{RefCounter, Weak};
+use crate::{Address, Handler, MessageChannel, WasmRc, WasmSend, WeakAddress};
+
+/// A registry of actors by key. See the [module level documentation](self) for more.
+///
+/// Cloning a [`Registry`] returns another handle to the same registry.
+pub struct Registry<K = String> {
+    entries: WasmRc<Mutex<HashMap<K, Entry>>>,
+}
+
+impl Registry {
+    /// Create a new, empty [`Registry`] with [`String`] keys.
+    ///
+    /// Use [`Registry::default`] to create a registry with another type of key.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<K> Registry<K>
+where
+    K: Hash + Eq + Clone,
+{
+    /// Register the actor behind the given [`Address`] under the given key.
+    ///
+    /// The actor can then be looked up through [`Registry::lookup`]. To also make it discoverable
+    /// through [`Registry::lookup_channel`], use [`Registration::expose`] on the returned value.
+    ///
+    /// Returns an error if another actor which is still running is registered under this key.
+    pub fn register<A, Rc>(
+        &self,
+        key: impl Into<K>,
+        address: &Address<A, Rc>,
+    ) -> Result<Registration<'_, K, A>, AlreadyRegistered>
+    where
+        A: 'static,
+        Rc: RefCounter,
+    {
+        let key = key.into();
+        let mut entries = self.lock();
+
+        entries.retain(|_, entry| entry.is_running());
+
+        if entries.contains_key(&key) {
+            return Err(AlreadyRegistered);
+        }
+
+        let address = Address(address.0.to_tx_weak());
+        entries.insert(
+            key.clone(),
+            Entry {
+                join: address.join(),
+                address: Box::new(address),
+                channels: HashMap::new(),
+            },
+        );
+
+        Ok(Registration {
+            registry: self,
+            key,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Look up the actor registered under the given key.
+    ///
+    /// Returns `None` if no actor is registered under this key, if it has stopped, or if it is not
+    /// of type `A`.
+    pub fn lookup<A, Q>(&self, key: &Q) -> Option<WeakAddress<A>>
+    where
+        A: 'static,
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.with_entry(key, |entry| {
+            (*entry.address)
+                .as_any()
+                .downcast_ref::<WeakAddress<A>>()
+                .cloned()
+        })
+    }
+
+    /// Look up a [`MessageChannel`] to the actor registered under the given key.
+    ///
+    /// Returns `None` if no actor is registered under this key, if it has stopped, or if it did
+    /// not [`expose`](Registration::expose) a channel for `M` with return type `R`.
+    pub fn lookup_channel<M, R, Q>(&self, key: &Q) -> Option<MessageChannel<M, R, Weak>>
+    where
+        M: WasmSend + 'static,
+        R: WasmSend + 'static,
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.with_entry(key, |entry| {
+            (*entry
+                .channels
+                .get(&TypeId::of::<MessageChannel<M, R, Weak>>())?)
+            .as_any()
+            .downcast_ref::<MessageChannel<M, R, Weak>>()
+            .cloned()
+        })
+    }
+
+    /// Remove the actor registered under the given key, returning whether there was one.
+    pub fn deregister<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.lock()
+            .remove(key)
+            .map_or(false, |mut entry| entry.is_running())
+    }
+
+    /// Returns whether an actor which is still running is registered under the given key.
+    pub fn contains<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.with_entry(key, |_| Some(())).is_some()
+    }
+
+    /// Run the given function on the entry under the given key, deregistering it if its actor has
+    /// stopped.
+    fn with_entry<Q, T>(&self, key: &Q, f: impl FnOnce(&Entry) -> Option<T>) -> Option<T>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut entries = self.lock();
+        let entry = entries.get_mut(key)?;
+
+        if !entry.is_running() {
+            entries.remove(key);
+            return None;
+        }
+
+        f(entry)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, HashMap<K, Entry>> {
+        // The lock is never held while running user code, so it cannot be poisoned.
+        self.entries.lock().unwrap()
+    }
+}
+
+impl<K> Default for Registry<K> {
+    fn default() -> Self {
+        Self {
+            entries: WasmRc::new(Mutex::new(HashMap::new())),
+        }
+    }
+}
+
+impl<K> Clone for Registry<K> {
+    fn clone(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+impl<K> fmt::Debug for Registry<K>
+where
+    K: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.entries.lock() {
+            Ok(entries) => f.debug_set().entries(entries.keys()).finish(),
+            Err(_) => f.write_str("Registry { <poisoned> }"),
+        }
+    }
+}
+
+/// A registration of an actor in a [`Registry`], returned from [`Registry::register`].
+pub struct Registration<'a, K, A> {
+    registry: &'a Registry<K>,
+    key: K,
+    phantom: PhantomData<for<'b> fn(&'b A)>,
+}
+
+impl<'a, K, A> Registration<'a, K, A>
+where
+    K: Hash + Eq + Clone,
+    A: 'static,
+{
+    /// Allow looking up a [`MessageChannel`] for messages of type `M` to this actor through
+    /// [`Registry::lookup_channel`].
+    pub fn expose<M>(self) -> Self
+    where
+        A: Handler<M>,
+        M: WasmSend + 'static,
+    {
+        let mut entries = self.registry.lock();
+
+        if let Some(entry) = entries.get_mut(&self.key) {
+            let channel = (*entry.address)
+                .as_any()
+                .downcast_ref::<WeakAddress<A>>()
+                .map(|address| MessageChannel::<M, A::Return, Weak>::new(address.clone()));
+
+            if let Some(channel) = channel {
+                let type_id = TypeId::of::<MessageChannel<M, A::Return, Weak>>();
+                entry.channels.insert(type_id, Box::new(channel));
+            }
+        }
+
+        drop(entries);
+        self
+    }
+}
+
+/// The error returned from [`Registry::register`] if an actor is already registered under the
+/// given key.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AlreadyRegistered;
+
+impl fmt::Display for AlreadyRegistered {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("An actor is already registered under this key")
+    }
+}
+
+impl std::error::Error for AlreadyRegistered {}
+
+struct Entry {
+    /// The [`WeakAddress`] of the registered actor.
+    address: Box<dyn Erased>,
+    /// The exposed [`MessageChannel`]s of the registered actor, by their type.
+    channels: HashMap<TypeId, Box<dyn Erased>>,
+    join: ActorJoinHandle,
+}
+
+impl Entry {
+    fn is_running(&mut self) -> bool {
+        (&mut self.join).now_or_never().is_none()
+    }
+}
+
+/// Helper trait because Rust does not allow to `+` non-auto traits in trait objects.
+trait Erased: WasmSend {
+    fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: Any + WasmSend> Erased for T {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
@@ -0,0 +1,649 @@
+//! Actors which are reachable over a byte-stream transport, such as a TCP connection.
+//!
+//! The client side of a connection is a [`RemoteAddress`], which sends messages much like an
+//! [`Address`]: it returns a [`SendFuture`], so [`priority`](SendFuture::priority),
+//! [`timeout`](SendFuture::timeout), [`ttl`](SendFuture::ttl) and [`detach`](SendFuture::detach)
+//! work as usual. The time-to-live of a message starts once the server received it. The server
+//! side is a [`Server`], which forwards the messages it receives to a local [`Address`] and sends
+//! back the [`Handler::Return`] values.
+//!
+//! Messages and return values are serialized with [serde](https://serde.rs). Messages which can be
+//! sent to a remote actor must implement [`RemoteMessage`], and the server must be told which
+//! messages to accept through [`Server::handle`]. Both sides of a connection should be built from
+//! the same definitions of the actor and its messages.
+//!
+//! Transports are any type implementing [`AsyncRead`] and [`AsyncWrite`] from the `futures` crate.
+//! Types implementing the traits of tokio can be adapted through `tokio_util::compat`.
+//!
+//! ```rust
+//! # use xtra::prelude::*;
+//! # use xtra::remote::{RemoteAddress, RemoteMessage, Server};
+//! # use serde::{Deserialize, Serialize};
+//! # #[derive(xtra::Actor)]
+//! # struct Greeter;
+//! #[derive(Serialize, Deserialize)]
+//! struct Greet(String);
+//!
+//! impl RemoteMessage for Greet {}
+//!
+//! impl Handler<Greet> for Greeter {
+//!     type Return = String;
+//!
+//!     async fn handle(&mut self, greet: Greet, _ctx: &mut Context<Self>) -> String {
+//!         format!("Hello, {}!", greet.0)
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! # use tokio_util::compat::TokioAsyncReadCompatExt;
+//! let (client_io, server_io) = tokio::io::duplex(1024);
+//!
+//! let server = Server::new(xtra::spawn_tokio(Greeter, Mailbox::unbounded())).handle::<Greet>();
+//! tokio::spawn(async move { server.serve(server_io.compat()).await });
+//!
+//! let (address, connection) = RemoteAddress::<Greeter>::connect(client_io.compat());
+//! tokio::spawn(connection);
+//!
+//! assert_eq!(address.send(Greet("world".to_owned())).await.unwrap(), "Hello, world!");
+//! # })
+//! ```
+//!
+//! A peer sending malformed data, or a frame larger than [`MAX_FRAME_LEN`], closes the
+//! connection. A message or return value which cannot be serialized, or which is longer than
+//! [`MAX_FRAME_LEN`] once serialized, is not sent and its [`SendFuture`] resolves to
+//! [`Error::Serialization`] instead.
+
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::future::Future;
+use std::io;
+use std::marker::PhantomData;
+use std::pin::{pin, Pin};
+use std::sync::{Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use bincode::Options;
+use futures_core::FusedFuture;
+use futures_util::future::{self, Either, FutureExt};
+use futures_util::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use futures_util::StreamExt;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::send_future::private::{SetDeadline, SetPriority, SetTtl};
+use crate::send_future::ResolveToHandlerReturn;
+use crate::{
+    ActorErasedSending, Address, Error, Handler, SendFuture, WasmBoxFuture, WasmRc, WasmSend,
+};
+
+/// The maximum number of requests a [`Server`] handles concurrently per connection. Once it is
+/// reached, no further requests are read from the connection until one of them completes.
+const MAX_IN_FLIGHT: usize = 1024;
+
+/// The maximum length in bytes of a single serialized request or response. A peer which sends a
+/// longer frame is disconnected with [`io::ErrorKind::InvalidData`], before the frame is read.
+pub const MAX_FRAME_LEN: usize = 16 * 1024 * 1024;
+
+/// A message which can be sent to an actor through a [`RemoteAddress`].
+pub trait RemoteMessage: Serialize + DeserializeOwned + WasmSend + 'static {
+    /// The name which identifies this type of message on the wire. It must be unique among the
+    /// messages handled by a [`Server`] and the same on both sides of a connection.
+    ///
+    /// Defaults to the type name of the message, which is only stable if both sides are built
+    /// with the same version of the compiler.
+    fn name() -> &'static str {
+        std::any::type_name::<Self>()
+    }
+}
+
+/// An address to an actor on the other side of a connection, served by a [`Server`].
+///
+/// A [`RemoteAddress`] is created through [`RemoteAddress::connect`]. Cloning it returns another
+/// address which sends messages over the same connection.
+pub struct RemoteAddress<A> {
+    shared: WasmRc<Mutex<Shared>>,
+    phantom: PhantomData<fn() -> A>,
+}
+
+impl<A> RemoteAddress<A> {
+    /// Create a [`RemoteAddress`] which sends messages over the given connection.
+    ///
+    /// This also returns the future which drives the connection. It must be spawned or polled for
+    /// messages to be sent. It resolves once all [`RemoteAddress`]es to the connection are dropped
+    /// and all responses have been received, or with an error if the connection fails.
+    pub fn connect<T>(io: T) -> (Self, impl Future<Output = io::Result<()>>)
+    where
+        T: AsyncRead + AsyncWrite + WasmSend + 'static,
+    {
+        let shared = WasmRc::new(Mutex::new(Shared {
+            outgoing: BinaryHeap::new(),
+            pending: HashMap::new(),
+            next_id: 0,
+            addresses: 1,
+            closed: false,
+            writer: None,
+        }));
+
+        let address = RemoteAddress {
+            shared: shared.clone(),
+            phantom: PhantomData,
+        };
+
+        (address, drive(io, shared))
+    }
+
+    /// Returns whether the connection to the remote actor is still open.
+    pub fn is_connected(&self) -> bool {
+        !lock(&self.shared).closed
+    }
+
+    /// Send a message to the remote actor. This behaves like [`Address::send`].
+    ///
+    /// If the connection is lost before the return value of the handler was received, the
+    /// [`SendFuture`] resolves to [`Error::Disconnected`]. If the message cannot be serialized, or
+    /// if it is longer than [`MAX_FRAME_LEN`] once serialized, it resolves to
+    /// [`Error::Serialization`].
+    pub fn send<M>(
+        &self,
+        message: M,
+    ) -> SendFuture<ActorErasedSending, ResolveToHandlerReturn<<A as Handler<M>>::Return>>
+    where
+        A: Handler<M>,
+        M: RemoteMessage,
+        A::Return: DeserializeOwned,
+    {
+        let (result_sender, receiver) = catty::oneshot();
+        let resolve = move |response: Result<Vec<u8>, Error>| -> io::Result<()> {
+            let result = match response {
+                Ok(bytes) => Ok(decode::<A::Return>(&bytes)?),
+                Err(error) => Err(error),
+            };
+            let _ = result_sender.send(result);
+
+            Ok(())
+        };
+
+        let request = Request {
+            message: M::name(),
+            payload: encode(&message).map_err(|_| Error::Serialization),
+            priority: 0,
+            deadline: None,
+            ttl: None,
+            resolve: Box::new(resolve),
+        };
+
+        SendFuture::sending_custom(
+            RemoteSending {
+                request: Some(request),
+                shared: self.shared.clone(),
+            },
+            receiver,
+        )
+    }
+}
+
+impl<A> Clone for RemoteAddress<A> {
+    fn clone(&self) -> Self {
+        lock(&self.shared).addresses += 1;
+
+        RemoteAddress {
+            shared: self.shared.clone(),
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<A> Drop for RemoteAddress<A> {
+    fn drop(&mut self) {
+        let mut shared = lock(&self.shared);
+        shared.addresses -= 1;
+        shared.wake_writer();
+    }
+}
+
+/// The server side of connections to an actor, which forwards the messages it receives to the
+/// actor's [`Address`] and sends back the return values.
+pub struct Server<A> {
+    address: Address<A>,
+    handlers: HashMap<&'static str, Dispatch<A>>,
+}
+
+type Dispatch<A> = fn(&Address<A>, WireRequest) -> io::Result<ResponseFuture>;
+
+type ResponseFuture = WasmBoxFuture<'static, WireResponse>;
+
+impl<A: 'static> Server<A> {
+    /// Create a [`Server`] which forwards messages to the given [`Address`].
+    ///
+    /// The server does not accept any messages until they are registered through
+    /// [`Server::handle`].
+    pub fn new(address: Address<A>) -> Self {
+        Server {
+            address,
+            handlers: HashMap::new(),
+        }
+    }
+
+    /// Accept messages of type `M` from clients.
+    pub fn handle<M>(mut self) -> Self
+    where
+        A: Handler<M>,
+        M: RemoteMessage,
+        A::Return: Serialize,
+    {
+        self.handlers.insert(M::name(), dispatch::<A, M>);
+        self
+    }
+
+    /// Serve the given connection until the client closes it.
+    ///
+    /// Requests from the connection are handled concurrently, honouring their priority and
+    /// timeout. The connection is closed with an error if the client sends a message which was
+    /// not registered through [`Server::handle`] or which cannot be deserialized. A return value
+    /// which cannot be serialized is answered with [`Error::Serialization`] instead.
+    pub async fn serve<T>(&self, io: T) -> io::Result<()>
+    where
+        T: AsyncRead + AsyncWrite,
+    {
+        let (reader, mut writer) = io.split();
+
+        let requests = futures_util::stream::unfold(reader, |mut reader| async move {
+            match read_frame::<_, WireRequest>(&mut reader).await {
+                Ok(Some(request)) => Some((Ok(request), reader)),
+                Ok(None) => None,
+                Err(error) => Some((Err(error), reader)),
+            }
+        });
+
+        let mut responses = requests
+            .map(
+                |request| match request.and_then(|request| self.dispatch(request)) {
+                    Ok(response) => Either::Left(response.map(Ok)),
+                    Err(error) => Either::Right(future::ready(Err(error))),
+                },
+            )
+            .buffer_unordered(MAX_IN_FLIGHT);
+
+        while let Some(response) = responses.next().await {
+            let response = response?;
+            let frame = match encode_frame(&response) {
+                Ok(frame) => frame,
+                // Only fail this request, such as if the return value is longer than a frame.
+                Err(_) => encode_frame(&WireResponse {
+                    id: response.id,
+                    result: Err(Error::Serialization),
+                })?,
+            };
+
+            write_frame(&mut writer, &frame).await?;
+        }
+
+        writer.close().await
+    }
+
+    fn dispatch(&self, request: WireRequest) -> io::Result<ResponseFuture> {
+        match self.handlers.get(request.message.as_str()) {
+            Some(dispatch) => dispatch(&self.address, request),
+            None => Err(invalid_data(format!(
+                "Message `{}` is not handled by this server",
+                request.message
+            ))),
+        }
+    }
+}
+
+/// Decode a request for a message of type `M` and send it to the actor.
+fn dispatch<A, M>(address: &Address<A>, request: WireRequest) -> io::Result<ResponseFuture>
+where
+    A: Handler<M>,
+    M: RemoteMessage,
+    A::Return: Serialize,
+{
+    let message = decode::<M>(&request.payload)?;
+    let send = address.send(message).priority(request.priority);
+    let send = match request.timeout {
+        Some(timeout) => send.timeout(timeout),
+        None => send,
+    };
+    let send = match request.ttl {
+        Some(ttl) => send.ttl(ttl),
+        None => send,
+    };
+
+    let id = request.id;
+
+    Ok(Box::pin(async move {
+        let result = match send.await {
+            Ok(value) => encode(&value).map_err(|_| Error::Serialization),
+            Err(error) => Err(error),
+        };
+
+        WireResponse { id, result }
+    }))
+}
+
+/// A request as it is sent over the wire.
+#[derive(Serialize, Deserialize)]
+struct WireRequest {
+    id: u64,
+    message: String,
+    priority: u32,
+    timeout: Option<Duration>,
+    ttl: Option<Duration>,
+    payload: Vec<u8>,
+}
+
+/// A response as it is sent over the wire.
+#[derive(Serialize, Deserialize)]
+struct WireResponse {
+    id: u64,
+    result: Result<Vec<u8>, Error>,
+}
+
+/// The state of a connection, shared between its [`RemoteAddress`]es and the future driving it.
+struct Shared {
+    /// Requests which are waiting to be written to the connection, by priority.
+    outgoing: BinaryHeap<Outgoing>,
+    /// Requests which are waiting for a response, by id.
+    pending: HashMap<u64, Resolve>,
+    next_id: u64,
+    /// The number of [`RemoteAddress`]es to this connection.
+    addresses: usize,
+    closed: bool,
+    writer: Option<Waker>,
+}
+
+impl Shared {
+    fn wake_writer(&mut self) {
+        if let Some(waker) = self.writer.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Resolves the [`SendFuture`] of a request with the response of the server. Fails if the
+/// response cannot be deserialized.
+type Resolve = Box<dyn ResolveFn>;
+
+trait ResolveFn: FnOnce(Result<Vec<u8>, Error>) -> io::Result<()> + WasmSend {}
+impl<F: FnOnce(Result<Vec<u8>, Error>) -> io::Result<()> + WasmSend> ResolveFn for F {}
+
+/// A request which is queued to be written to the connection.
+struct Outgoing {
+    id: u64,
+    priority: u32,
+    deadline: Option<Instant>,
+    frame: Vec<u8>,
+}
+
+impl PartialEq for Outgoing {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Outgoing {}
+
+impl PartialOrd for Outgoing {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Outgoing {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Higher priorities first, then in order of sending.
+        self.priority
+            .cmp(&other.priority)
+            .then_with(|| other.id.cmp(&self.id))
+    }
+}
+
+/// A request which has not been queued yet.
+struct Request {
+    message: &'static str,
+    /// The serialized message, or [`Error::Serialization`] if it could not be serialized.
+    payload: Result<Vec<u8>, Error>,
+    priority: u32,
+    deadline: Option<Instant>,
+    ttl: Option<Duration>,
+    resolve: Resolve,
+}
+
+/// "Sending" state of the [`SendFuture`]s of a [`RemoteAddress`].
+struct RemoteSending {
+    request: Option<Request>,
+    shared: WasmRc<Mutex<Shared>>,
+}
+
+impl Future for RemoteSending {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let request = this.request.take().expect("polled after completion");
+        let mut shared = lock(&this.shared);
+
+        if shared.closed {
+            return Poll::Ready(Err(Error::Disconnected));
+        }
+
+        let id = shared.next_id;
+
+        let frame = request.payload.and_then(|payload| {
+            encode_frame(&WireRequest {
+                id,
+                message: request.message.to_owned(),
+                priority: request.priority,
+                timeout: request
+                    .deadline
+                    .map(|deadline| deadline.saturating_duration_since(Instant::now())),
+                ttl: request.ttl,
+                payload,
+            })
+            .map_err(|_| Error::Serialization)
+        });
+        let frame = match frame {
+            Ok(frame) => frame,
+            Err(error) => return Poll::Ready(Err(error)),
+        };
+
+        shared.next_id += 1;
+
+        shared.pending.insert(id, request.resolve);
+        shared.outgoing.push(Outgoing {
+            id,
+            priority: request.priority,
+            deadline: request.deadline,
+            frame,
+        });
+        shared.wake_writer();
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl FusedFuture for RemoteSending {
+    fn is_terminated(&self) -> bool {
+        self.request.is_none()
+    }
+}
+
+impl SetPriority for RemoteSending {
+    fn set_priority(&mut self, priority: u32) {
+        match self.request.as_mut() {
+            Some(request) => request.priority = priority,
+            None => panic!("Cannot set priority after first poll"),
+        }
+    }
+}
+
+impl SetDeadline for RemoteSending {
+    fn set_deadline(&mut self, deadline: Instant) {
+        match self.request.as_mut() {
+            Some(request) => request.deadline = Some(deadline),
+            None => panic!("Cannot set deadline after first poll"),
+        }
+    }
+}
+
+impl SetTtl for RemoteSending {
+    fn set_ttl(&mut self, ttl: Duration) {
+        match self.request.as_mut() {
+            Some(request) => request.ttl = Some(ttl),
+            None => panic!("Cannot set time-to-live after first poll"),
+        }
+    }
+}
+
+/// Drive the client side of a connection, writing queued requests and resolving them with the
+/// responses read from it.
+async fn drive<T>(io: T, shared: WasmRc<Mutex<Shared>>) -> io::Result<()>
+where
+    T: AsyncRead + AsyncWrite,
+{
+    let (mut reader, mut writer) = io.split();
+
+    let reading = async {
+        while let Some(response) = read_frame::<_, WireResponse>(&mut reader).await? {
+            let resolve = {
+                let mut shared = lock(&shared);
+                let resolve = shared.pending.remove(&response.id);
+                shared.wake_writer(); // The writer finishes once no responses are pending.
+                resolve
+            };
+
+            if let Some(resolve) = resolve {
+                resolve(response.result)?;
+            }
+        }
+
+        io::Result::Ok(())
+    };
+
+    let writing = async {
+        while let Some(frame) = future::poll_fn(|cx| next_outgoing(&shared, cx)).await {
+            write_frame(&mut writer, &frame).await?;
+        }
+
+        writer.close().await
+    };
+
+    let result = match future::select(pin!(reading), pin!(writing)).await {
+        Either::Left((result, _)) | Either::Right((result, _)) => result,
+    };
+
+    let pending = {
+        let mut shared = lock(&shared);
+        shared.closed = true;
+        shared.outgoing.clear();
+        std::mem::take(&mut shared.pending)
+    };
+
+    for resolve in pending.into_values() {
+        let _ = resolve(Err(Error::Disconnected));
+    }
+
+    result
+}
+
+/// Take the next request to be written to the connection, or `None` once all [`RemoteAddress`]es
+/// are dropped and no responses are pending anymore.
+fn next_outgoing(shared: &Mutex<Shared>, cx: &mut Context<'_>) -> Poll<Option<Vec<u8>>> {
+    let mut shared = lock(shared);
+
+    while let Some(outgoing) = shared.outgoing.pop() {
+        if outgoing
+            .deadline
+            .map_or(true, |deadline| deadline > Instant::now())
+        {
+            return Poll::Ready(Some(outgoing.frame));
+        }
+
+        // The request timed out before it was written, so its `SendFuture` has resolved already.
+        shared.pending.remove(&outgoing.id);
+    }
+
+    if shared.addresses == 0 && shared.pending.is_empty() {
+        return Poll::Ready(None);
+    }
+
+    shared.writer = Some(cx.waker().clone());
+    Poll::Pending
+}
+
+fn lock(shared: &Mutex<Shared>) -> MutexGuard<'_, Shared> {
+    // The lock is never held while running user code, so it cannot be poisoned.
+    shared.lock().unwrap()
+}
+
+async fn read_frame<R, T>(reader: &mut R) -> io::Result<Option<T>>
+where
+    R: AsyncRead + Unpin,
+    T: DeserializeOwned,
+{
+    let mut len = [0; 4];
+
+    match reader.read_exact(&mut len).await {
+        Ok(()) => {}
+        Err(error) if error.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(error) => return Err(error),
+    }
+
+    let len = u32::from_le_bytes(len) as usize;
+
+    if len > MAX_FRAME_LEN {
+        return Err(invalid_data(format!(
+            "frame of {len} bytes exceeds the maximum of {MAX_FRAME_LEN} bytes"
+        )));
+    }
+
+    let mut frame = vec![0; len];
+    reader.read_exact(&mut frame).await?;
+
+    decode(&frame).map(Some)
+}
+
+async fn write_frame<W>(writer: &mut W, frame: &[u8]) -> io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer.write_all(frame).await?;
+    writer.flush().await
+}
+
+/// Serialize the given value, prefixed with its length.
+fn encode_frame<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    let payload = encode(value)?;
+    let len = u32::try_from(payload.len()).map_err(invalid_data)?;
+
+    let mut frame = Vec::with_capacity(4 + payload.len());
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.extend_from_slice(&payload);
+
+    Ok(frame)
+}
+
+fn encode<T: Serialize>(value: &T) -> io::Result<Vec<u8>> {
+    options().serialize(value).map_err(invalid_data)
+}
+
+fn decode<T: DeserializeOwned>(bytes: &[u8]) -> io::Result<T> {
+    options().deserialize(bytes).map_err(invalid_data)
+}
+
+/// The encoding of [`bincode::serialize`], which also bounds the memory allocated while decoding
+/// lengths read from the wire.
+fn options() -> impl Options {
+    bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_FRAME_LEN as u64)
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,614 @@
+//! Retries and circuit breaking for requests to actors.
+//!
+//! A [`Resilient`] wrapper around an [`Address`] or a [`MessageChannel`] sends messages much like
+//! the wrapped value: it returns a [`SendFuture`], so [`priority`](SendFuture::priority),
+//! [`timeout`](SendFuture::timeout) and [`ttl`](SendFuture::ttl) work as usual. On top of that, it
+//! applies the following policies to every request:
+//!
+//! - A [`RetryPolicy`] resends the message if an attempt failed with an error that is worth
+//!   retrying, waiting between attempts as configured through [`Backoff`]. By default, only
+//!   [transient](is_transient) errors are retried, so that a stopped actor which fails with
+//!   [`Error::Disconnected`] is not retried in vain.
+//! - A [`CircuitBreaker`] stops sending messages to an actor which keeps failing, resolving to
+//!   [`Error::CircuitOpen`] right away instead. After a while, it lets a single trial request
+//!   through and closes again once that request succeeds. It can be shared between wrappers to
+//!   guard the same actor.
+//!
+//! As a message may be sent more than once, it must implement [`Clone`].
+//!
+//! ```rust
+//! # use std::time::Duration;
+//! # use xtra::prelude::*;
+//! use xtra::resilience::{Backoff, CircuitBreaker, Resilient, RetryPolicy};
+//!
+//! # #[derive(xtra::Actor)]
+//! # struct Greeter;
+//! #[derive(Clone)]
+//! struct Greet(&'static str);
+//!
+//! impl Handler<Greet> for Greeter {
+//!     type Return = String;
+//!
+//!     async fn handle(&mut self, greet: Greet, _ctx: &mut Context<Self>) -> String {
+//!         format!("Hello, {}!", greet.0)
+//!     }
+//! }
+//!
+//! # #[cfg(feature = "tokio")]
+//! # tokio::runtime::Runtime::new().unwrap().block_on(async {
+//! let address = xtra::spawn_tokio(Greeter, Mailbox::unbounded());
+//! let greeter = Resilient::new(address)
+//!     .retry(
+//!         RetryPolicy::new(3)
+//!             .attempt_timeout(Duration::from_secs(1))
+//!             .backoff(Backoff::Exponential {
+//!                 initial: Duration::from_millis(10),
+//!                 max: Duration::from_secs(1),
+//!             }),
+//!     )
+//!     .circuit_breaker(CircuitBreaker::new(5, Duration::from_secs(30)));
+//!
+//! assert_eq!(greeter.send(Greet("world")).await.unwrap(), "Hello, world!");
+//! # })
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_core::FusedFuture;
+use futures_timer::Delay;
+use futures_util::FutureExt;
+use web_time::Instant;
+
+use crate::refcount::RefCounter;
+use crate::send_future::private::{SetDeadline, SetPriority, SetTtl};
+use crate::send_future::{ActorErasedSending, ResolveToHandlerReturn};
+use crate::{Address, Error, Handler, MessageChannel, SendFuture, WasmBoxFuture, WasmRc, WasmSend};
+
+/// Wraps an [`Address`] or a [`MessageChannel`], retrying failed requests and guarding them with a
+/// circuit breaker. See the [module documentation](self) for details.
+///
+/// By default, requests are neither retried nor guarded by a circuit breaker.
+#[derive(Clone, Debug)]
+pub struct Resilient<T> {
+    inner: T,
+    retry: RetryPolicy,
+    breaker: Option<CircuitBreaker>,
+}
+
+impl<T> Resilient<T> {
+    /// Wrap the given [`Address`] or [`MessageChannel`].
+    pub fn new(inner: T) -> Self {
+        Resilient {
+            inner,
+            retry: RetryPolicy::default(),
+            breaker: None,
+        }
+    }
+
+    /// Retry failed requests according to the given policy.
+    pub fn retry(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Guard requests with the given circuit breaker. Every attempt of a request is recorded by
+    /// the circuit breaker, and no attempt is made while it is open.
+    pub fn circuit_breaker(mut self, breaker: CircuitBreaker) -> Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    /// The wrapped [`Address`] or [`MessageChannel`].
+    pub fn inner(&self) -> &T {
+        &self.inner
+    }
+
+    /// Construct a [`SendFuture`] which sends the given message through `send`, applying the
+    /// policies of this wrapper.
+    fn sending<M, R, F>(
+        &self,
+        message: M,
+        send: fn(&T, M) -> SendFuture<F, ResolveToHandlerReturn<R>>,
+    ) -> SendFuture<ActorErasedSending, ResolveToHandlerReturn<R>>
+    where
+        T: Clone + WasmSend + 'static,
+        M: Clone + WasmSend + 'static,
+        R: WasmSend + 'static,
+        F: Future<Output = Result<(), Error>>
+            + FusedFuture
+            + SetPriority
+            + SetDeadline
+            + SetTtl
+            + Unpin
+            + WasmSend
+            + 'static,
+    {
+        let (result_sender, receiver) = catty::oneshot();
+        let resilient = self.clone();
+
+        let start = move |options: Options| -> WasmBoxFuture<'static, ()> {
+            Box::pin(async move {
+                let result = resilient.run(message, options, send).await;
+                let _ = result_sender.send(result);
+            })
+        };
+
+        SendFuture::sending_custom(
+            Retrying {
+                start: Some(Box::new(start)),
+                options: Options::default(),
+                running: None,
+            },
+            receiver,
+        )
+    }
+
+    /// Send the given message until an attempt succeeds or the retry policy gives up.
+    async fn run<M, R, F>(
+        self,
+        message: M,
+        options: Options,
+        send: fn(&T, M) -> SendFuture<F, ResolveToHandlerReturn<R>>,
+    ) -> Result<R, Error>
+    where
+        M: Clone,
+        F: Future<Output = Result<(), Error>>
+            + FusedFuture
+            + SetPriority
+            + SetDeadline
+            + SetTtl
+            + Unpin,
+    {
+        let mut attempt = 1;
+
+        loop {
+            let permit = self.breaker.as_ref().map(CircuitBreaker::acquire);
+
+            let result = match permit.transpose() {
+                Ok(permit) => {
+                    let sending = self.attempt(message.clone(), options, send);
+                    let result = sending.await;
+
+                    if let Some(permit) = permit {
+                        permit.record(&result);
+                    }
+
+                    result
+                }
+                Err(error) => Err(error),
+            };
+
+            match result {
+                Err(error)
+                    if attempt < self.retry.max_attempts && (self.retry.retry_on)(&error) =>
+                {
+                    let delay = self.retry.backoff.delay(attempt);
+
+                    if !delay.is_zero() {
+                        Delay::new(delay).await;
+                    }
+
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Make a single attempt at sending the given message.
+    fn attempt<M, R, F>(
+        &self,
+        message: M,
+        options: Options,
+        send: fn(&T, M) -> SendFuture<F, ResolveToHandlerReturn<R>>,
+    ) -> SendFuture<F, ResolveToHandlerReturn<R>>
+    where
+        F: SetPriority + SetDeadline + SetTtl,
+    {
+        let mut sending = send(&self.inner, message).priority(options.priority);
+
+        let attempt_deadline = self
+            .retry
+            .attempt_timeout
+            .map(|timeout| Instant::now() + timeout);
+        let deadline = match (options.deadline, attempt_deadline) {
+            (Some(deadline), Some(attempt_deadline)) => Some(deadline.min(attempt_deadline)),
+            (deadline, attempt_deadline) => deadline.or(attempt_deadline),
+        };
+
+        if let Some(deadline) = deadline {
+            sending = sending.deadline(deadline);
+        }
+
+        if let Some(ttl) = options.ttl {
+            sending = sending.ttl(ttl);
+        }
+
+        sending
+    }
+}
+
+impl<A, Rc> Resilient<Address<A, Rc>>
+where
+    Rc: RefCounter,
+{
+    /// Send a message to the actor, applying the policies of this wrapper. See [`Address::send`].
+    ///
+    /// The returned [`SendFuture`] resolves to the result of the last attempt. Its
+    /// [`timeout`](SendFuture::timeout) bounds all attempts together, including the time spent
+    /// waiting between them. A [`detach`](SendFuture::detach)ed future only resolves once the last
+    /// attempt was made.
+    #[allow(clippy::type_complexity)]
+    pub fn send<M>(
+        &self,
+        message: M,
+    ) -> SendFuture<ActorErasedSending, ResolveToHandlerReturn<<A as Handler<M>>::Return>>
+    where
+        A: Handler<M>,
+        M: Clone + WasmSend + 'static,
+    {
+        self.sending(message, Address::send)
+    }
+}
+
+impl<M, R, Rc> Resilient<MessageChannel<M, R, Rc>>
+where
+    M: Clone + WasmSend + 'static,
+    R: WasmSend + 'static,
+    Rc: RefCounter,
+{
+    /// Send a message to the actor, applying the policies of this wrapper. See
+    /// [`Resilient::<Address<_>>::send`](Resilient::send) for details.
+    pub fn send(&self, message: M) -> SendFuture<ActorErasedSending, ResolveToHandlerReturn<R>> {
+        self.sending(message, MessageChannel::send)
+    }
+}
+
+/// Decides whether and when a failed request is retried.
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    backoff: Backoff,
+    attempt_timeout: Option<Duration>,
+    retry_on: fn(&Error) -> bool,
+}
+
+impl RetryPolicy {
+    /// Make at most the given number of attempts, including the first one. By default, attempts
+    /// are made right after each other and [transient](is_transient) errors are retried.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_attempts` is zero.
+    pub fn new(max_attempts: u32) -> Self {
+        assert!(max_attempts > 0, "at least one attempt must be made");
+
+        RetryPolicy {
+            max_attempts,
+            backoff: Backoff::None,
+            attempt_timeout: None,
+            retry_on: is_transient,
+        }
+    }
+
+    /// Wait between attempts as configured by the given [`Backoff`].
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Fail a single attempt with [`Error::Timeout`] if it took longer than the given duration.
+    /// As timeouts are transient, the request is then retried by default.
+    pub fn attempt_timeout(mut self, timeout: Duration) -> Self {
+        self.attempt_timeout = Some(timeout);
+        self
+    }
+
+    /// Retry failed attempts only if the given function returns `true` for their error.
+    ///
+    /// ```rust
+    /// # use xtra::Error;
+    /// # use xtra::resilience::RetryPolicy;
+    /// let policy = RetryPolicy::new(3).retry_on(|error| *error == Error::HandlerPanicked);
+    /// # drop(policy);
+    /// ```
+    pub fn retry_on(mut self, retry_on: fn(&Error) -> bool) -> Self {
+        self.retry_on = retry_on;
+        self
+    }
+}
+
+impl Default for RetryPolicy {
+    /// Make a single attempt, i.e. never retry.
+    fn default() -> Self {
+        RetryPolicy::new(1)
+    }
+}
+
+impl fmt::Debug for RetryPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RetryPolicy")
+            .field("max_attempts", &self.max_attempts)
+            .field("backoff", &self.backoff)
+            .field("attempt_timeout", &self.attempt_timeout)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Whether the given error is transient, i.e. whether a retry may succeed. These are:
+///
+/// - [`Error::Interrupted`], as the actor may have been restarted since.
+/// - [`Error::Timeout`], as the actor may have been busy.
+/// - [`Error::MailboxFull`], as there may be space in the mailbox by now.
+pub fn is_transient(error: &Error) -> bool {
+    matches!(
+        error,
+        Error::Interrupted | Error::Timeout | Error::MailboxFull
+    )
+}
+
+/// How long a [`RetryPolicy`] waits before retrying a failed attempt.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Backoff {
+    /// Retry right away.
+    None,
+    /// Wait the same duration before every retry.
+    Fixed(Duration),
+    /// Wait `initial` before the first retry and double the duration for every further retry, up
+    /// to `max`.
+    Exponential {
+        /// The duration to wait before the first retry.
+        initial: Duration,
+        /// The longest duration to wait before a retry.
+        max: Duration,
+    },
+}
+
+impl Backoff {
+    /// The duration to wait after the given failed attempt, counting from 1.
+    fn delay(&self, attempt: u32) -> Duration {
+        match *self {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential { initial, max } => 1u32
+                .checked_shl(attempt - 1)
+                .and_then(|factor| initial.checked_mul(factor))
+                .map_or(max, |delay| delay.min(max)),
+        }
+    }
+}
+
+/// A circuit breaker which stops requests to an actor that keeps failing.
+///
+/// The circuit breaker starts out [closed](CircuitState::Closed), letting all requests through.
+/// Once the given number of requests failed in a row, it [opens](CircuitState::Open): requests
+/// fail with [`Error::CircuitOpen`] without being sent. After the given reset timeout, it becomes
+/// [half-open](CircuitState::HalfOpen) and lets a single trial request through. If that request
+/// succeeds, the circuit breaker closes again. Otherwise, it opens for another reset timeout.
+///
+/// Requests count as failed if they resolve to [`Error::HandlerPanicked`], [`Error::Timeout`] or
+/// [`Error::Expired`]. Other errors, such as [`Error::Disconnected`], are not caused by the actor
+/// being unhealthy and are ignored.
+///
+/// Cloning a [`CircuitBreaker`] returns another handle to the same circuit breaker.
+#[derive(Clone)]
+pub struct CircuitBreaker(WasmRc<Breaker>);
+
+struct Breaker {
+    failure_threshold: u32,
+    reset_timeout: Duration,
+    state: Mutex<State>,
+}
+
+#[derive(Clone, Copy)]
+enum State {
+    Closed { failures: u32 },
+    Open { until: Instant },
+    HalfOpen { in_flight: bool },
+}
+
+impl CircuitBreaker {
+    /// Create a closed circuit breaker which opens after the given number of failed requests in a
+    /// row and stays open for the given reset timeout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `failure_threshold` is zero.
+    pub fn new(failure_threshold: u32, reset_timeout: Duration) -> Self {
+        assert!(failure_threshold > 0, "failure threshold must not be zero");
+
+        CircuitBreaker(WasmRc::new(Breaker {
+            failure_threshold,
+            reset_timeout,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }))
+    }
+
+    /// The current state of this circuit breaker.
+    pub fn state(&self) -> CircuitState {
+        match *self.0.state.lock().unwrap() {
+            State::Closed { .. } => CircuitState::Closed,
+            State::Open { until } if until > Instant::now() => CircuitState::Open,
+            State::Open { .. } | State::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+
+    /// Ask for permission to send a request, failing with [`Error::CircuitOpen`] if the circuit
+    /// breaker is open or its trial request is in flight.
+    fn acquire(&self) -> Result<Permit, Error> {
+        let mut state = self.0.state.lock().unwrap();
+
+        let trial = match *state {
+            State::Closed { .. } => false,
+            State::Open { until } if until > Instant::now() => return Err(Error::CircuitOpen),
+            State::Open { .. } | State::HalfOpen { in_flight: false } => {
+                *state = State::HalfOpen { in_flight: true };
+                true
+            }
+            State::HalfOpen { in_flight: true } => return Err(Error::CircuitOpen),
+        };
+
+        Ok(Permit {
+            breaker: self.clone(),
+            trial,
+            recorded: false,
+        })
+    }
+
+    /// Record whether a request succeeded.
+    fn record(&self, trial: bool, success: bool) {
+        let mut state = self.0.state.lock().unwrap();
+
+        *state = match (*state, trial) {
+            (State::Closed { .. }, false) if success => State::Closed { failures: 0 },
+            (State::Closed { failures }, false) if failures + 1 < self.0.failure_threshold => {
+                State::Closed {
+                    failures: failures + 1,
+                }
+            }
+            (State::HalfOpen { .. }, true) if success => State::Closed { failures: 0 },
+            (State::Closed { .. }, false) | (State::HalfOpen { .. }, true) => State::Open {
+                until: Instant::now() + self.0.reset_timeout,
+            },
+            // The request was let through before the state changed, so it says nothing about the
+            // current state.
+            (state, _) => state,
+        };
+    }
+}
+
+impl fmt::Debug for CircuitBreaker {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CircuitBreaker")
+            .field("failure_threshold", &self.0.failure_threshold)
+            .field("reset_timeout", &self.0.reset_timeout)
+            .field("state", &self.state())
+            .finish()
+    }
+}
+
+/// The state of a [`CircuitBreaker`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CircuitState {
+    /// Requests are let through.
+    Closed,
+    /// Requests fail with [`Error::CircuitOpen`] without being sent.
+    Open,
+    /// The next request is let through as a trial. Further requests fail with
+    /// [`Error::CircuitOpen`] until it resolved.
+    HalfOpen,
+}
+
+/// The permission of a [`CircuitBreaker`] to send a single request.
+struct Permit {
+    breaker: CircuitBreaker,
+    trial: bool,
+    recorded: bool,
+}
+
+impl Permit {
+    /// Record the result of the request with the circuit breaker. Errors which say nothing about
+    /// the health of the actor are not recorded.
+    fn record<R>(mut self, result: &Result<R, Error>) {
+        let success = match result {
+            Ok(_) => true,
+            Err(Error::HandlerPanicked | Error::Timeout | Error::Expired) => false,
+            Err(_) => return,
+        };
+
+        self.breaker.record(self.trial, success);
+        self.recorded = true;
+    }
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        if !self.trial || self.recorded {
+            return;
+        }
+
+        // Let another trial request through, as this one was cancelled or its result ignored.
+        if let Ok(mut state) = self.breaker.0.state.lock() {
+            if let State::HalfOpen { in_flight: true } = *state {
+                *state = State::HalfOpen { in_flight: false };
+            }
+        }
+    }
+}
+
+/// The options a [`SendFuture`] returned by [`Resilient`] applies to every attempt.
+#[derive(Clone, Copy, Default)]
+struct Options {
+    priority: u32,
+    deadline: Option<Instant>,
+    ttl: Option<Duration>,
+}
+
+/// "Sending" state of the [`SendFuture`]s of a [`Resilient`] wrapper, which makes all attempts of
+/// a request.
+struct Retrying {
+    start: Option<Box<dyn StartFn>>,
+    options: Options,
+    running: Option<WasmBoxFuture<'static, ()>>,
+}
+
+/// Starts an attempt of a request with the given options.
+trait StartFn: FnOnce(Options) -> WasmBoxFuture<'static, ()> + WasmSend {}
+impl<F: FnOnce(Options) -> WasmBoxFuture<'static, ()> + WasmSend> StartFn for F {}
+
+impl Retrying {
+    fn options(&mut self, setting: &str) -> &mut Options {
+        if self.start.is_none() {
+            panic!("Cannot set {} after first poll", setting);
+        }
+
+        &mut self.options
+    }
+}
+
+impl Future for Retrying {
+    type Output = Result<(), Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        if let Some(start) = this.start.take() {
+            this.running = Some(start(this.options));
+        }
+
+        let running = this.running.as_mut().expect("polled after completion");
+        futures_util::ready!(running.poll_unpin(cx));
+        this.running = None;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl FusedFuture for Retrying {
+    fn is_terminated(&self) -> bool {
+        self.start.is_none() && self.running.is_none()
+    }
+}
+
+impl SetPriority for Retrying {
+    fn set_priority(&mut self, priority: u32) {
+        self.options("priority").priority = priority;
+    }
+}
+
+impl SetDeadline for Retrying {
+    fn set_deadline(&mut self, deadline: Instant) {
+        self.options("deadline").deadline = Some(deadline);
+    }
+}
+
+impl SetTtl for Retrying {
+    fn set_ttl(&mut self, ttl: Duration) {
+        self.options("time-to-live").ttl = Some(ttl);
+    }
+}
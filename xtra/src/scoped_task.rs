@@ -2,10 +2,13 @@ use std::future::Future;
 use std::pin::Pin;
 use std::task::{Context, Poll};
 
-use futures_util::FutureExt;
+use futures_core::Stream;
+use futures_util::{FutureExt, StreamExt};
 
-use crate::address::{ActorJoinHandle, Address};
+use crate::address::{ActorJoinHandle, Address, WeakAddress};
 use crate::chan::RefCounter;
+use crate::refcount::Either;
+use crate::{Error, Handler, WasmBoxFuture, WasmSend};
 
 /// Scope a given task to the lifecycle of an actor - see [`ScopedTask`].
 pub fn scoped<A, Rc, F, R>(address: &Address<A, Rc>, task: F) -> ScopedTask<F>
@@ -51,3 +54,162 @@ where
         }
     }
 }
+
+/// Attach a stream to an actor, sending each of its items to the actor as a message. The returned
+/// task has to be polled, e.g. by spawning it, for the items to be sent.
+///
+/// The task only holds a weak address to the actor, so it does not keep the actor alive. It sends
+/// the next item once the actor has handled the previous one, which also means it waits for space
+/// in a bounded mailbox. It completes with `Some(())` once the stream is exhausted, or with `None`
+/// as soon as the actor stops.
+///
+/// The items are sent with the default priority unless configured otherwise through
+/// [`ScopedTask::priority`]. Through [`ScopedTask::notify_finished`], the actor can also be sent a
+/// [`StreamFinished`] message once the stream is exhausted.
+///
+/// ```rust
+/// # use xtra::prelude::*;
+/// use xtra::StreamFinished;
+///
+/// # #[derive(Default, xtra::Actor)]
+/// struct Summer {
+///     sum: u32,
+///     finished: bool,
+/// }
+///
+/// impl Handler<u32> for Summer {
+///     type Return = ();
+///
+///     async fn handle(&mut self, number: u32, _ctx: &mut Context<Self>) {
+///         self.sum += number;
+///     }
+/// }
+///
+/// impl Handler<StreamFinished> for Summer {
+///     type Return = ();
+///
+///     async fn handle(&mut self, _: StreamFinished, _ctx: &mut Context<Self>) {
+///         self.finished = true;
+///     }
+/// }
+///
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let address = xtra::spawn_tokio(Summer::default(), Mailbox::unbounded());
+/// let numbers = futures_util::stream::iter([1, 2, 3]);
+///
+/// let task = xtra::attach_stream(&address, numbers).notify_finished();
+/// assert_eq!(tokio::spawn(task).await.unwrap(), Some(()));
+/// # })
+/// ```
+pub fn attach_stream<A, Rc, S>(
+    address: &Address<A, Rc>,
+    stream: S,
+) -> ScopedTask<AttachStream<A, S>>
+where
+    Rc: RefCounter + Into<Either>,
+    S: Stream,
+    A: Handler<S::Item>,
+{
+    scoped(
+        address,
+        AttachStream {
+            address: address.as_either().downgrade(),
+            stream: Some(stream),
+            priority: 0,
+            finished: None,
+            task: None,
+        },
+    )
+}
+
+impl<A, S> ScopedTask<AttachStream<A, S>> {
+    /// Send the items of the stream with the given priority. See
+    /// [`SendFuture::priority`](crate::SendFuture::priority).
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.fut.priority = priority;
+        self
+    }
+
+    /// Send a [`StreamFinished`] message to the actor once the stream is exhausted. It is not sent
+    /// if the actor stops first.
+    pub fn notify_finished(mut self) -> Self
+    where
+        A: Handler<StreamFinished>,
+    {
+        self.fut.finished = Some(|address: WeakAddress<A>| -> WasmBoxFuture<'static, ()> {
+            Box::pin(async move {
+                let _ = address.send(StreamFinished).await;
+            })
+        });
+        self
+    }
+}
+
+/// The message sent to an actor once a stream attached to it through [`attach_stream`] is
+/// exhausted, if requested through [`ScopedTask::notify_finished`].
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct StreamFinished;
+
+/// Sends the items of a stream to an actor. See [`attach_stream`].
+#[must_use = "Futures do nothing unless polled"]
+pub struct AttachStream<A, S> {
+    address: WeakAddress<A>,
+    stream: Option<S>,
+    priority: u32,
+    finished: Option<fn(WeakAddress<A>) -> WasmBoxFuture<'static, ()>>,
+    task: Option<WasmBoxFuture<'static, ()>>,
+}
+
+// The stream is never pinned in place, it is moved into the task on the first poll.
+impl<A, S> Unpin for AttachStream<A, S> {}
+
+impl<A, S> Future for AttachStream<A, S>
+where
+    S: Stream + WasmSend + 'static,
+    S::Item: WasmSend + 'static,
+    A: Handler<S::Item>,
+{
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+
+        let task = match (&mut this.task, this.stream.take()) {
+            (Some(task), _) => task,
+            (task, Some(stream)) => task.insert(Box::pin(forward(
+                this.address.clone(),
+                stream,
+                this.priority,
+                this.finished,
+            ))),
+            (None, None) => return Poll::Ready(()),
+        };
+
+        task.poll_unpin(cx)
+    }
+}
+
+async fn forward<A, S>(
+    address: WeakAddress<A>,
+    stream: S,
+    priority: u32,
+    finished: Option<fn(WeakAddress<A>) -> WasmBoxFuture<'static, ()>>,
+) where
+    S: Stream,
+    A: Handler<S::Item>,
+{
+    let mut stream = std::pin::pin!(stream);
+
+    while let Some(item) = stream.next().await {
+        if let Err(Error::Disconnected | Error::Interrupted) =
+            address.send(item).priority(priority).await
+        {
+            return;
+        }
+    }
+
+    if let Some(finished) = finished {
+        finished(address).await;
+    }
+}
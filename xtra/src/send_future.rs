@@ -2,13 +2,17 @@ use std::future::Future;
 use std::mem;
 use std::pin::Pin;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 use futures_core::FusedFuture;
+#[cfg(feature = "timeout")]
+use futures_timer::Delay;
 use futures_util::FutureExt;
+use web_time::Instant;
 
 use crate::chan::{MailboxFull, MessageToAll, MessageToOne, RefCounter, WaitingSender};
-use crate::envelope::{BroadcastEnvelopeConcrete, ReturningEnvelope};
-use crate::{chan, Error, Handler, WasmRc, WasmSend};
+use crate::envelope::{BroadcastEnvelopeConcrete, CoalescingEnvelope, ReturningEnvelope};
+use crate::{blocking, chan, Coalesce, Error, Handler, WasmRc, WasmSend};
 
 /// A [`Future`] that represents the state of sending a message to an actor.
 ///
@@ -22,10 +26,13 @@ use crate::{chan, Error, Handler, WasmRc, WasmSend};
 ///
 /// In case an actor's mailbox is bounded, [`SendFuture`] will yield `Pending` until the message is queued successfully.
 /// This allows an actor to exercise backpressure on its users.
+///
+/// How long a [`SendFuture`] waits can be bounded through [`timeout`](SendFuture::timeout) or [`deadline`](SendFuture::deadline).
 #[must_use = "Futures do nothing unless polled"]
 pub struct SendFuture<F, S> {
   sending: F,
   state: S,
+  deadline: Option<Deadline>,
 }
 
 /// State-type for [`SendFuture`] to declare that it should resolve to the return value of the [`Handler`](crate::Handler).
@@ -50,6 +57,7 @@ where
     SendFuture {
       sending: self.sending,
       state: self.state.resolve_to_receiver(),
+      deadline: self.deadline,
     }
   }
 }
@@ -68,6 +76,93 @@ where
   }
 }
 
+#[cfg(feature = "timeout")]
+#[cfg_attr(docsrs, doc(cfg(feature = "timeout")))]
+impl<F, S> SendFuture<F, S>
+where
+  F: private::SetDeadline,
+{
+  /// Resolve to [`Error::Timeout`] if the message could not be sent and handled within the given
+  /// duration. See [`SendFuture::deadline`] for details.
+  ///
+  /// Panics if this future has already been polled.
+  pub fn timeout(self, duration: Duration) -> Self {
+    self.deadline(Instant::now() + duration)
+  }
+
+  /// Resolve to [`Error::Timeout`] if the message could not be sent and handled by the given point
+  /// in time.
+  ///
+  /// The deadline covers waiting for space in a full mailbox as well as waiting for the return
+  /// value of the [`Handler`]. If the deadline passes before the message was dispatched to the
+  /// actor, it is withdrawn from the mailbox and will not be handled. A message that is already
+  /// being handled is not interrupted. For [`detach`](SendFuture::detach)ed futures, the deadline
+  /// carries over to the returned [`Receiver`]. Broadcasts are only withdrawn while waiting for
+  /// space in the mailbox.
+  ///
+  /// Panics if this future has already been polled.
+  pub fn deadline(mut self, deadline: Instant) -> Self {
+    self.sending.set_deadline(deadline);
+    self.deadline = Some(Deadline::new(deadline));
+
+    self
+  }
+}
+
+impl<F, S> SendFuture<F, S>
+where
+  F: private::SetTtl,
+{
+  /// Discard the message if it was not dispatched to the actor within the given duration after
+  /// it was sent, resolving to [`Error::Expired`] instead of the return value of the [`Handler`].
+  ///
+  /// Unlike [`timeout`](SendFuture::timeout), the time-to-live only bounds how long the message
+  /// waits in the mailbox: once it is dispatched, the handler's result is awaited no matter how
+  /// long it takes. Expired messages are reported to the hook configured through
+  /// [`MailboxBuilder::on_expired`](crate::MailboxBuilder::on_expired).
+  ///
+  /// Panics if this future has already been polled.
+  pub fn ttl(mut self, ttl: Duration) -> Self {
+    self.sending.set_ttl(ttl);
+
+    self
+  }
+}
+
+impl<F, S> SendFuture<F, S>
+where
+  Self: Future,
+{
+  /// Block the current thread until this future resolves, for sending messages from synchronous
+  /// code such as threads that are not managed by an async runtime.
+  ///
+  /// # Panics
+  ///
+  /// Panics if called from within a [`Handler`], as blocking the thread there could deadlock the
+  /// actor. Use `.await` instead. Handlers of actors spawned through [`spawn_thread`](crate::spawn_thread)
+  /// may block their own thread.
+  pub fn wait(self) -> <Self as Future>::Output {
+    blocking::block_on(self)
+  }
+}
+
+impl<F, S> SendFuture<F, S>
+where
+  F: Future<Output = Result<(), Error>> + Unpin,
+{
+  /// Poll the sending of the message, resolving to [`Error::Timeout`] if the deadline passes first.
+  fn poll_sending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Error>> {
+    if let Poll::Ready(result) = self.sending.poll_unpin(cx) {
+      return Poll::Ready(result);
+    }
+
+    match self.deadline.as_mut() {
+      Some(deadline) => deadline.poll_unpin(cx).map(|()| Err(Error::Timeout)),
+      None => Poll::Pending,
+    }
+  }
+}
+
 /// "Sending" state of [`SendFuture`] for cases where the actor type is named and we sent a single message.
 #[must_use = "Futures do nothing unless polled"]
 pub struct ActorNamedSending<A, Rc: RefCounter>(Sending<A, MessageToOne<A>, Rc>);
@@ -101,6 +196,27 @@ where
         sender,
       }),
       state: ResolveToHandlerReturn::new(receiver),
+      deadline: None,
+    }
+  }
+
+  /// Construct a [`SendFuture`] for a message which is merged into a queued message with the same
+  /// key, if there is one. See [`Coalesce`].
+  pub(crate) fn sending_coalesced<M>(message: M, sender: chan::Ptr<A, Rc>) -> Self
+  where
+    A: Handler<M, Return = R>,
+    M: Coalesce + WasmSend + 'static,
+    R: Clone,
+  {
+    let (envelope, receiver) = CoalescingEnvelope::<A, M, R>::new(message, 0);
+
+    Self {
+      sending: ActorNamedSending(Sending::New {
+        msg: Box::new(envelope) as MessageToOne<A>,
+        sender,
+      }),
+      state: ResolveToHandlerReturn::new(receiver),
+      deadline: None,
     }
   }
 }
@@ -121,6 +237,24 @@ impl<R> SendFuture<ActorErasedSending, ResolveToHandlerReturn<R>> {
         sender,
       })),
       state: ResolveToHandlerReturn::new(receiver),
+      deadline: None,
+    }
+  }
+}
+
+#[cfg(feature = "timeout")]
+impl<R> SendFuture<ActorErasedSending, ResolveToHandlerReturn<R>> {
+  /// Construct a [`SendFuture`] which sends the message through the given state, for messages that
+  /// are not sent straight into a local mailbox, such as the ones of a [`RemoteAddress`](crate::remote::RemoteAddress)
+  /// or of a [`Resilient`](crate::resilience::Resilient) wrapper.
+  pub(crate) fn sending_custom<F>(sending: F, receiver: catty::Receiver<Result<R, Error>>) -> Self
+  where
+    F: private::ErasedSending,
+  {
+    Self {
+      sending: ActorErasedSending(Box::new(sending)),
+      state: ResolveToHandlerReturn::new(receiver),
+      deadline: None,
     }
   }
 }
@@ -142,6 +276,7 @@ where
         sender,
       }),
       state: Broadcast(()),
+      deadline: None,
     }
   }
 }
@@ -162,6 +297,7 @@ impl SendFuture<ActorErasedSending, Broadcast> {
         sender,
       })),
       state: Broadcast(()),
+      deadline: None,
     }
   }
 }
@@ -308,10 +444,11 @@ where
     let this = self.get_mut();
 
     if !this.sending.is_terminated() {
-      futures_util::ready!(this.sending.poll_unpin(ctx))?;
+      futures_util::ready!(this.poll_sending(ctx))?;
     }
 
-    let receiver = this.state.0.take().expect("polled after completion");
+    let mut receiver = this.state.0.take().expect("polled after completion");
+    receiver.deadline = this.deadline.take();
 
     Poll::Ready(Ok(receiver))
   }
@@ -327,7 +464,11 @@ where
     let this = self.get_mut();
 
     if !this.sending.is_terminated() {
-      futures_util::ready!(this.sending.poll_unpin(ctx))?;
+      futures_util::ready!(this.poll_sending(ctx))?;
+    }
+
+    if let Some(deadline) = this.deadline.take() {
+      this.state.0.deadline = Some(deadline);
     }
 
     this.state.0.poll_unpin(ctx)
@@ -340,27 +481,116 @@ where
   type Output = Result<(), Error>;
 
   fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
-    self.get_mut().sending.poll_unpin(ctx)
+    self.get_mut().poll_sending(ctx)
   }
 }
 
 /// A [`Future`] that resolves to the [`Return`](crate::Handler::Return) value of a [`Handler`](crate::Handler).
 ///
 /// In case the actor becomes disconnected during the execution of the handler, this future will resolve to [`Error::Interrupted`].
+/// If the handler panics, this future will resolve to [`Error::HandlerPanicked`].
+/// If the [`deadline`](SendFuture::deadline) of the request passes, this future will resolve to [`Error::Timeout`].
 #[must_use = "Futures do nothing unless polled"]
-pub struct Receiver<R>(catty::Receiver<R>);
+pub struct Receiver<R> {
+  receiver: catty::Receiver<Result<R, Error>>,
+  deadline: Option<Deadline>,
+}
+
+impl<R> Receiver<R> {
+  pub(crate) fn new(receiver: catty::Receiver<Result<R, Error>>) -> Self {
+    Receiver {
+      receiver,
+      deadline: None,
+    }
+  }
+
+  /// Block the current thread until the handler returned. See [`SendFuture::wait`].
+  ///
+  /// # Panics
+  ///
+  /// Panics if called from within a [`Handler`], unless the actor was spawned through
+  /// [`spawn_thread`](crate::spawn_thread).
+  pub fn wait(self) -> Result<R, Error> {
+    blocking::block_on(self)
+  }
+}
 
 impl<R> Future for Receiver<R> {
   type Output = Result<R, Error>;
 
   fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-    self.get_mut().0.poll_unpin(cx).map_err(|_| Error::Interrupted)
+    let this = self.get_mut();
+
+    match this.receiver.poll_unpin(cx) {
+      Poll::Ready(Ok(result)) => return Poll::Ready(result),
+      // The envelope is dropped without a result if it was withdrawn because of its deadline.
+      Poll::Ready(Err(_)) if this.deadline.as_ref().map_or(false, Deadline::has_passed) => {
+        return Poll::Ready(Err(Error::Timeout))
+      }
+      Poll::Ready(Err(_)) => return Poll::Ready(Err(Error::Interrupted)),
+      Poll::Pending => {}
+    }
+
+    match this.deadline.as_mut() {
+      Some(deadline) => deadline.poll_unpin(cx).map(|()| Err(Error::Timeout)),
+      None => Poll::Pending,
+    }
+  }
+}
+
+/// The point in time after which a [`SendFuture`] or [`Receiver`] resolves to [`Error::Timeout`].
+#[cfg(feature = "timeout")]
+struct Deadline {
+  at: Instant,
+  delay: Delay,
+}
+
+/// Without the `timeout` feature, requests never have a deadline.
+#[cfg(not(feature = "timeout"))]
+enum Deadline {}
+
+#[cfg(not(feature = "timeout"))]
+impl Deadline {
+  fn has_passed(&self) -> bool {
+    match *self {}
+  }
+}
+
+#[cfg(not(feature = "timeout"))]
+impl Future for Deadline {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Self::Output> {
+    match *self.get_mut() {}
+  }
+}
+
+#[cfg(feature = "timeout")]
+impl Deadline {
+  fn new(at: Instant) -> Self {
+    Self {
+      at,
+      delay: Delay::new(at.saturating_duration_since(Instant::now())),
+    }
+  }
+
+  fn has_passed(&self) -> bool {
+    self.at <= Instant::now()
+  }
+}
+
+#[cfg(feature = "timeout")]
+impl Future for Deadline {
+  type Output = ();
+
+  fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+    self.get_mut().delay.poll_unpin(cx)
   }
 }
 
 impl<R> ResolveToHandlerReturn<R> {
-  fn new(receiver: catty::Receiver<R>) -> Self {
-    Self(Receiver(receiver))
+  fn new(receiver: catty::Receiver<Result<R, Error>>) -> Self {
+    Self(Receiver::new(receiver))
   }
 
   fn resolve_to_receiver(self) -> ResolveToReceiver<R> {
@@ -368,7 +598,7 @@ impl<R> ResolveToHandlerReturn<R> {
   }
 }
 
-mod private {
+pub(crate) mod private {
   use crate::WasmRc;
 
   use super::*;
@@ -427,14 +657,109 @@ mod private {
     }
   }
 
+  pub trait SetDeadline {
+    fn set_deadline(&mut self, deadline: Instant);
+  }
+
+  impl<A, Rc> SetDeadline for Sending<A, MessageToOne<A>, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_deadline(&mut self, deadline: Instant) {
+      match self {
+        Sending::New { msg, .. } => msg.set_deadline(deadline),
+        _ => panic!("Cannot set deadline after first poll"),
+      }
+    }
+  }
+
+  impl<A, Rc> SetDeadline for Sending<A, MessageToAll<A>, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_deadline(&mut self, _: Instant) {
+      // Broadcasts are not withdrawn once they are queued, so there is nothing to set.
+      if !matches!(self, Sending::New { .. }) {
+        panic!("Cannot set deadline after first poll")
+      }
+    }
+  }
+
+  impl<A, Rc> SetDeadline for ActorNamedSending<A, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_deadline(&mut self, deadline: Instant) {
+      self.0.set_deadline(deadline)
+    }
+  }
+
+  impl<A, Rc> SetDeadline for ActorNamedBroadcasting<A, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_deadline(&mut self, deadline: Instant) {
+      self.0.set_deadline(deadline)
+    }
+  }
+
+  impl SetDeadline for ActorErasedSending {
+    fn set_deadline(&mut self, deadline: Instant) {
+      self.0.set_deadline(deadline)
+    }
+  }
+
+  pub trait SetTtl {
+    fn set_ttl(&mut self, ttl: Duration);
+  }
+
+  impl<A, Rc> SetTtl for Sending<A, MessageToOne<A>, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_ttl(&mut self, ttl: Duration) {
+      match self {
+        Sending::New { msg, .. } => msg.set_ttl(ttl),
+        _ => panic!("Cannot set time-to-live after first poll"),
+      }
+    }
+  }
+
+  impl<A, Rc> SetTtl for Sending<A, MessageToAll<A>, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_ttl(&mut self, _: Duration) {
+      // Broadcasts have no single sender to report their expiry to, so they do not expire.
+      if !matches!(self, Sending::New { .. }) {
+        panic!("Cannot set time-to-live after first poll")
+      }
+    }
+  }
+
+  impl<A, Rc> SetTtl for ActorNamedSending<A, Rc>
+  where
+    Rc: RefCounter,
+  {
+    fn set_ttl(&mut self, ttl: Duration) {
+      self.0.set_ttl(ttl)
+    }
+  }
+
+  impl SetTtl for ActorErasedSending {
+    fn set_ttl(&mut self, ttl: Duration) {
+      self.0.set_ttl(ttl)
+    }
+  }
+
   /// Helper trait because Rust does not allow to `+` non-auto traits in trait objects.
   pub trait ErasedSending:
-    Future<Output = Result<(), Error>> + FusedFuture + SetPriority + WasmSend + 'static + Unpin
+    Future<Output = Result<(), Error>> + FusedFuture + SetPriority + SetDeadline + SetTtl + WasmSend + 'static + Unpin
   {
   }
 
   impl<F> ErasedSending for F where
-    F: Future<Output = Result<(), Error>> + FusedFuture + SetPriority + WasmSend + 'static + Unpin
+    F: Future<Output = Result<(), Error>> + FusedFuture + SetPriority + SetDeadline + SetTtl + WasmSend + 'static + Unpin
   {
   }
 }
@@ -1,3 +1,12 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_util::future::{AbortHandle, Abortable};
+use futures_util::FutureExt;
+
+use crate::{Actor, Error, Mailbox, WasmBoxFuture, WasmSend};
+
 /// Spawns the given actor into the tokio runtime, returning an [`Address`](crate::Address) to it.
 #[cfg(all(feature = "tokio", not(target_family = "wasm")))]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
@@ -13,6 +22,23 @@ where
     address
 }
 
+/// Spawns the given actor into the tokio runtime, returning an [`Address`](crate::Address) to it
+/// and an [`ActorHandle`] which resolves to its stop value.
+#[cfg(all(feature = "tokio", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub fn spawn_tokio_with_handle<A>(
+    actor: A,
+    (address, mailbox): (crate::Address<A>, crate::Mailbox<A>),
+) -> (crate::Address<A>, ActorHandle<A::Stop>)
+where
+    A: crate::Actor,
+{
+    let (task, handle) = with_handle(actor, mailbox);
+    tokio::spawn(task);
+
+    (address, handle)
+}
+
 /// Spawns the given actor into the async_std runtime, returning an [`Address`](crate::Address) to it.
 #[cfg(all(feature = "async_std", not(target_family = "wasm")))]
 #[cfg_attr(docsrs, doc(cfg(feature = "async_std")))]
@@ -28,6 +54,23 @@ where
     address
 }
 
+/// Spawns the given actor into the async_std runtime, returning an [`Address`](crate::Address) to it
+/// and an [`ActorHandle`] which resolves to its stop value.
+#[cfg(all(feature = "async_std", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "async_std")))]
+pub fn spawn_async_std_with_handle<A>(
+    actor: A,
+    (address, mailbox): (crate::Address<A>, crate::Mailbox<A>),
+) -> (crate::Address<A>, ActorHandle<A::Stop>)
+where
+    A: crate::Actor,
+{
+    let (task, handle) = with_handle(actor, mailbox);
+    async_std::task::spawn(task);
+
+    (address, handle)
+}
+
 /// Spawns the given actor into the smol runtime, returning an [`Address`](crate::Address) to it.
 #[cfg(all(feature = "smol", not(target_family = "wasm")))]
 #[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
@@ -43,6 +86,23 @@ where
     address
 }
 
+/// Spawns the given actor into the smol runtime, returning an [`Address`](crate::Address) to it
+/// and an [`ActorHandle`] which resolves to its stop value.
+#[cfg(all(feature = "smol", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+pub fn spawn_smol_with_handle<A>(
+    actor: A,
+    (address, mailbox): (crate::Address<A>, crate::Mailbox<A>),
+) -> (crate::Address<A>, ActorHandle<A::Stop>)
+where
+    A: crate::Actor,
+{
+    let (task, handle) = with_handle(actor, mailbox);
+    smol::spawn(task).detach();
+
+    (address, handle)
+}
+
 /// Spawns the given actor onto the thread-local runtime via `wasm_bindgen_futures`, returning an [`Address`](crate::Address) to it.
 #[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
 #[cfg_attr(docsrs, doc(cfg(feature = "wasm_bindgen")))]
@@ -57,3 +117,268 @@ where
 
     address
 }
+
+/// Spawns the given actor onto the thread-local runtime via `wasm_bindgen_futures`, returning an
+/// [`Address`](crate::Address) to it and an [`ActorHandle`] which resolves to its stop value.
+#[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm_bindgen")))]
+pub fn spawn_wasm_bindgen_with_handle<A>(
+    actor: A,
+    (address, mailbox): (crate::Address<A>, crate::Mailbox<A>),
+) -> (crate::Address<A>, ActorHandle<A::Stop>)
+where
+    A: crate::Actor,
+{
+    let (task, handle) = with_handle(actor, mailbox);
+    wasm_bindgen_futures::spawn_local(task);
+
+    (address, handle)
+}
+
+/// Spawns the given actor onto a dedicated OS thread, returning an [`Address`](crate::Address) to it
+/// and an [`ActorHandle`] which resolves to its stop value.
+///
+/// The actor runs on a minimal executor of its own rather than on an async runtime, so its
+/// handlers may block, e.g. on file or database IO, without stalling other tasks. This includes
+/// [`SendFuture::wait`](crate::SendFuture::wait) and [`Address::send_blocking`](crate::Address::send_blocking),
+/// which panic in the handlers of other actors. The thread is named after the actor type and exits
+/// once the actor stops.
+#[cfg(not(target_family = "wasm"))]
+pub fn spawn_thread<A>(
+    actor: A,
+    (address, mailbox): (crate::Address<A>, crate::Mailbox<A>),
+) -> (crate::Address<A>, ActorHandle<A::Stop>)
+where
+    A: crate::Actor,
+{
+    let (task, handle) = with_handle(actor, mailbox);
+
+    std::thread::Builder::new()
+        .name(std::any::type_name::<A>().to_owned())
+        .spawn(move || crate::blocking::block_on_dedicated(task))
+        .expect("failed to spawn actor thread");
+
+    (address, handle)
+}
+
+/// Spawns `threads` actors created by the given function onto dedicated OS threads, all sharing the
+/// given [`Mailbox`](crate::Mailbox). Returns an [`Address`](crate::Address) to them and an
+/// [`ActorHandle`] for each of them.
+///
+/// Each message sent through the address is handled by whichever actor is idle first, so up to
+/// `threads` blocking handlers run in parallel. See [`spawn_thread`] for details.
+///
+/// # Panics
+///
+/// Panics if `threads` is 0.
+#[cfg(not(target_family = "wasm"))]
+pub fn spawn_blocking_pool<A>(
+    threads: usize,
+    mut actor: impl FnMut() -> A,
+    (address, mailbox): (crate::Address<A>, crate::Mailbox<A>),
+) -> (crate::Address<A>, Vec<ActorHandle<A::Stop>>)
+where
+    A: crate::Actor,
+{
+    assert!(threads > 0, "A blocking pool needs at least one thread");
+
+    let handles = (0..threads)
+        .map(|_| spawn_thread(actor(), (address.clone(), mailbox.clone())).1)
+        .collect();
+
+    (address, handles)
+}
+
+/// An executor onto which actors can be spawned through [`Actor::spawn_on`].
+///
+/// Accepting any [`Spawner`] allows libraries built on xtra to stay agnostic of the async runtime
+/// in use. Implementations are provided for every runtime supported by xtra's spawn features, e.g.
+/// [`TokioSpawner`] for the `tokio` feature, for `futures::executor::ThreadPool` with the
+/// `thread_pool` feature, and for closures which spawn the given task themselves.
+///
+/// Since a [`Spawner`] is [`Clone`], an actor can keep it to spawn its children onto the same
+/// executor:
+///
+/// ```rust
+/// # use xtra::prelude::*;
+/// use xtra::Spawner;
+///
+/// # #[derive(xtra::Actor)]
+/// # struct Child;
+/// #[derive(xtra::Actor)]
+/// struct Parent<S> {
+///     spawner: S,
+/// }
+///
+/// struct SpawnChild;
+///
+/// impl<S: Spawner> Handler<SpawnChild> for Parent<S> {
+///     type Return = Address<Child>;
+///
+///     async fn handle(&mut self, _: SpawnChild, _ctx: &mut Context<Self>) -> Address<Child> {
+///         let (address, mailbox) = Mailbox::unbounded();
+///         let _ = Child.spawn_on(&self.spawner, mailbox);
+///
+///         address
+///     }
+/// }
+///
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let spawner = xtra::TokioSpawner;
+/// let (parent, mailbox) = Mailbox::unbounded();
+/// let _ = Parent { spawner }.spawn_on(&spawner, mailbox);
+///
+/// let child = parent.send(SpawnChild).await.unwrap();
+/// assert!(child.is_connected());
+/// # })
+/// ```
+pub trait Spawner: Clone + WasmSend + 'static {
+    /// Spawn the given task onto this executor, running it to completion in the background.
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>);
+}
+
+/// A [`Spawner`] for the tokio runtime.
+#[cfg(all(feature = "tokio", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioSpawner;
+
+#[cfg(all(feature = "tokio", not(target_family = "wasm")))]
+impl Spawner for TokioSpawner {
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        tokio::spawn(task);
+    }
+}
+
+/// A [`Spawner`] for the async_std runtime.
+#[cfg(all(feature = "async_std", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "async_std")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdSpawner;
+
+#[cfg(all(feature = "async_std", not(target_family = "wasm")))]
+impl Spawner for AsyncStdSpawner {
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        async_std::task::spawn(task);
+    }
+}
+
+/// A [`Spawner`] for the smol runtime.
+#[cfg(all(feature = "smol", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmolSpawner;
+
+#[cfg(all(feature = "smol", not(target_family = "wasm")))]
+impl Spawner for SmolSpawner {
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        smol::spawn(task).detach();
+    }
+}
+
+/// A [`Spawner`] for the thread-local runtime of `wasm_bindgen_futures`.
+#[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm_bindgen")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WasmBindgenSpawner;
+
+#[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
+impl Spawner for WasmBindgenSpawner {
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        wasm_bindgen_futures::spawn_local(task);
+    }
+}
+
+#[cfg(all(feature = "thread_pool", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "thread_pool")))]
+impl Spawner for futures_executor::ThreadPool {
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        self.spawn_ok(task);
+    }
+}
+
+/// Any closure which spawns the given task is a [`Spawner`], e.g.
+/// `|task: WasmBoxFuture<'static, ()>| my_runtime::spawn(task)`.
+impl<F> Spawner for F
+where
+    F: Fn(WasmBoxFuture<'static, ()>) + Clone + WasmSend + 'static,
+{
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        self(task);
+    }
+}
+
+/// A handle to a spawned actor, returned from functions such as `spawn_tokio_with_handle`.
+///
+/// Awaiting an [`ActorHandle`] resolves to the value returned from [`Actor::stopped`], or the
+/// error returned from [`Actor::started`], once the actor has stopped. It resolves to
+/// [`Error::Interrupted`] instead if the actor was [aborted](ActorHandle::abort) or its task
+/// panicked. Dropping an [`ActorHandle`] does not stop the actor.
+pub struct ActorHandle<S> {
+    stop: catty::Receiver<S>,
+    abort: AbortHandle,
+}
+
+impl<S> ActorHandle<S> {
+    /// Abort the actor the next time it yields, e.g. while it waits for a message or in the middle
+    /// of a handler. Unlike [`Context::stop_self`](crate::Context::stop_self), this does not call
+    /// [`Actor::stopped`] and does not wait for the current handler to finish.
+    pub fn abort(&self) {
+        self.abort.abort();
+    }
+
+    /// Returns whether the actor was aborted through [`ActorHandle::abort`].
+    pub fn is_aborted(&self) -> bool {
+        self.abort.is_aborted()
+    }
+}
+
+impl<S> Future for ActorHandle<S> {
+    type Output = Result<S, Error>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.get_mut()
+            .stop
+            .poll_unpin(cx)
+            .map(|stop| stop.map_err(|_| Error::Interrupted))
+    }
+}
+
+impl<S> std::fmt::Debug for ActorHandle<S> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ActorHandle")
+            .field("stop", &std::any::type_name::<S>())
+            .field("aborted", &self.is_aborted())
+            .finish()
+    }
+}
+
+/// Create the task which runs the given actor on its mailbox, along with an [`ActorHandle`] to it.
+pub(crate) fn with_handle<A>(
+    actor: A,
+    mailbox: Mailbox<A>,
+) -> (impl Future<Output = ()>, ActorHandle<A::Stop>)
+where
+    A: Actor,
+{
+    task_with_handle(crate::run(mailbox, actor))
+}
+
+/// Create the task which runs the given event loop of an actor, along with an [`ActorHandle`]
+/// which resolves to its output.
+pub(crate) fn task_with_handle<F>(run: F) -> (impl Future<Output = ()>, ActorHandle<F::Output>)
+where
+    F: Future,
+{
+    let (abort, registration) = AbortHandle::new_pair();
+    let (sender, stop) = catty::oneshot();
+
+    let task = Abortable::new(run, registration).map(move |stop| {
+        if let Ok(stop) = stop {
+            let _ = sender.send(stop);
+        }
+    });
+
+    (task, ActorHandle { stop, abort })
+}
@@ -0,0 +1,375 @@
+//! Supervisors restart actors which have stopped according to a [`Strategy`].
+//!
+//! A supervised actor is restarted on the same [`Mailbox`] it was initially given. Existing
+//! [`Address`](crate::Address)es therefore keep working across restarts and callers never need to
+//! find out about a new address.
+
+use std::collections::VecDeque;
+use std::future::poll_fn;
+use std::ops::ControlFlow;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_util::future::{self, Either};
+use futures_util::FutureExt;
+use web_time::Instant;
+
+use crate::{Actor, Mailbox, WasmBoxFuture, WasmSend};
+
+/// Determines which children are restarted when one of them stops.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Strategy {
+    /// Only the child that stopped is restarted.
+    OneForOne,
+    /// All children are stopped and then restarted whenever one of them stops.
+    OneForAll,
+    /// The child that stopped and all children that were added after it are stopped and then
+    /// restarted. Children added before it are left untouched.
+    RestForOne,
+}
+
+/// The reason why [`Supervisor::run`] returned.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SupervisorStop {
+    /// All children have stopped and none of them is to be restarted.
+    Finished,
+    /// The children were restarted more often than allowed by [`Supervisor::intensity`]. All
+    /// remaining children have been stopped.
+    RestartIntensityExceeded,
+}
+
+/// A specification of how to start and restart a single child of a [`Supervisor`].
+pub struct ChildSpec<A: Actor> {
+    mailbox: Mailbox<A>,
+    factory: Box<dyn Factory<A>>,
+    restart: Box<dyn RestartPolicy<A::Stop>>,
+}
+
+impl<A: Actor> ChildSpec<A> {
+    /// Create a new [`ChildSpec`] which runs actors created by `factory` on the given [`Mailbox`].
+    ///
+    /// By default, the child is restarted every time it stops, as long as there are still strong
+    /// [`Address`](crate::Address)es to it. Use [`ChildSpec::restart_if`] to change this.
+    pub fn new<F>(mailbox: Mailbox<A>, factory: F) -> Self
+    where
+        F: FnMut() -> A + WasmSend + 'static,
+    {
+        Self {
+            mailbox,
+            factory: Box::new(factory),
+            restart: Box::new(|_: &A::Stop| true),
+        }
+    }
+
+    /// Only restart the child if the given predicate returns `true` for the value returned from
+    /// [`Actor::stopped`].
+    ///
+    /// A child is never restarted once all strong [`Address`](crate::Address)es to it have been
+    /// dropped, as nobody would be able to send it a message anymore. A child whose
+    /// [`Actor::on_panic`] returned [`PanicAction::Restart`](crate::PanicAction::Restart) is always
+    /// restarted, regardless of this predicate.
+    pub fn restart_if<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&A::Stop) -> bool + WasmSend + 'static,
+    {
+        self.restart = Box::new(predicate);
+        self
+    }
+
+    /// Never restart this child.
+    pub fn temporary(self) -> Self {
+        self.restart_if(|_| false)
+    }
+}
+
+/// A supervisor runs a set of child actors and restarts them according to a [`Strategy`] when
+/// they stop.
+///
+/// Every child is run on the [`Mailbox`] given in its [`ChildSpec`]. When a child is restarted, a
+/// new instance of the actor is created from the child's factory and run on the same [`Mailbox`],
+/// so messages that were queued but not yet handled are delivered to the new instance.
+///
+/// A supervisor gives up if its children need to be restarted too often, as configured with
+/// [`Supervisor::intensity`].
+///
+/// # Example
+///
+/// ```rust
+/// # use xtra::prelude::*;
+/// # use xtra::supervisor::{ChildSpec, Strategy, Supervisor};
+/// # struct Worker;
+/// # impl Actor for Worker { type Stop = (); async fn stopped(self) {} }
+/// # #[cfg(feature = "tokio")]
+/// # tokio::runtime::Runtime::new().unwrap().block_on(async {
+/// let (address, mailbox) = Mailbox::unbounded();
+/// let supervisor = Supervisor::new(Strategy::OneForOne)
+///     .child(ChildSpec::new(mailbox, || Worker));
+///
+/// tokio::spawn(supervisor.run());
+/// # drop(address);
+/// # })
+/// ```
+pub struct Supervisor {
+    strategy: Strategy,
+    max_restarts: usize,
+    within: Duration,
+    restarts: VecDeque<Instant>,
+    children: Vec<Box<dyn Supervised>>,
+}
+
+impl Supervisor {
+    /// Create a new [`Supervisor`] with the given [`Strategy`] and no children.
+    ///
+    /// By default, a supervisor allows at most 3 restarts within 5 seconds.
+    pub fn new(strategy: Strategy) -> Self {
+        Self {
+            strategy,
+            max_restarts: 3,
+            within: Duration::from_secs(5),
+            restarts: VecDeque::new(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Set the maximum restart intensity of this supervisor.
+    ///
+    /// If more than `max_restarts` restarts happen within the window of `within`, the supervisor
+    /// stops all of its children and [`Supervisor::run`] resolves to
+    /// [`SupervisorStop::RestartIntensityExceeded`].
+    pub fn intensity(mut self, max_restarts: usize, within: Duration) -> Self {
+        self.max_restarts = max_restarts;
+        self.within = within;
+        self
+    }
+
+    /// Add a child to this supervisor. Children are started in the order they are added.
+    pub fn child<A: Actor>(mut self, spec: ChildSpec<A>) -> Self {
+        self.children.push(Box::new(Child {
+            spec,
+            running: None,
+            stop: None,
+        }));
+        self
+    }
+
+    /// Run all children of this supervisor, restarting them as required.
+    ///
+    /// This future resolves once no child is running anymore, or once the restart intensity has
+    /// been exceeded.
+    pub async fn run(mut self) -> SupervisorStop {
+        for child in &mut self.children {
+            child.start();
+        }
+
+        loop {
+            let (index, exit) = match poll_fn(|cx| self.poll_next_exit(cx)).await {
+                Some(exited) => exited,
+                None => return SupervisorStop::Finished,
+            };
+
+            if exit == Exit::Done {
+                continue;
+            }
+
+            if !self.record_restart() {
+                self.stop_children(0).await;
+                return SupervisorStop::RestartIntensityExceeded;
+            }
+
+            match self.strategy {
+                Strategy::OneForOne => self.children[index].start(),
+                Strategy::OneForAll => self.restart_children(0, index).await,
+                Strategy::RestForOne => self.restart_children(index, index).await,
+            }
+        }
+    }
+
+    /// Poll all running children, returning the index of the first one that exited.
+    ///
+    /// Resolves to `None` if no child is running anymore.
+    fn poll_next_exit(&mut self, cx: &mut Context<'_>) -> Poll<Option<(usize, Exit)>> {
+        let mut any_running = false;
+
+        for (index, child) in self.children.iter_mut().enumerate() {
+            if !child.is_running() {
+                continue;
+            }
+
+            any_running = true;
+
+            if let Poll::Ready(exit) = child.poll_exit(cx) {
+                return Poll::Ready(Some((index, exit)));
+            }
+        }
+
+        if any_running {
+            Poll::Pending
+        } else {
+            Poll::Ready(None)
+        }
+    }
+
+    /// Record a restart, returning whether it is within the allowed restart intensity.
+    fn record_restart(&mut self) -> bool {
+        let now = Instant::now();
+
+        while let Some(oldest) = self.restarts.front() {
+            if now.duration_since(*oldest) <= self.within {
+                break;
+            }
+
+            self.restarts.pop_front();
+        }
+
+        self.restarts.push_back(now);
+        self.restarts.len() <= self.max_restarts
+    }
+
+    /// Stop all running children from `from` onwards and wait for them to exit, returning the
+    /// indices of those which are to be restarted.
+    async fn stop_children(&mut self, from: usize) -> Vec<usize> {
+        for child in self.children.iter_mut().skip(from) {
+            if child.is_running() {
+                child.stop();
+            }
+        }
+
+        let mut to_restart = Vec::new();
+
+        poll_fn(|cx| {
+            let mut pending = false;
+
+            for (index, child) in self.children.iter_mut().enumerate().skip(from) {
+                if !child.is_running() {
+                    continue;
+                }
+
+                match child.poll_exit(cx) {
+                    Poll::Ready(Exit::Restart) => to_restart.push(index),
+                    Poll::Ready(Exit::Done) => {}
+                    Poll::Pending => pending = true,
+                }
+            }
+
+            if pending {
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await;
+
+        to_restart
+    }
+
+    /// Stop all children from `from` onwards, then restart those which are to be restarted
+    /// together with the child at `failed`, which has already exited.
+    async fn restart_children(&mut self, from: usize, failed: usize) {
+        let mut to_start = self.stop_children(from).await;
+        to_start.push(failed);
+        to_start.sort_unstable();
+
+        for index in to_start {
+            self.children[index].start();
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Exit {
+    /// The child stopped and should be restarted.
+    Restart,
+    /// The child stopped for good.
+    Done,
+}
+
+/// Type-erased interface of a [`Child`] so that children of different actor types can be
+/// supervised together.
+trait Supervised: WasmSend {
+    fn start(&mut self);
+
+    fn stop(&mut self);
+
+    fn is_running(&self) -> bool;
+
+    fn poll_exit(&mut self, cx: &mut Context<'_>) -> Poll<Exit>;
+}
+
+struct Child<A: Actor> {
+    spec: ChildSpec<A>,
+    running: Option<WasmBoxFuture<'static, A::Stop>>,
+    stop: Option<catty::Sender<()>>,
+}
+
+impl<A: Actor> Supervised for Child<A> {
+    fn start(&mut self) {
+        let mailbox = self.spec.mailbox.share();
+        mailbox.discard_pending_shutdown();
+
+        let actor = (self.spec.factory)();
+        let (stop, stopped) = catty::oneshot();
+        self.running = Some(Box::pin(run_until_stopped(mailbox, actor, stopped)));
+        self.stop = Some(stop);
+    }
+
+    fn stop(&mut self) {
+        if let Some(stop) = self.stop.take() {
+            let _ = stop.send(());
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.is_some()
+    }
+
+    fn poll_exit(&mut self, cx: &mut Context<'_>) -> Poll<Exit> {
+        let running = match self.running.as_mut() {
+            Some(running) => running,
+            None => return Poll::Ready(Exit::Done),
+        };
+
+        let stop = futures_util::ready!(running.poll_unpin(cx));
+        self.running = None;
+
+        let restart_requested = self.spec.mailbox.take_restart_request();
+
+        if self.spec.mailbox.address().is_connected()
+            && (restart_requested || (self.spec.restart)(&stop))
+        {
+            Poll::Ready(Exit::Restart)
+        } else {
+            Poll::Ready(Exit::Done)
+        }
+    }
+}
+
+/// Run the given actor like [`crate::run`], until `stopped` resolves. Unlike shutting down the
+/// mailbox, this only stops this actor and not other actors running on the same mailbox.
+async fn run_until_stopped<A: Actor>(
+    mailbox: Mailbox<A>,
+    mut actor: A,
+    mut stopped: catty::Receiver<()>,
+) -> A::Stop {
+    if let Err(stop) = actor.started(&mailbox).await {
+        return stop;
+    }
+
+    loop {
+        let message = match future::select(mailbox.next(), &mut stopped).await {
+            Either::Left((message, _)) => message,
+            Either::Right(_) => break,
+        };
+
+        if let ControlFlow::Break(()) = message.dispatch_to(&mut actor).await {
+            break;
+        }
+    }
+
+    actor.stopped().await
+}
+
+trait Factory<A>: FnMut() -> A + WasmSend {}
+impl<A, F: FnMut() -> A + WasmSend> Factory<A> for F {}
+
+trait RestartPolicy<S>: Fn(&S) -> bool + WasmSend {}
+impl<S, F: Fn(&S) -> bool + WasmSend> RestartPolicy<S> for F {}
@@ -0,0 +1,288 @@
+//! Utilities for testing actors deterministically, without a runtime and without sleeping.
+//!
+//! An [`Executor`] runs futures on the current thread, polling woken tasks in the order in which
+//! they were woken. A [`TestMailbox`] runs an actor on such an executor, but only handles a message
+//! when asked to through [`TestMailbox::step`]. In between, the queued messages can be inspected
+//! through [`TestMailbox::queued`].
+//!
+//! ```rust
+//! # use xtra::prelude::*;
+//! use xtra::testing::{Executor, TestMailbox};
+//!
+//! #[derive(Default, xtra::Actor)]
+//! struct Counter(usize);
+//!
+//! struct Inc;
+//!
+//! impl Handler<Inc> for Counter {
+//!     type Return = usize;
+//!
+//!     async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) -> usize {
+//!         self.0 += 1;
+//!         self.0
+//!     }
+//! }
+//!
+//! let executor = Executor::new();
+//! let (address, mailbox) = Mailbox::unbounded();
+//! let mut counter = TestMailbox::new(&executor, mailbox, Counter::default());
+//!
+//! let first = executor.block_on(address.send(Inc).detach()).unwrap();
+//! let second = executor.block_on(address.send(Inc).priority(1).detach()).unwrap();
+//! assert_eq!(counter.queued().len(), 2);
+//! assert_eq!(counter.queued()[0].priority, 1);
+//!
+//! assert!(counter.step());
+//! assert_eq!(executor.block_on(second), Ok(1));
+//! assert!(counter.step());
+//! assert_eq!(executor.block_on(first), Ok(2));
+//! assert!(!counter.step());
+//!
+//! drop(address);
+//! assert!(counter.step());
+//! assert!(counter.is_stopped());
+//! ```
+//!
+//! Futures which are woken from other threads, such as timers or the
+//! [`timeout`](crate::SendFuture::timeout) of a message, are not supported: the executor panics
+//! once none of its tasks can make progress, rather than waiting for them.
+
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::future::Future;
+use std::ops::ControlFlow;
+use std::pin::pin;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures_util::future::LocalBoxFuture;
+use futures_util::task::{waker, ArcWake};
+use futures_util::FutureExt;
+
+pub use crate::chan::QueuedMessage;
+use crate::{Actor, Mailbox};
+
+/// A single-threaded executor which runs tasks in a deterministic order.
+///
+/// Cloning an [`Executor`] returns another handle to the same executor.
+#[derive(Clone, Default)]
+pub struct Executor {
+    tasks: Rc<RefCell<Vec<Task>>>,
+    queue: Arc<Mutex<VecDeque<usize>>>,
+}
+
+struct Task {
+    future: Option<LocalBoxFuture<'static, ()>>,
+    waker: Arc<TaskWaker>,
+}
+
+impl Executor {
+    /// Create a new [`Executor`] without any tasks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Spawn a future onto this executor. It is polled whenever the executor runs, e.g. during
+    /// [`Executor::block_on`]. Its output is discarded.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future + 'static,
+    {
+        let mut tasks = self.tasks.borrow_mut();
+        let waker = Arc::new(TaskWaker {
+            id: tasks.len(),
+            scheduled: AtomicBool::new(false),
+            queue: self.queue.clone(),
+        });
+
+        waker.wake_by_ref_inner();
+        tasks.push(Task {
+            future: Some(future.map(drop).boxed_local()),
+            waker,
+        });
+    }
+
+    /// Poll all spawned tasks which were woken, in the order in which they were woken, until none
+    /// of them can make progress anymore. Returns whether any task was polled.
+    pub fn run_until_stalled(&self) -> bool {
+        let mut polled = false;
+
+        while let Some(id) = self.next_scheduled() {
+            polled = true;
+
+            let (future, task_waker) = {
+                let mut tasks = self.tasks.borrow_mut();
+                let task = &mut tasks[id];
+                (task.future.take(), task.waker.clone())
+            };
+
+            let mut future = match future {
+                Some(future) => future,
+                None => continue, // The task has completed already.
+            };
+
+            task_waker.scheduled.store(false, Ordering::SeqCst);
+            let waker = waker(task_waker);
+
+            if future
+                .poll_unpin(&mut Context::from_waker(&waker))
+                .is_pending()
+            {
+                self.tasks.borrow_mut()[id].future = Some(future);
+            }
+        }
+
+        polled
+    }
+
+    /// Run the given future to completion on the current thread, running the spawned tasks
+    /// alongside it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the future cannot make progress because neither it nor any spawned task was
+    /// woken, which would otherwise block forever.
+    pub fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future,
+    {
+        let mut future = pin!(future);
+        let main = Arc::new(MainWaker(AtomicBool::new(true)));
+        let waker = waker(main.clone());
+
+        loop {
+            if main.0.swap(false, Ordering::SeqCst) {
+                if let Poll::Ready(output) = future.as_mut().poll(&mut Context::from_waker(&waker))
+                {
+                    return output;
+                }
+            }
+
+            if !self.run_until_stalled() && !main.0.load(Ordering::SeqCst) {
+                panic!("Executor stalled: the future passed to `block_on` cannot make progress");
+            }
+        }
+    }
+
+    fn next_scheduled(&self) -> Option<usize> {
+        self.queue.lock().unwrap().pop_front()
+    }
+}
+
+struct TaskWaker {
+    id: usize,
+    scheduled: AtomicBool,
+    queue: Arc<Mutex<VecDeque<usize>>>,
+}
+
+impl TaskWaker {
+    fn wake_by_ref_inner(&self) {
+        if !self.scheduled.swap(true, Ordering::SeqCst) {
+            self.queue.lock().unwrap().push_back(self.id);
+        }
+    }
+}
+
+impl ArcWake for TaskWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.wake_by_ref_inner();
+    }
+}
+
+struct MainWaker(AtomicBool);
+
+impl ArcWake for MainWaker {
+    fn wake_by_ref(arc_self: &Arc<Self>) {
+        arc_self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+/// A mailbox which runs an actor on an [`Executor`], handling one message at a time on request.
+pub struct TestMailbox<A: Actor> {
+    executor: Executor,
+    mailbox: Mailbox<A>,
+    actor: Option<A>,
+    stop: Option<A::Stop>,
+}
+
+impl<A: Actor> TestMailbox<A> {
+    /// Start the given actor on the given [`Mailbox`], running [`Actor::started`] on the given
+    /// executor.
+    pub fn new(executor: &Executor, mailbox: Mailbox<A>, mut actor: A) -> Self {
+        let (actor, stop) = match executor.block_on(actor.started(&mailbox)) {
+            Ok(()) => (Some(actor), None),
+            Err(stop) => (None, Some(stop)),
+        };
+
+        TestMailbox {
+            executor: executor.clone(),
+            mailbox,
+            actor,
+            stop,
+        }
+    }
+
+    /// Handle the next message in the mailbox, like [`yield_once`](crate::yield_once) does. If
+    /// the message stops the actor, [`Actor::stopped`] is run as well.
+    ///
+    /// Returns `false` without doing anything if the mailbox is empty or the actor has stopped.
+    pub fn step(&mut self) -> bool {
+        let actor = match self.actor.as_mut() {
+            Some(actor) => actor,
+            None => return false,
+        };
+
+        let message = match self.mailbox.next().now_or_never() {
+            Some(message) => message,
+            None => return false,
+        };
+
+        if let ControlFlow::Break(()) = self.executor.block_on(message.dispatch_to(actor)) {
+            let actor = self.actor.take().expect("actor is running");
+            self.stop = Some(self.executor.block_on(actor.stopped()));
+        }
+
+        true
+    }
+
+    /// Handle messages until the mailbox is empty or the actor has stopped, returning the number
+    /// of handled messages.
+    pub fn run_until_idle(&mut self) -> usize {
+        let mut handled = 0;
+
+        while self.step() {
+            handled += 1;
+        }
+
+        handled
+    }
+
+    /// Describe the messages in the mailbox, in the order in which they will be handled. The
+    /// order of broadcasts with the same priority is unspecified.
+    pub fn queued(&self) -> Vec<QueuedMessage> {
+        self.mailbox.queued()
+    }
+
+    /// The actor, or `None` if it has stopped.
+    pub fn actor(&self) -> Option<&A> {
+        self.actor.as_ref()
+    }
+
+    /// Returns whether the actor has stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.is_some()
+    }
+
+    /// The value returned from [`Actor::stopped`], or the error returned from
+    /// [`Actor::started`], once the actor has stopped.
+    pub fn stop_reason(&self) -> Option<&A::Stop> {
+        self.stop.as_ref()
+    }
+
+    /// The [`Mailbox`] the actor runs on.
+    pub fn mailbox(&self) -> &Mailbox<A> {
+        &self.mailbox
+    }
+}
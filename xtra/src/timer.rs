@@ -0,0 +1,137 @@
+//! Scheduling of delayed and periodic messages.
+//!
+//! Timers are provided by the async runtime, so scheduling a message requires a [`Timer`] for the
+//! runtime in use. An implementation is provided for every runtime supported by xtra's spawn
+//! features, e.g. [`TokioTimer`] for the `tokio` feature.
+//!
+//! Scheduled messages are bound to the lifetime of the actor they are sent to: a pending
+//! [`Address::send_after`](crate::Address::send_after) or
+//! [`Address::send_interval`](crate::Address::send_interval) does not keep the actor alive and is
+//! stopped automatically once the actor stops.
+
+use std::future::Future;
+use std::time::Duration;
+
+use futures_util::future::{AbortHandle, Abortable};
+
+use crate::{WasmBoxFuture, WasmSend, WeakAddress};
+
+/// A timer of an async runtime which can be used to schedule messages.
+pub trait Timer: Clone + WasmSend + 'static {
+    /// Returns a future which completes after the given duration has elapsed.
+    fn sleep(&self, duration: Duration) -> WasmBoxFuture<'static, ()>;
+
+    /// Spawn the given task onto the runtime of this timer.
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>);
+}
+
+/// A [`Timer`] for the tokio runtime.
+#[cfg(all(feature = "tokio", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TokioTimer;
+
+#[cfg(all(feature = "tokio", not(target_family = "wasm")))]
+impl Timer for TokioTimer {
+    fn sleep(&self, duration: Duration) -> WasmBoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        tokio::spawn(task);
+    }
+}
+
+/// A [`Timer`] for the async_std runtime.
+#[cfg(all(feature = "async_std", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "async_std")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct AsyncStdTimer;
+
+#[cfg(all(feature = "async_std", not(target_family = "wasm")))]
+impl Timer for AsyncStdTimer {
+    fn sleep(&self, duration: Duration) -> WasmBoxFuture<'static, ()> {
+        Box::pin(async_std::task::sleep(duration))
+    }
+
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        async_std::task::spawn(task);
+    }
+}
+
+/// A [`Timer`] for the smol runtime.
+#[cfg(all(feature = "smol", not(target_family = "wasm")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "smol")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SmolTimer;
+
+#[cfg(all(feature = "smol", not(target_family = "wasm")))]
+impl Timer for SmolTimer {
+    fn sleep(&self, duration: Duration) -> WasmBoxFuture<'static, ()> {
+        let timer = smol::Timer::after(duration);
+
+        Box::pin(async move {
+            timer.await;
+        })
+    }
+
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        smol::spawn(task).detach();
+    }
+}
+
+/// A [`Timer`] for the thread-local runtime of `wasm_bindgen_futures`.
+#[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
+#[cfg_attr(docsrs, doc(cfg(feature = "wasm_bindgen")))]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct WasmBindgenTimer;
+
+#[cfg(all(feature = "wasm_bindgen", target_family = "wasm"))]
+impl Timer for WasmBindgenTimer {
+    fn sleep(&self, duration: Duration) -> WasmBoxFuture<'static, ()> {
+        Box::pin(gloo_timers::future::sleep(duration))
+    }
+
+    fn spawn(&self, task: WasmBoxFuture<'static, ()>) {
+        wasm_bindgen_futures::spawn_local(task);
+    }
+}
+
+/// A handle to a scheduled message, returned from functions such as
+/// [`Address::send_after`](crate::Address::send_after).
+///
+/// Dropping a [`TimerHandle`] does not cancel the scheduled message. It is cancelled either
+/// explicitly through [`TimerHandle::cancel`] or once the actor it was scheduled for stops.
+#[derive(Clone, Debug)]
+pub struct TimerHandle(AbortHandle);
+
+impl TimerHandle {
+    /// Cancel the scheduled message. If the message is periodic, no further messages are sent.
+    ///
+    /// A message which has already been sent to the actor is not withdrawn from its mailbox.
+    pub fn cancel(&self) {
+        self.0.abort();
+    }
+
+    /// Returns whether the scheduled message was cancelled through [`TimerHandle::cancel`].
+    pub fn is_cancelled(&self) -> bool {
+        self.0.is_aborted()
+    }
+}
+
+/// Spawn the given task onto the timer's runtime, scoped to the lifetime of the actor behind
+/// `address`.
+pub(crate) fn spawn_scoped<A, T, F>(timer: &T, address: &WeakAddress<A>, task: F) -> TimerHandle
+where
+    T: Timer,
+    F: Future<Output = ()> + WasmSend + 'static,
+{
+    let (handle, registration) = AbortHandle::new_pair();
+    let task = Abortable::new(crate::scoped(address, task), registration);
+
+    timer.spawn(Box::pin(async move {
+        let _ = task.await;
+    }));
+
+    TimerHandle(handle)
+}
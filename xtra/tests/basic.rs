@@ -1,14 +1,17 @@
+use std::cell::RefCell;
 use std::cmp::Ordering as CmpOrdering;
 use std::ops::ControlFlow;
+use std::rc::Rc;
 use std::task::Poll;
 use std::time::Duration;
 
 use futures_util::task::noop_waker_ref;
-use futures_util::FutureExt;
+use futures_util::{FutureExt, StreamExt};
 use smol_timeout::TimeoutExt;
-use tokio::task::JoinSet;
+use tokio::task::{JoinSet, LocalSet};
+use xtra::local::{Local, LocalActor, LocalHandler};
 use xtra::prelude::*;
-use xtra::Error;
+use xtra::{Error, PanicAction, TrySendError};
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 struct Accumulator(usize);
@@ -180,6 +183,223 @@ async fn handle_left_messages() {
     assert_eq!(xtra::run(mailbox, Accumulator(0)).await, 10);
 }
 
+#[tokio::test]
+async fn actor_handle_resolves_to_stop_value() {
+    let (addr, handle) = xtra::spawn_tokio_with_handle(Accumulator(0), Mailbox::unbounded());
+
+    for _ in 0..3 {
+        addr.send(Inc).await.unwrap();
+    }
+
+    drop(addr);
+
+    assert_eq!(handle.await, Ok(3));
+}
+
+#[tokio::test]
+async fn aborted_actor_handle_resolves_to_interrupted() {
+    let (addr, handle) = xtra::spawn_tokio_with_handle(Accumulator(0), Mailbox::unbounded());
+    addr.send(Inc).await.unwrap();
+
+    handle.abort();
+
+    assert!(handle.is_aborted());
+    assert_eq!(handle.await, Err(Error::Interrupted));
+    assert!(!addr.is_connected());
+}
+
+#[tokio::test]
+async fn actor_can_be_spawned_on_spawner() {
+    let (addr, mailbox) = Mailbox::unbounded();
+    let handle = Accumulator(0).spawn_on(&xtra::TokioSpawner, mailbox);
+
+    addr.send(Inc).await.unwrap();
+    drop(addr);
+
+    assert_eq!(handle.await, Ok(1));
+}
+
+#[tokio::test]
+async fn actor_can_be_spawned_on_closure() {
+    let spawner = |task: xtra::WasmBoxFuture<'static, ()>| {
+        tokio::spawn(task);
+    };
+    let (addr, mailbox) = Mailbox::unbounded();
+    let handle = Accumulator(0).spawn_on(&spawner, mailbox);
+
+    addr.send(Inc).await.unwrap();
+    drop(addr);
+
+    assert_eq!(handle.await, Ok(1));
+}
+
+#[derive(xtra::Actor)]
+struct Blocking;
+
+struct SleepFor(Duration);
+
+impl Handler<SleepFor> for Blocking {
+    type Return = std::thread::ThreadId;
+
+    async fn handle(&mut self, sleep: SleepFor, _ctx: &mut Context<Self>) -> std::thread::ThreadId {
+        std::thread::sleep(sleep.0);
+        std::thread::current().id()
+    }
+}
+
+/// Sends [`Inc`] to the given accumulator, blocking until it was handled.
+struct IncBlocking(Address<Accumulator>);
+
+impl Handler<IncBlocking> for Blocking {
+    type Return = Result<(), Error>;
+
+    async fn handle(&mut self, inc: IncBlocking, _ctx: &mut Context<Self>) -> Result<(), Error> {
+        inc.0.send_blocking(Inc)
+    }
+}
+
+#[tokio::test]
+async fn actor_runs_on_dedicated_thread() {
+    let (addr, handle) = xtra::spawn_thread(Accumulator(0), Mailbox::unbounded());
+
+    addr.send(Inc).await.unwrap();
+    drop(addr);
+
+    assert_eq!(handle.await, Ok(1));
+}
+
+#[tokio::test]
+async fn blocking_pool_handles_messages_in_parallel() {
+    let (addr, handles) = xtra::spawn_blocking_pool(3, || Blocking, Mailbox::unbounded());
+    assert_eq!(handles.len(), 3);
+
+    let sleep = || addr.send(SleepFor(Duration::from_millis(100)));
+    let (a, b, c) = tokio::join!(sleep(), sleep(), sleep());
+    let threads = [a.unwrap(), b.unwrap(), c.unwrap()];
+
+    assert!(!threads.contains(&std::thread::current().id()));
+    assert_ne!(threads[0], threads[1]);
+    assert_ne!(threads[1], threads[2]);
+    assert_ne!(threads[0], threads[2]);
+
+    drop(addr);
+    for handle in handles {
+        assert_eq!(handle.await, Ok(()));
+    }
+}
+
+#[tokio::test]
+async fn dedicated_thread_handlers_may_block() {
+    let accumulator = xtra::spawn_tokio(Accumulator(0), Mailbox::unbounded());
+    let (addr, handle) = xtra::spawn_thread(Blocking, Mailbox::unbounded());
+
+    assert_eq!(
+        addr.send(IncBlocking(accumulator.clone())).await,
+        Ok(Ok(()))
+    );
+    assert_eq!(accumulator.send(Report).await.unwrap().0, 1);
+
+    drop(addr);
+    assert_eq!(handle.await, Ok(()));
+}
+
+#[test]
+#[should_panic(expected = "A blocking pool needs at least one thread")]
+fn blocking_pool_needs_a_thread() {
+    let _ = xtra::spawn_blocking_pool(0, || Blocking, Mailbox::unbounded());
+}
+
+#[derive(Default)]
+struct LocalCounter(Rc<RefCell<usize>>);
+
+impl LocalActor for LocalCounter {
+    type Stop = usize;
+
+    async fn stopped(self) -> usize {
+        *self.0.borrow()
+    }
+}
+
+impl LocalHandler<Inc> for LocalCounter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Local<Self>>) {
+        *self.0.borrow_mut() += 1;
+    }
+}
+
+impl LocalHandler<StopSelf> for LocalCounter {
+    type Return = ();
+
+    async fn handle(&mut self, _: StopSelf, ctx: &mut Context<Local<Self>>) {
+        ctx.stop_self();
+    }
+}
+
+impl LocalHandler<Explode> for LocalCounter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Explode, _ctx: &mut Context<Local<Self>>) {
+        panic!("handler panicked on purpose")
+    }
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn local_actor_can_be_messaged_from_other_threads() {
+    let local_set = LocalSet::new();
+    let (addr, mailbox) = Mailbox::unbounded();
+    let handle = LocalCounter::default().spawn_local(&local_set, mailbox);
+
+    let sender = tokio::spawn(async move {
+        for _ in 0..5 {
+            addr.send(Inc).await.unwrap();
+        }
+    });
+
+    let stop = local_set
+        .run_until(async move {
+            sender.await.unwrap();
+            handle.await
+        })
+        .await;
+
+    assert_eq!(stop, Ok(5));
+}
+
+#[tokio::test]
+async fn local_actor_can_stop_itself() {
+    let local_set = LocalSet::new();
+    let (addr, mailbox) = Mailbox::unbounded();
+    let handle = LocalCounter::default().spawn_local(&local_set, mailbox);
+
+    let stop = local_set
+        .run_until(async move {
+            addr.send(Inc).await.unwrap();
+            addr.send(StopSelf).await.unwrap();
+
+            handle.await
+        })
+        .await;
+
+    assert_eq!(stop, Ok(1));
+}
+
+#[tokio::test]
+async fn panicking_local_handler_interrupts_actor() {
+    let local_set = LocalSet::new();
+    let (addr, mailbox) = Mailbox::unbounded();
+    let handle = LocalCounter::default().spawn_local(&local_set, mailbox);
+
+    let stop = local_set
+        .run_until(async move {
+            assert_eq!(addr.send(Explode).await, Err(Error::HandlerPanicked));
+            handle.await
+        })
+        .await;
+
+    assert_eq!(stop, Err(Error::Interrupted));
+}
+
 #[tokio::test]
 async fn actor_can_be_restarted() {
     let (addr, mailbox) = Mailbox::unbounded();
@@ -641,7 +861,7 @@ async fn broadcast_tail_advances_bound_2() {
             .priority(0)
             .timeout(Duration::from_secs(2))
             .await,
-        Some(Ok(())),
+        Ok(()),
         "New broadcast message should be accepted when queue has space",
     );
 }
@@ -670,7 +890,7 @@ async fn broadcast_tail_does_not_advance_unless_both_handle() {
 
     assert_eq!(
         addr.broadcast(Message::Broadcast { priority: 0 }).priority(0).timeout(Duration::from_secs(2)).await,
-        None,
+        Err(Error::Timeout),
         "New broadcast message should NOT be accepted since the other actor has not yet handled the message",
     );
 }
@@ -862,6 +1082,134 @@ fn scoped_task() {
     assert_eq!(scoped.poll_unpin(&mut fut_ctx), Poll::Ready(None));
 }
 
+#[derive(Default, xtra::Actor)]
+struct Collector {
+    items: Vec<u32>,
+    finished: bool,
+}
+
+struct Collected;
+
+impl Handler<u32> for Collector {
+    type Return = ();
+
+    async fn handle(&mut self, item: u32, _ctx: &mut Context<Self>) {
+        self.items.push(item);
+    }
+}
+
+impl Handler<xtra::StreamFinished> for Collector {
+    type Return = ();
+
+    async fn handle(&mut self, _: xtra::StreamFinished, _ctx: &mut Context<Self>) {
+        self.finished = true;
+    }
+}
+
+impl Handler<Collected> for Collector {
+    type Return = (Vec<u32>, bool);
+
+    async fn handle(&mut self, _: Collected, _ctx: &mut Context<Self>) -> (Vec<u32>, bool) {
+        (self.items.clone(), self.finished)
+    }
+}
+
+#[tokio::test]
+async fn attached_stream_is_sent_to_actor() {
+    let addr = xtra::spawn_tokio(Collector::default(), Mailbox::unbounded());
+    let items = futures_util::stream::iter([1, 2, 3]);
+
+    assert_eq!(xtra::attach_stream(&addr, items).await, Some(()));
+    assert_eq!(addr.send(Collected).await.unwrap(), (vec![1, 2, 3], false));
+}
+
+#[tokio::test]
+async fn attached_stream_notifies_when_finished() {
+    let addr = xtra::spawn_tokio(Collector::default(), Mailbox::unbounded());
+    let items = futures_util::stream::iter([1, 2]);
+
+    let task = xtra::attach_stream(&addr, items)
+        .priority(1)
+        .notify_finished();
+
+    assert_eq!(task.await, Some(()));
+    assert_eq!(addr.send(Collected).await.unwrap(), (vec![1, 2], true));
+}
+
+#[tokio::test]
+async fn attached_stream_stops_with_actor() {
+    let addr = xtra::spawn_tokio(Collector::default(), Mailbox::unbounded());
+    let items = futures_util::stream::iter([1]).chain(futures_util::stream::pending());
+
+    let task = tokio::spawn(xtra::attach_stream(&addr, items).notify_finished());
+
+    // The attached stream does not keep the actor alive.
+    drop(addr);
+
+    let stopped = task
+        .timeout(Duration::from_secs(1))
+        .await
+        .expect("task to stop with the actor");
+    assert_eq!(stopped.unwrap(), None);
+}
+
+#[derive(xtra::Actor)]
+struct Doubler;
+
+impl Handler<u32> for Doubler {
+    type Return = u32;
+
+    async fn handle(&mut self, number: u32, _ctx: &mut Context<Self>) -> u32 {
+        number * 2
+    }
+}
+
+#[tokio::test]
+async fn reply_stream_yields_replies_in_order() {
+    let addr = xtra::spawn_tokio(Doubler, Mailbox::unbounded());
+    let requests = futures_util::stream::iter(1..=5);
+
+    let replies = addr
+        .into_stream_of(requests)
+        .concurrency(3)
+        .collect::<Vec<_>>()
+        .await;
+
+    assert_eq!(replies, vec![Ok(2), Ok(4), Ok(6), Ok(8), Ok(10)]);
+}
+
+#[test]
+fn reply_stream_limits_requests_in_flight() {
+    let (addr, _mailbox) = Mailbox::<Doubler>::unbounded();
+    let requests = futures_util::stream::iter(1..=5);
+    let mut replies = addr.clone().into_stream_of(requests);
+    let mut ctx = std::task::Context::from_waker(noop_waker_ref());
+
+    assert!(replies.poll_next_unpin(&mut ctx).is_pending());
+    assert_eq!(addr.len(), 1);
+
+    let mut replies = replies.concurrency(3);
+    assert!(replies.poll_next_unpin(&mut ctx).is_pending());
+    assert_eq!(addr.len(), 3);
+}
+
+#[test]
+fn reply_stream_yields_errors_if_actor_stopped() {
+    let (addr, mailbox) = Mailbox::<Doubler>::unbounded();
+    drop(mailbox);
+
+    let replies = addr
+        .into_stream_of(futures_util::stream::iter([1, 2]))
+        .collect::<Vec<_>>()
+        .now_or_never()
+        .unwrap();
+
+    assert_eq!(
+        replies,
+        vec![Err(Error::Disconnected), Err(Error::Disconnected)]
+    );
+}
+
 #[test]
 fn test_addr_cmp_hash_eq() {
     let addr1 = Mailbox::<Greeter>::unbounded().0;
@@ -986,3 +1334,359 @@ async fn receive_future_can_dispatch_in_one_poll_after_it_has_been_polled() {
 
     assert!(receive_future.now_or_never().is_some())
 }
+
+struct Panic;
+
+#[derive(Default)]
+struct Isolated {
+    handled: usize,
+    action: Option<PanicAction>,
+}
+
+impl Actor for Isolated {
+    type Stop = usize;
+
+    async fn stopped(self) -> usize {
+        self.handled
+    }
+
+    async fn on_panic(&mut self, _: Box<dyn std::any::Any + Send>) -> PanicAction {
+        self.action.unwrap_or(PanicAction::Continue)
+    }
+}
+
+impl Handler<Panic> for Isolated {
+    type Return = ();
+
+    async fn handle(&mut self, _: Panic, _ctx: &mut Context<Self>) {
+        panic!("handler panicked on purpose")
+    }
+}
+
+impl Handler<Inc> for Isolated {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) -> usize {
+        self.handled += 1;
+        self.handled
+    }
+}
+
+#[tokio::test]
+async fn actor_keeps_running_after_handler_panic() {
+    let address = xtra::spawn_tokio(Isolated::default(), Mailbox::unbounded());
+
+    assert_eq!(address.send(Inc).await, Ok(1));
+    assert_eq!(address.send(Panic).await, Err(Error::HandlerPanicked));
+    assert_eq!(
+        address.send(Inc).await,
+        Ok(2),
+        "actor should keep its state"
+    );
+}
+
+#[tokio::test]
+async fn actor_stops_after_handler_panic_if_requested() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let actor = Isolated {
+        handled: 0,
+        action: Some(PanicAction::Stop),
+    };
+    let stopped = tokio::spawn(xtra::run(mailbox, actor));
+
+    assert_eq!(address.send(Panic).await, Err(Error::HandlerPanicked));
+    assert_eq!(stopped.await.unwrap(), 0);
+    assert!(!address.is_connected());
+}
+
+#[tokio::test]
+async fn handler_panic_propagates_by_default() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let task = tokio::spawn(xtra::run(mailbox, Greeter));
+
+    assert_eq!(
+        address.send(Explode).await,
+        Err(Error::HandlerPanicked),
+        "sender should be notified before the panic propagates"
+    );
+    assert!(task.await.unwrap_err().is_panic());
+}
+
+struct Explode;
+
+impl Handler<Explode> for Greeter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Explode, _ctx: &mut Context<Self>) {
+        panic!("handler panicked on purpose")
+    }
+}
+
+#[derive(Default, xtra::Actor)]
+struct Sleeper {
+    handled: usize,
+}
+
+impl Handler<Duration> for Sleeper {
+    type Return = ();
+
+    async fn handle(&mut self, duration: Duration, _: &mut Context<Self>) {
+        tokio::time::sleep(duration).await;
+        self.handled += 1;
+    }
+}
+
+impl Handler<Report> for Sleeper {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Report, _: &mut Context<Self>) -> usize {
+        self.handled
+    }
+}
+
+/// Keeps the [`Sleeper`] busy until `release` resolves, signalling `started` once it is handled.
+struct Hold {
+    started: tokio::sync::oneshot::Sender<()>,
+    release: tokio::sync::oneshot::Receiver<()>,
+}
+
+impl Handler<Hold> for Sleeper {
+    type Return = ();
+
+    async fn handle(&mut self, hold: Hold, _: &mut Context<Self>) {
+        let _ = hold.started.send(());
+        let _ = hold.release.await;
+        self.handled += 1;
+    }
+}
+
+/// Make the sleeper handle a [`Hold`] message, returning once it does so. The sleeper is busy
+/// until the returned sender is used or dropped.
+async fn hold(
+    address: &Address<Sleeper>,
+) -> (tokio::sync::oneshot::Sender<()>, xtra::Receiver<()>) {
+    let (started, started_rx) = tokio::sync::oneshot::channel();
+    let (release, release_rx) = tokio::sync::oneshot::channel();
+
+    let busy = address
+        .send(Hold {
+            started,
+            release: release_rx,
+        })
+        .detach()
+        .await
+        .unwrap();
+    started_rx.await.unwrap();
+
+    (release, busy)
+}
+
+#[tokio::test]
+async fn send_times_out_while_handler_runs() {
+    let address = xtra::spawn_tokio(LongRunningHandler, Mailbox::unbounded());
+
+    assert_eq!(
+        address
+            .send(Duration::from_secs(3))
+            .timeout(Duration::from_millis(50))
+            .await,
+        Err(Error::Timeout)
+    );
+}
+
+#[tokio::test]
+async fn detached_receiver_inherits_timeout() {
+    let address = xtra::spawn_tokio(LongRunningHandler, Mailbox::unbounded());
+
+    let receiver = address
+        .send(Duration::from_secs(3))
+        .timeout(Duration::from_millis(50))
+        .detach()
+        .await
+        .unwrap();
+
+    assert_eq!(receiver.await, Err(Error::Timeout));
+}
+
+#[tokio::test]
+async fn timed_out_message_is_withdrawn_before_dispatch() {
+    let address = xtra::spawn_tokio(Sleeper::default(), Mailbox::unbounded());
+    let (release, busy) = hold(&address).await;
+
+    assert_eq!(
+        address
+            .send(Duration::ZERO)
+            .timeout(Duration::from_millis(50))
+            .await,
+        Err(Error::Timeout)
+    );
+
+    release.send(()).unwrap();
+    busy.await.unwrap();
+    assert_eq!(
+        address.send(Report).await,
+        Ok(1),
+        "timed out message should not be handled"
+    );
+}
+
+#[tokio::test]
+async fn send_times_out_while_mailbox_is_full() {
+    let address = xtra::spawn_tokio(Sleeper::default(), Mailbox::bounded(1));
+    let (release, busy) = hold(&address).await;
+    let queued = address.send(Duration::ZERO).detach().await.unwrap();
+
+    assert_eq!(
+        address
+            .send(Duration::ZERO)
+            .timeout(Duration::from_millis(50))
+            .await,
+        Err(Error::Timeout)
+    );
+
+    release.send(()).unwrap();
+    busy.await.unwrap();
+    queued.await.unwrap();
+    assert_eq!(
+        address.send(Report).await,
+        Ok(2),
+        "timed out message should not be handled"
+    );
+}
+
+#[tokio::test(start_paused = true)]
+async fn expired_message_is_discarded_at_dispatch() {
+    let address = xtra::spawn_tokio(Sleeper::default(), Mailbox::unbounded());
+    let (release, busy) = hold(&address).await;
+
+    // The mailbox does not read the clock of tokio, so pausing it cannot make a longer
+    // time-to-live pass. A time-to-live of zero has passed by the time the message is dispatched.
+    let expired = address
+        .send(Duration::ZERO)
+        .ttl(Duration::ZERO)
+        .detach()
+        .await
+        .unwrap();
+
+    release.send(()).unwrap();
+    busy.await.unwrap();
+    assert_eq!(expired.await, Err(Error::Expired));
+    assert_eq!(
+        address.send(Report).await,
+        Ok(1),
+        "expired message should not be handled"
+    );
+}
+
+#[tokio::test]
+async fn ttl_does_not_bound_handler() {
+    let address = xtra::spawn_tokio(Sleeper::default(), Mailbox::unbounded());
+
+    assert_eq!(
+        address
+            .send(Duration::from_millis(50))
+            .ttl(Duration::from_millis(10))
+            .await,
+        Ok(())
+    );
+}
+
+#[tokio::test]
+async fn try_send_queues_message_without_waiting() {
+    let (address, mailbox) = Mailbox::bounded(1);
+
+    let receiver = address.try_send(Inc).unwrap();
+    assert!(matches!(
+        address.try_send(Inc),
+        Err(TrySendError::Full(Inc))
+    ));
+
+    let mut accumulator = Accumulator(0);
+    xtra::yield_once(&mailbox, &mut accumulator).await;
+
+    assert_eq!(receiver.await, Ok(()));
+    assert_eq!(accumulator, Accumulator(1));
+    assert!(address.try_send(Inc).is_ok());
+}
+
+#[tokio::test]
+async fn try_send_returns_message_if_disconnected() {
+    let (address, mailbox) = Mailbox::<Accumulator>::unbounded();
+    drop(mailbox);
+
+    let error = address.try_send(Inc).unwrap_err();
+
+    assert!(error.is_disconnected());
+    assert!(matches!(error.into_inner(), Inc));
+}
+
+#[tokio::test]
+async fn try_send_on_message_channel() {
+    let address = xtra::spawn_tokio(Accumulator(0), Mailbox::bounded(1));
+    let channel = MessageChannel::<Report, Accumulator, _>::new(address.downgrade());
+
+    let receiver = channel.try_send(Report).unwrap();
+    assert_eq!(receiver.await, Ok(Accumulator(0)));
+
+    drop(address);
+    channel.join().await;
+
+    assert!(channel.try_send(Report).unwrap_err().is_disconnected());
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn send_blocking_waits_for_return_value() {
+    let address = xtra::spawn_tokio(Accumulator(0), Mailbox::unbounded());
+
+    let report = tokio::task::spawn_blocking(move || {
+        address.send_blocking(Inc).unwrap();
+        let receiver = address.send(Inc).detach().wait().unwrap();
+        receiver.wait().unwrap();
+
+        address.send(Report).wait()
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(report, Ok(Accumulator(2)));
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn wait_respects_timeout() {
+    let address = xtra::spawn_tokio(Sleeper::default(), Mailbox::unbounded());
+
+    let result = tokio::task::spawn_blocking(move || {
+        address
+            .send(Duration::from_secs(10))
+            .timeout(Duration::from_millis(20))
+            .wait()
+    })
+    .await
+    .unwrap();
+
+    assert_eq!(result, Err(Error::Timeout));
+}
+
+struct BlockOn(Address<Accumulator>);
+
+#[derive(xtra::Actor)]
+struct Blocker;
+
+impl Handler<BlockOn> for Blocker {
+    type Return = ();
+
+    async fn handle(&mut self, BlockOn(address): BlockOn, _: &mut Context<Self>) {
+        let _ = address.send_blocking(Inc);
+    }
+}
+
+#[tokio::test]
+async fn blocking_within_handler_panics() {
+    let accumulator = xtra::spawn_tokio(Accumulator(0), Mailbox::unbounded());
+    let address = xtra::spawn_tokio(Blocker, Mailbox::unbounded());
+
+    assert_eq!(
+        address.send(BlockOn(accumulator)).await,
+        Err(Error::HandlerPanicked)
+    );
+}
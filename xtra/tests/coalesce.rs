@@ -0,0 +1,257 @@
+use futures_util::FutureExt;
+use xtra::prelude::*;
+use xtra::{Coalesce, Error};
+
+#[derive(Default, xtra::Actor)]
+struct Log(Vec<(u32, u32)>);
+
+/// Replaces queued messages with the same key.
+struct Set {
+    key: u32,
+    value: u32,
+}
+
+/// Adds up the values of queued messages with the same key.
+struct Add {
+    key: u32,
+    value: u32,
+}
+
+struct Get;
+
+/// Panics in the handler.
+struct Explode {
+    key: u32,
+}
+
+impl Coalesce for Set {
+    type Key = u32;
+
+    fn coalesce_key(&self) -> u32 {
+        self.key
+    }
+}
+
+impl Coalesce for Add {
+    type Key = u32;
+
+    fn coalesce_key(&self) -> u32 {
+        self.key
+    }
+
+    fn coalesce(&mut self, newer: Self) {
+        self.value += newer.value;
+    }
+}
+
+impl Coalesce for Explode {
+    type Key = u32;
+
+    fn coalesce_key(&self) -> u32 {
+        self.key
+    }
+}
+
+impl Handler<Set> for Log {
+    type Return = usize;
+
+    async fn handle(&mut self, set: Set, _ctx: &mut Context<Self>) -> usize {
+        self.0.push((set.key, set.value));
+        self.0.len()
+    }
+}
+
+impl Handler<Add> for Log {
+    type Return = usize;
+
+    async fn handle(&mut self, add: Add, _ctx: &mut Context<Self>) -> usize {
+        self.0.push((add.key, add.value));
+        self.0.len()
+    }
+}
+
+impl Handler<Explode> for Log {
+    type Return = ();
+
+    async fn handle(&mut self, _: Explode, _ctx: &mut Context<Self>) {
+        panic!("Boom!")
+    }
+}
+
+impl Handler<Get> for Log {
+    type Return = Vec<(u32, u32)>;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> Vec<(u32, u32)> {
+        self.0.clone()
+    }
+}
+
+#[tokio::test]
+async fn newer_message_replaces_queued_one() {
+    let (address, mailbox) = Mailbox::<Log>::unbounded();
+
+    let first = address
+        .send_coalesced(Set { key: 1, value: 1 })
+        .detach()
+        .await
+        .unwrap();
+    let other = address
+        .send_coalesced(Set { key: 2, value: 2 })
+        .detach()
+        .await
+        .unwrap();
+    let second = address
+        .send_coalesced(Set { key: 1, value: 3 })
+        .detach()
+        .await
+        .unwrap();
+    assert_eq!(address.len(), 2);
+
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    assert_eq!(first.await, Ok(1));
+    assert_eq!(second.await, Ok(1));
+    assert_eq!(other.await, Ok(2));
+    assert_eq!(address.send(Get).await.unwrap(), vec![(1, 3), (2, 2)]);
+}
+
+#[tokio::test]
+async fn custom_coalesce_merges_messages() {
+    let (address, mailbox) = Mailbox::<Log>::unbounded();
+
+    for value in 1..=3 {
+        let _ = address
+            .send_coalesced(Add { key: 1, value })
+            .detach()
+            .await
+            .unwrap();
+    }
+    assert_eq!(address.len(), 1);
+
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    assert_eq!(address.send(Get).await.unwrap(), vec![(1, 6)]);
+}
+
+#[tokio::test]
+async fn merged_message_keeps_higher_priority() {
+    let (address, mailbox) = Mailbox::<Log>::unbounded();
+
+    let _ = address
+        .send_coalesced(Set { key: 1, value: 1 })
+        .detach()
+        .await
+        .unwrap();
+    let _ = address
+        .send_coalesced(Set { key: 2, value: 2 })
+        .detach()
+        .await
+        .unwrap();
+    let _ = address
+        .send_coalesced(Set { key: 2, value: 3 })
+        .priority(1)
+        .detach()
+        .await
+        .unwrap();
+
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    assert_eq!(address.send(Get).await.unwrap(), vec![(2, 3), (1, 1)]);
+}
+
+#[tokio::test]
+async fn plain_send_is_not_coalesced() {
+    let (address, mailbox) = Mailbox::<Log>::unbounded();
+
+    let _ = address
+        .send_coalesced(Set { key: 1, value: 1 })
+        .detach()
+        .await
+        .unwrap();
+    let _ = address
+        .send(Set { key: 1, value: 2 })
+        .detach()
+        .await
+        .unwrap();
+    let _ = address
+        .send_coalesced(Set { key: 1, value: 3 })
+        .detach()
+        .await
+        .unwrap();
+    assert_eq!(address.len(), 2);
+
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    assert_eq!(address.send(Get).await.unwrap(), vec![(1, 3), (1, 2)]);
+}
+
+#[tokio::test]
+async fn coalescing_does_not_wait_for_space() {
+    let (address, mailbox) = Mailbox::<Log>::bounded(1);
+
+    let first = address
+        .send_coalesced(Set { key: 1, value: 1 })
+        .detach()
+        .await
+        .unwrap();
+    let second = address
+        .send_coalesced(Set { key: 1, value: 2 })
+        .detach()
+        .now_or_never()
+        .expect("message to be merged without waiting")
+        .unwrap();
+    assert!(address
+        .send_coalesced(Set { key: 2, value: 2 })
+        .detach()
+        .now_or_never()
+        .is_none());
+
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    assert_eq!(first.await, Ok(1));
+    assert_eq!(second.await, Ok(1));
+}
+
+#[tokio::test]
+async fn merged_senders_are_interrupted_if_actor_stops() {
+    let (address, mailbox) = Mailbox::<Log>::unbounded();
+
+    let first = address
+        .send_coalesced(Set { key: 1, value: 1 })
+        .detach()
+        .await
+        .unwrap();
+    let second = address
+        .send_coalesced(Set { key: 1, value: 2 })
+        .detach()
+        .await
+        .unwrap();
+
+    drop(mailbox);
+
+    assert_eq!(first.await, Err(Error::Interrupted));
+    assert_eq!(second.await, Err(Error::Interrupted));
+}
+
+#[tokio::test]
+async fn merged_senders_are_notified_of_panics() {
+    let (address, mailbox) = Mailbox::<Log>::unbounded();
+
+    let first = address
+        .send_coalesced(Explode { key: 1 })
+        .detach()
+        .await
+        .unwrap();
+    let second = address
+        .send_coalesced(Explode { key: 1 })
+        .detach()
+        .await
+        .unwrap();
+    assert_eq!(address.len(), 1);
+
+    // `Log` does not override `on_panic`, so the panic tears down the actor.
+    xtra::spawn_tokio(Log::default(), (address, mailbox));
+
+    assert_eq!(first.await, Err(Error::HandlerPanicked));
+    assert_eq!(second.await, Err(Error::HandlerPanicked));
+}
@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use xtra::dead_letter::{DeadLetter, Reason};
+use xtra::prelude::*;
+use xtra::{Error, WeakAddress};
+
+#[derive(Default, xtra::Actor)]
+struct Counter(usize);
+
+struct Inc;
+
+struct Get;
+
+impl Handler<Inc> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Get> for Counter {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+        self.0
+    }
+}
+
+fn mailbox_with_dead_letters() -> (
+    Address<Counter>,
+    Mailbox<Counter>,
+    Arc<Mutex<Vec<DeadLetter>>>,
+) {
+    let dead_letters = Arc::new(Mutex::new(Vec::new()));
+    let (address, mailbox) = Mailbox::builder()
+        .dead_letters({
+            let dead_letters = dead_letters.clone();
+            move |letter| dead_letters.lock().unwrap().push(letter)
+        })
+        .build();
+
+    (address, mailbox, dead_letters)
+}
+
+fn dead_letter(priority: u32, reason: Reason) -> DeadLetter {
+    DeadLetter {
+        actor: std::any::type_name::<Counter>(),
+        message: std::any::type_name::<Inc>(),
+        priority,
+        reason,
+    }
+}
+
+#[tokio::test]
+async fn queued_messages_are_reported_on_shutdown() {
+    let (address, mailbox, dead_letters) = mailbox_with_dead_letters();
+
+    let _ = address.send(Inc).priority(3).detach().await.unwrap();
+    drop(mailbox);
+
+    assert_eq!(
+        *dead_letters.lock().unwrap(),
+        vec![dead_letter(3, Reason::Shutdown)]
+    );
+}
+
+#[tokio::test]
+async fn messages_to_disconnected_actor_are_reported() {
+    let (address, mailbox, dead_letters) = mailbox_with_dead_letters();
+    drop(mailbox);
+
+    assert_eq!(address.send(Inc).await, Err(Error::Disconnected));
+    assert_eq!(
+        *dead_letters.lock().unwrap(),
+        vec![dead_letter(0, Reason::Disconnected)]
+    );
+}
+
+#[tokio::test]
+async fn dead_letters_from_within_the_sink_are_dropped() {
+    let dead_letters = Arc::new(Mutex::new(Vec::new()));
+    let forward_to = Arc::new(Mutex::new(None::<WeakAddress<Counter>>));
+    let (address, mailbox) = Mailbox::builder()
+        .dead_letters({
+            let dead_letters = dead_letters.clone();
+            let forward_to = forward_to.clone();
+            move |letter| {
+                dead_letters.lock().unwrap().push(letter);
+
+                // Fails as the actor is disconnected, which must not deadlock the sink.
+                if let Some(address) = forward_to.lock().unwrap().as_ref() {
+                    let _ = address.send(Inc).now_or_never();
+                }
+            }
+        })
+        .build();
+    *forward_to.lock().unwrap() = Some(address.downgrade());
+    drop(mailbox);
+
+    assert_eq!(address.send(Inc).await, Err(Error::Disconnected));
+    assert_eq!(
+        *dead_letters.lock().unwrap(),
+        vec![dead_letter(0, Reason::Disconnected)]
+    );
+}
+
+#[tokio::test]
+async fn timed_out_messages_are_reported() {
+    let (address, mailbox, dead_letters) = mailbox_with_dead_letters();
+
+    let receiver = address
+        .send(Inc)
+        .timeout(Duration::from_millis(10))
+        .detach()
+        .await
+        .unwrap();
+    assert_eq!(receiver.await, Err(Error::Timeout));
+
+    tokio::spawn(xtra::run(mailbox, Counter::default()));
+
+    assert_eq!(address.send(Get).await, Ok(0));
+    assert_eq!(
+        *dead_letters.lock().unwrap(),
+        vec![dead_letter(0, Reason::Timeout)]
+    );
+}
+
+#[tokio::test]
+async fn handled_messages_are_not_reported() {
+    let (address, mailbox, dead_letters) = mailbox_with_dead_letters();
+    tokio::spawn(xtra::run(mailbox, Counter::default()));
+
+    address.send(Inc).await.unwrap();
+    drop(address);
+
+    assert!(dead_letters.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn expired_messages_are_reported_to_hook() {
+    let expired = Arc::new(Mutex::new(Vec::new()));
+    let dead_letters = Arc::new(Mutex::new(Vec::new()));
+    let (address, mailbox) = Mailbox::builder()
+        .on_expired({
+            let expired = expired.clone();
+            move |letter| expired.lock().unwrap().push(letter)
+        })
+        .dead_letters({
+            let dead_letters = dead_letters.clone();
+            move |letter| dead_letters.lock().unwrap().push(letter)
+        })
+        .build();
+
+    let receiver = address
+        .send(Inc)
+        .ttl(Duration::from_millis(10))
+        .detach()
+        .await
+        .unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    tokio::spawn(xtra::run(mailbox, Counter::default()));
+
+    assert_eq!(address.send(Get).await, Ok(0));
+    assert_eq!(receiver.await, Err(Error::Expired));
+    assert_eq!(
+        *expired.lock().unwrap(),
+        vec![dead_letter(0, Reason::Expired)]
+    );
+    assert_eq!(
+        *dead_letters.lock().unwrap(),
+        vec![dead_letter(0, Reason::Expired)]
+    );
+}
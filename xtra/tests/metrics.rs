@@ -0,0 +1,196 @@
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use metrics_util::debugging::{DebugValue, DebuggingRecorder};
+use xtra::metrics::{Histogram, QueueDepth};
+use xtra::prelude::*;
+use xtra::Error;
+
+#[derive(Default, xtra::Actor)]
+struct Counter(usize);
+
+struct Inc;
+
+#[derive(Clone)]
+struct Ping;
+
+struct Sleep(Duration);
+
+struct Explode;
+
+impl Handler<Inc> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Ping> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {}
+}
+
+impl Handler<Sleep> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, sleep: Sleep, _ctx: &mut Context<Self>) {
+        tokio::time::sleep(sleep.0).await;
+    }
+}
+
+impl Handler<Explode> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Explode, _ctx: &mut Context<Self>) {
+        panic!("Boom!")
+    }
+}
+
+#[tokio::test]
+async fn handled_messages_are_counted_per_type() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    for _ in 0..3 {
+        address.send(Inc).await.unwrap();
+    }
+    address.send(Ping).await.unwrap();
+
+    let metrics = address.metrics();
+
+    assert_eq!(metrics.actor, std::any::type_name::<Counter>());
+    assert_eq!(metrics.handled(), 4);
+    assert_eq!(metrics.message::<Inc>().unwrap().handled, 3);
+    assert_eq!(metrics.message::<Inc>().unwrap().latency.count(), 3);
+    assert_eq!(metrics.message::<Ping>().unwrap().handled, 1);
+    assert!(metrics.message::<Sleep>().is_none());
+}
+
+#[tokio::test]
+async fn handler_duration_is_recorded() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    address
+        .send(Sleep(Duration::from_millis(20)))
+        .await
+        .unwrap();
+
+    let metrics = address.metrics();
+    let sleep = metrics.message::<Sleep>().unwrap();
+
+    assert_eq!(sleep.handler_duration.count(), 1);
+    assert!(sleep.handler_duration.max() >= Duration::from_millis(20));
+}
+
+#[tokio::test]
+async fn latency_includes_time_spent_in_mailbox() {
+    let (address, mailbox) = Mailbox::unbounded();
+
+    address.send(Inc).detach().await.unwrap();
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    xtra::spawn_tokio(Counter::default(), (address.clone(), mailbox));
+    address.send(Ping).await.unwrap();
+
+    let metrics = address.metrics();
+
+    assert!(metrics.message::<Inc>().unwrap().latency.max() >= Duration::from_millis(20));
+}
+
+#[tokio::test]
+async fn panicking_handlers_are_recorded() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    assert_eq!(address.send(Explode).await, Err(Error::HandlerPanicked));
+    assert_eq!(address.metrics().message::<Explode>().unwrap().handled, 1);
+}
+
+#[tokio::test]
+async fn queue_depth_is_reported_per_queue() {
+    let (address, _mailbox) = Mailbox::<Counter>::bounded(1);
+
+    address.send(Inc).detach().await.unwrap();
+    address.broadcast(Ping).await.unwrap();
+
+    let mut waiting = Box::pin(address.send(Inc).detach());
+    assert!((&mut waiting).now_or_never().is_none());
+
+    assert_eq!(
+        address.metrics().queue,
+        QueueDepth {
+            unicast: 1,
+            broadcast: 1,
+            waiting_senders: 1,
+        }
+    );
+
+    drop(waiting);
+
+    assert_eq!(address.metrics().queue.waiting_senders, 0);
+}
+
+#[test]
+fn histogram_quantiles_are_bounded_by_buckets() {
+    let mut histogram = Histogram::default();
+    assert_eq!(histogram.quantile(0.5), None);
+    assert_eq!(histogram.mean(), None);
+
+    for micros in [0, 3, 3, 100] {
+        histogram.record(Duration::from_micros(micros));
+    }
+
+    assert_eq!(histogram.count(), 4);
+    assert_eq!(histogram.sum(), Duration::from_micros(106));
+    assert_eq!(histogram.mean(), Some(Duration::from_nanos(26_500)));
+    assert_eq!(histogram.quantile(0.0), Some(Duration::from_micros(1)));
+    assert_eq!(histogram.quantile(0.5), Some(Duration::from_micros(4)));
+    assert_eq!(histogram.quantile(1.0), Some(Duration::from_micros(100)));
+
+    let buckets = histogram.buckets().collect::<Vec<_>>();
+    assert_eq!(buckets[0], (Duration::from_micros(1), 1));
+    assert_eq!(buckets[2], (Duration::from_micros(4), 2));
+    assert_eq!(buckets[7], (Duration::from_micros(128), 1));
+    assert_eq!(buckets.last().unwrap().0, Duration::MAX);
+}
+
+#[tokio::test]
+async fn metrics_are_exported_to_facade() {
+    let recorder = DebuggingRecorder::new();
+    let snapshotter = recorder.snapshotter();
+    assert!(recorder.install().is_ok());
+
+    let (address, mailbox) = Mailbox::builder().export_metrics("counter").build();
+    let address = xtra::spawn_tokio(Counter::default(), (address, mailbox));
+
+    address.send(Inc).await.unwrap();
+    address.send(Inc).await.unwrap();
+
+    let metrics = snapshotter.snapshot().into_vec();
+    let find = |name: &str| {
+        metrics
+            .iter()
+            .find(|(key, ..)| {
+                key.key().name() == name
+                    && key
+                        .key()
+                        .labels()
+                        .any(|label| label.key() == "actor" && label.value() == "counter")
+            })
+            .map(|(.., value)| value)
+            .unwrap_or_else(|| panic!("{name} was not exported"))
+    };
+
+    assert_eq!(find("xtra_messages_handled_total"), &DebugValue::Counter(2));
+    assert!(matches!(
+        find("xtra_message_latency_seconds"),
+        DebugValue::Histogram(samples) if samples.len() == 2
+    ));
+    assert!(matches!(
+        find("xtra_handler_duration_seconds"),
+        DebugValue::Histogram(samples) if samples.len() == 2
+    ));
+    assert!(matches!(
+        find("xtra_mailbox_messages"),
+        DebugValue::Gauge(_)
+    ));
+}
@@ -0,0 +1,139 @@
+use std::sync::{Arc, Mutex};
+
+use futures_util::FutureExt;
+use xtra::dead_letter::{DeadLetter, Reason};
+use xtra::prelude::*;
+use xtra::{Error, OverflowPolicy};
+
+#[derive(Default, xtra::Actor)]
+struct Log(Vec<u32>);
+
+struct Push(u32);
+
+struct Get;
+
+impl Handler<Push> for Log {
+    type Return = ();
+
+    async fn handle(&mut self, push: Push, _ctx: &mut Context<Self>) {
+        self.0.push(push.0);
+    }
+}
+
+impl Handler<Get> for Log {
+    type Return = Vec<u32>;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> Vec<u32> {
+        self.0.clone()
+    }
+}
+
+fn bounded(policy: OverflowPolicy) -> (Address<Log>, Mailbox<Log>, Arc<Mutex<Vec<DeadLetter>>>) {
+    let dead_letters = Arc::new(Mutex::new(Vec::new()));
+    let (address, mailbox) = Mailbox::builder()
+        .capacity(2)
+        .overflow_policy(policy)
+        .dead_letters({
+            let dead_letters = dead_letters.clone();
+            move |letter| dead_letters.lock().unwrap().push(letter)
+        })
+        .build();
+
+    (address, mailbox, dead_letters)
+}
+
+/// Start the actor on the mailbox and return the values it handled.
+async fn handled(address: &Address<Log>, mailbox: Mailbox<Log>) -> Vec<u32> {
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    address.send(Get).await.unwrap()
+}
+
+fn dropped(priority: u32) -> DeadLetter {
+    DeadLetter {
+        actor: std::any::type_name::<Log>(),
+        message: std::any::type_name::<Push>(),
+        priority,
+        reason: Reason::DroppedByPolicy,
+    }
+}
+
+#[tokio::test]
+async fn block_waits_for_space() {
+    let (address, _mailbox, _) = bounded(OverflowPolicy::Block);
+
+    address.send(Push(1)).detach().await.unwrap();
+    address.send(Push(2)).detach().await.unwrap();
+
+    assert!(address.send(Push(3)).detach().now_or_never().is_none());
+}
+
+#[tokio::test]
+async fn drop_newest_drops_message_being_sent() {
+    let (address, mailbox, dead_letters) = bounded(OverflowPolicy::DropNewest);
+
+    address.send(Push(1)).detach().await.unwrap();
+    address.send(Push(2)).detach().await.unwrap();
+    let third = address.send(Push(3)).detach().await.unwrap();
+
+    assert_eq!(third.await, Err(Error::Interrupted));
+    assert_eq!(*dead_letters.lock().unwrap(), vec![dropped(0)]);
+    assert_eq!(handled(&address, mailbox).await, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn drop_oldest_makes_space_for_message_being_sent() {
+    let (address, mailbox, dead_letters) = bounded(OverflowPolicy::DropOldest);
+
+    let first = address.send(Push(1)).priority(1).detach().await.unwrap();
+    address.send(Push(2)).detach().await.unwrap();
+    address.send(Push(3)).detach().await.unwrap();
+
+    assert_eq!(first.await, Err(Error::Interrupted));
+    assert_eq!(*dead_letters.lock().unwrap(), vec![dropped(1)]);
+    assert_eq!(handled(&address, mailbox).await, vec![2, 3]);
+}
+
+#[tokio::test]
+async fn reject_fails_sending() {
+    let (address, mailbox, dead_letters) = bounded(OverflowPolicy::Reject);
+
+    address.send(Push(1)).detach().await.unwrap();
+    address.send(Push(2)).detach().await.unwrap();
+
+    assert_eq!(
+        address.send(Push(3)).detach().await.err(),
+        Some(Error::MailboxFull)
+    );
+    assert_eq!(address.send(Push(4)).await, Err(Error::MailboxFull));
+    assert_eq!(*dead_letters.lock().unwrap(), vec![dropped(0), dropped(0)]);
+    assert_eq!(handled(&address, mailbox).await, vec![1, 2]);
+}
+
+#[tokio::test]
+async fn drop_lowest_priority_drops_message_handled_last() {
+    let (address, mailbox, dead_letters) = bounded(OverflowPolicy::DropLowestPriority);
+
+    address.send(Push(1)).priority(1).detach().await.unwrap();
+    address.send(Push(2)).detach().await.unwrap();
+    address.send(Push(3)).priority(2).detach().await.unwrap(); // Drops 2
+    address.send(Push(4)).priority(1).detach().await.unwrap(); // Dropped, as 1 was sent first
+
+    assert_eq!(*dead_letters.lock().unwrap(), vec![dropped(0), dropped(1)]);
+    assert_eq!(handled(&address, mailbox).await, vec![3, 1]);
+}
+
+#[tokio::test]
+async fn messages_of_same_priority_are_handled_in_send_order() {
+    let (address, mailbox) = Mailbox::unbounded();
+
+    for i in 0..10 {
+        address.send(Push(i)).detach().await.unwrap();
+    }
+    address.send(Push(10)).priority(1).detach().await.unwrap();
+
+    let mut expected = vec![10];
+    expected.extend(0..10);
+
+    assert_eq!(handled(&address, mailbox).await, expected);
+}
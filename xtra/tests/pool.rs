@@ -0,0 +1,184 @@
+use std::collections::HashSet;
+
+use xtra::pool::{ActorPool, ConsistentHash, LeastLoaded, Random, RoundRobin, RoutingKey};
+use xtra::prelude::*;
+use xtra::Error;
+
+#[derive(xtra::Actor)]
+struct Worker {
+    id: usize,
+    seen: usize,
+}
+
+impl Worker {
+    fn new(id: usize) -> Self {
+        Worker { id, seen: 0 }
+    }
+}
+
+struct WhoAmI;
+
+#[derive(Clone)]
+struct Inc;
+
+struct Seen;
+
+struct ByUser(u32);
+
+impl RoutingKey for ByUser {
+    type Key = u32;
+
+    fn routing_key(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Handler<WhoAmI> for Worker {
+    type Return = usize;
+
+    async fn handle(&mut self, _: WhoAmI, _ctx: &mut Context<Self>) -> usize {
+        self.id
+    }
+}
+
+impl Handler<Inc> for Worker {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.seen += 1;
+    }
+}
+
+impl Handler<Seen> for Worker {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Seen, _ctx: &mut Context<Self>) -> usize {
+        self.seen
+    }
+}
+
+impl Handler<ByUser> for Worker {
+    type Return = usize;
+
+    async fn handle(&mut self, _: ByUser, _ctx: &mut Context<Self>) -> usize {
+        self.id
+    }
+}
+
+fn spawn_workers(count: usize) -> impl Iterator<Item = Address<Worker>> {
+    (0..count).map(|id| xtra::spawn_tokio(Worker::new(id), Mailbox::unbounded()))
+}
+
+#[tokio::test]
+async fn round_robin_visits_workers_in_turn() {
+    let pool = ActorPool::new(RoundRobin::default(), spawn_workers(3));
+
+    let mut ids = Vec::new();
+    for _ in 0..6 {
+        ids.push(pool.send(WhoAmI).await.unwrap());
+    }
+
+    assert_eq!(ids, vec![0, 1, 2, 0, 1, 2]);
+}
+
+#[tokio::test]
+async fn least_loaded_picks_emptiest_mailbox() {
+    let (busy, _busy_mailbox) = Mailbox::<Worker>::unbounded();
+    let (idle, _idle_mailbox) = Mailbox::<Worker>::unbounded();
+    busy.send(Inc).detach().await.unwrap();
+    busy.send(Inc).detach().await.unwrap();
+
+    let pool = ActorPool::new(LeastLoaded::default(), [busy.clone(), idle.clone()]);
+    pool.send(Inc).detach().await.unwrap();
+
+    assert_eq!(busy.len(), 2);
+    assert_eq!(idle.len(), 1);
+}
+
+#[tokio::test]
+async fn least_loaded_skips_disconnected_workers() {
+    let (stopped, mailbox) = Mailbox::<Worker>::unbounded();
+    drop(mailbox);
+    let running = xtra::spawn_tokio(Worker::new(1), Mailbox::unbounded());
+
+    let pool = ActorPool::new(LeastLoaded::default(), [stopped, running]);
+
+    for _ in 0..3 {
+        assert_eq!(pool.send(WhoAmI).await, Ok(1));
+    }
+}
+
+#[tokio::test]
+async fn random_reaches_every_worker() {
+    let pool = ActorPool::new(Random::with_seed(42), spawn_workers(3));
+
+    let mut ids = HashSet::new();
+    for _ in 0..100 {
+        ids.insert(pool.send(WhoAmI).await.unwrap());
+    }
+
+    assert_eq!(ids, HashSet::from([0, 1, 2]));
+}
+
+#[tokio::test]
+async fn consistent_hash_sends_same_key_to_same_worker() {
+    let pool = ActorPool::new(ConsistentHash, spawn_workers(4));
+
+    let mut ids = HashSet::new();
+    for user in 0..100 {
+        let id = pool.send(ByUser(user)).await.unwrap();
+
+        assert_eq!(pool.send(ByUser(user)).await, Ok(id));
+        ids.insert(id);
+    }
+
+    assert_eq!(ids.len(), 4, "keys should be spread over all workers");
+}
+
+#[tokio::test]
+async fn broadcast_reaches_every_worker() {
+    let pool = ActorPool::new(RoundRobin::default(), spawn_workers(3));
+
+    pool.broadcast(Inc).await.unwrap();
+
+    for worker in pool.workers() {
+        assert_eq!(worker.send(Seen).await, Ok(1));
+    }
+}
+
+#[tokio::test]
+async fn broadcast_to_disconnected_pool_fails() {
+    let (address, mailbox) = Mailbox::<Worker>::unbounded();
+    drop(mailbox);
+
+    let pool = ActorPool::new(RoundRobin::default(), [address]);
+
+    assert!(!pool.is_connected());
+    assert_eq!(pool.broadcast(Inc).await, Err(Error::Disconnected));
+}
+
+#[tokio::test]
+async fn message_channel_routes_through_pool() {
+    let pool = ActorPool::new(RoundRobin::default(), spawn_workers(2));
+    let channel = MessageChannel::<WhoAmI, usize>::from(pool.clone());
+
+    assert_eq!(channel.send(WhoAmI).await, Ok(0));
+    assert_eq!(pool.send(WhoAmI).await, Ok(1));
+    assert_eq!(channel.send(WhoAmI).await, Ok(0));
+
+    let weak = channel.downgrade();
+    assert!(weak.same_actor(&channel));
+    assert_eq!(weak.send(WhoAmI).await, Ok(1));
+}
+
+#[tokio::test]
+async fn join_resolves_once_all_workers_stopped() {
+    let pool = ActorPool::new(RoundRobin::default(), spawn_workers(2));
+    let weak = pool.downgrade();
+    assert!(weak.same_pool(&pool));
+
+    drop(pool);
+    weak.join().await;
+
+    assert!(!weak.is_connected());
+}
@@ -39,3 +39,13 @@ where
 {
     address1.as_either();
 }
+
+#[allow(dead_code)] // The mere existence of this function already ensures that addresses of local actors are `Send`, even though the actors are not.
+fn address_of_local_actor_is_send<A>(address: Address<xtra::local::Local<A>>)
+where
+    A: xtra::local::LocalActor,
+{
+    fn assert_send<T: Send>(_: T) {}
+
+    assert_send(address);
+}
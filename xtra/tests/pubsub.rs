@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use futures_util::FutureExt;
+use xtra::prelude::*;
+use xtra::pubsub::{Backpressure, Topic};
+
+#[derive(Clone)]
+struct Tick(u32);
+
+struct Get;
+
+#[derive(Default, xtra::Actor)]
+struct Log(Vec<u32>);
+
+#[derive(Default, xtra::Actor)]
+struct Counter(usize);
+
+/// Takes a while to handle every [`Tick`].
+#[derive(Default, xtra::Actor)]
+struct Slow(Vec<u32>);
+
+impl Handler<Tick> for Log {
+    type Return = ();
+
+    async fn handle(&mut self, tick: Tick, _ctx: &mut Context<Self>) {
+        self.0.push(tick.0);
+    }
+}
+
+impl Handler<Get> for Log {
+    type Return = Vec<u32>;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> Vec<u32> {
+        self.0.clone()
+    }
+}
+
+impl Handler<Tick> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Tick, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Get> for Counter {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+        self.0
+    }
+}
+
+impl Handler<Tick> for Slow {
+    type Return = ();
+
+    async fn handle(&mut self, tick: Tick, _ctx: &mut Context<Self>) {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        self.0.push(tick.0);
+    }
+}
+
+impl Handler<Get> for Slow {
+    type Return = Vec<u32>;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> Vec<u32> {
+        self.0.clone()
+    }
+}
+
+#[tokio::test]
+async fn publish_reaches_all_subscribers() {
+    let log = xtra::spawn_tokio(Log::default(), Mailbox::unbounded());
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    let topic = Topic::new();
+    topic.subscribe(log.clone());
+    topic.subscribe(counter.clone());
+
+    assert_eq!(topic.publish(Tick(1)).await, 2);
+    assert_eq!(topic.publish(Tick(2)).await, 2);
+
+    assert_eq!(log.send(Get).await.unwrap(), vec![1, 2]);
+    assert_eq!(counter.send(Get).await.unwrap(), 2);
+}
+
+#[tokio::test]
+async fn stopped_subscribers_are_removed() {
+    let log = xtra::spawn_tokio(Log::default(), Mailbox::unbounded());
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    let topic = Topic::new();
+    topic.subscribe(log.clone());
+    topic.subscribe(counter.clone());
+
+    let join = counter.join();
+    drop(counter);
+    join.await;
+
+    assert_eq!(topic.publish(Tick(1)).await, 1);
+    assert_eq!(topic.subscriber_count(), 1);
+    assert_eq!(log.send(Get).await.unwrap(), vec![1]);
+}
+
+#[tokio::test]
+async fn unsubscribed_actor_receives_nothing() {
+    let log = xtra::spawn_tokio(Log::default(), Mailbox::unbounded());
+
+    let topic = Topic::new();
+    let id = topic.subscribe(log.clone());
+    assert_eq!(topic.publish(Tick(1)).await, 1);
+
+    assert!(topic.unsubscribe(id));
+    assert!(!topic.unsubscribe(id));
+    assert_eq!(topic.publish(Tick(2)).await, 0);
+    assert_eq!(log.send(Get).await.unwrap(), vec![1]);
+}
+
+#[tokio::test]
+async fn wait_backpressure_waits_for_space() {
+    let (address, mailbox) = Mailbox::<Log>::bounded(1);
+
+    let topic = Topic::new();
+    topic.subscribe(address.clone());
+
+    assert_eq!(topic.publish(Tick(1)).await, 1);
+    assert!(topic.publish(Tick(2)).now_or_never().is_none());
+
+    xtra::spawn_tokio(Log::default(), (address.clone(), mailbox));
+
+    assert_eq!(topic.publish(Tick(3)).await, 1);
+    assert_eq!(address.send(Get).await.unwrap(), vec![1, 3]);
+    assert_eq!(topic.lag()[0].dropped, 0);
+}
+
+#[tokio::test]
+async fn skip_backpressure_reports_dropped_messages() {
+    let (address, _mailbox) = Mailbox::<Log>::bounded(1);
+    let (other, _other_mailbox) = Mailbox::<Counter>::unbounded();
+
+    let topic = Topic::with_backpressure(Backpressure::Skip);
+    let full = topic.subscribe(address.clone());
+    let unbounded = topic.subscribe(other.clone());
+
+    assert_eq!(topic.publish(Tick(1)).await, 2);
+    assert_eq!(topic.publish(Tick(2)).await, 1);
+    assert_eq!(topic.publish(Tick(3)).await, 1);
+
+    let lag = topic.lag();
+    assert_eq!(lag.len(), 2);
+    assert_eq!(lag[0].subscriber, full);
+    assert_eq!(lag[0].queued, 1);
+    assert_eq!(lag[0].dropped, 2);
+    assert_eq!(lag[1].subscriber, unbounded);
+    assert_eq!(lag[1].queued, 3);
+    assert_eq!(lag[1].dropped, 0);
+}
+
+#[tokio::test]
+async fn wait_for_backpressure_skips_after_timeout() {
+    let (address, _mailbox) = Mailbox::<Log>::bounded(1);
+
+    let topic = Topic::with_backpressure(Backpressure::WaitFor(Duration::from_millis(10)));
+    topic.subscribe(address.clone());
+
+    assert_eq!(topic.publish(Tick(1)).await, 1);
+    assert_eq!(topic.publish(Tick(2)).await, 0);
+
+    assert_eq!(topic.lag()[0].queued, 1);
+    assert_eq!(topic.lag()[0].dropped, 1);
+}
+
+#[tokio::test]
+async fn wait_for_backpressure_does_not_bound_slow_subscribers() {
+    let slow = xtra::spawn_tokio(Slow::default(), Mailbox::bounded(2));
+
+    let topic = Topic::with_backpressure(Backpressure::WaitFor(Duration::from_millis(10)));
+    topic.subscribe(slow.clone());
+
+    assert_eq!(topic.publish(Tick(1)).await, 1);
+    assert_eq!(topic.publish(Tick(2)).await, 1);
+
+    assert_eq!(slow.send(Get).await.unwrap(), vec![1, 2]);
+    assert_eq!(topic.lag()[0].dropped, 0);
+}
@@ -0,0 +1,138 @@
+use xtra::prelude::*;
+use xtra::registry::{AlreadyRegistered, Registry};
+
+#[derive(Default, xtra::Actor)]
+struct Counter(usize);
+
+#[derive(xtra::Actor)]
+struct Other;
+
+struct Inc;
+
+struct Get;
+
+struct Stop;
+
+impl Handler<Inc> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Get> for Counter {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+        self.0
+    }
+}
+
+impl Handler<Stop> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Stop, ctx: &mut Context<Self>) {
+        ctx.stop_self();
+    }
+}
+
+#[tokio::test]
+async fn lookup_returns_registered_actor() {
+    let registry = Registry::new();
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    registry.register("counter", &address).unwrap();
+
+    let found = registry.lookup::<Counter, _>("counter").unwrap();
+    found.send(Inc).await.unwrap();
+
+    assert!(found.same_actor(&address));
+    assert_eq!(address.send(Get).await, Ok(1));
+}
+
+#[tokio::test]
+async fn lookup_with_wrong_type_or_key_returns_none() {
+    let registry = Registry::new();
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let other = xtra::spawn_tokio(Other, Mailbox::unbounded());
+    registry.register("counter", &counter).unwrap();
+    registry.register("other", &other).unwrap();
+
+    assert!(registry.lookup::<Other, _>("counter").is_none());
+    assert!(registry.lookup::<Counter, _>("other").is_none());
+    assert!(registry.lookup::<Counter, _>("missing").is_none());
+}
+
+#[tokio::test]
+async fn lookup_channel_requires_exposed_message() {
+    let registry = Registry::new();
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    registry
+        .register("counter", &address)
+        .unwrap()
+        .expose::<Inc>()
+        .expose::<Get>();
+
+    let inc = registry.lookup_channel::<Inc, (), _>("counter").unwrap();
+    inc.send(Inc).await.unwrap();
+    let get = registry.lookup_channel::<Get, usize, _>("counter").unwrap();
+
+    assert_eq!(get.send(Get).await, Ok(1));
+    assert!(registry.lookup_channel::<Stop, (), _>("counter").is_none());
+}
+
+#[tokio::test]
+async fn cannot_register_twice_under_same_key() {
+    let registry = Registry::new();
+    let first = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let second = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    registry.register("counter", &first).unwrap();
+
+    assert_eq!(
+        registry.register("counter", &second).err(),
+        Some(AlreadyRegistered)
+    );
+}
+
+#[tokio::test]
+async fn registry_does_not_keep_actor_alive() {
+    let registry = Registry::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let actor = tokio::spawn(xtra::run(mailbox, Counter::default()));
+    registry.register("counter", &address).unwrap();
+
+    drop(address);
+    actor.await.unwrap();
+
+    assert!(!registry.contains("counter"));
+}
+
+#[tokio::test]
+async fn actor_is_deregistered_once_stopped() {
+    let registry = Registry::new();
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    registry.register("counter", &address).unwrap();
+
+    address.send(Stop).await.unwrap();
+    address.join().await;
+
+    assert!(registry.lookup::<Counter, _>("counter").is_none());
+
+    let replacement = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    assert!(registry.register("counter", &replacement).is_ok());
+}
+
+#[tokio::test]
+async fn custom_key_type() {
+    #[derive(Clone, Hash, PartialEq, Eq)]
+    struct Shard(u32);
+
+    let registry = Registry::<Shard>::default();
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    registry.register(Shard(1), &address).unwrap();
+
+    assert!(registry.lookup::<Counter, _>(&Shard(1)).is_some());
+    assert!(registry.lookup::<Counter, _>(&Shard(2)).is_none());
+    assert!(registry.deregister(&Shard(1)));
+    assert!(registry.lookup::<Counter, _>(&Shard(1)).is_none());
+}
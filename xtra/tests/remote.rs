@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+use tokio::io::AsyncWriteExt;
+use tokio_util::compat::{Compat, TokioAsyncReadCompatExt};
+use xtra::prelude::*;
+use xtra::remote::{RemoteAddress, RemoteMessage, Server, MAX_FRAME_LEN};
+use xtra::Error;
+
+#[derive(Default, xtra::Actor)]
+struct Counter(u32);
+
+#[derive(Serialize, Deserialize)]
+struct Add(u32);
+
+#[derive(Serialize, Deserialize)]
+struct Explode;
+
+#[derive(Serialize, Deserialize)]
+struct Sleep(Duration);
+
+#[derive(Serialize, Deserialize)]
+struct Upload(Vec<u8>);
+
+#[derive(Serialize, Deserialize)]
+struct Download(usize);
+
+#[derive(Serialize, Deserialize)]
+struct Unregistered;
+
+impl RemoteMessage for Add {}
+
+impl RemoteMessage for Explode {}
+
+impl RemoteMessage for Sleep {}
+
+impl RemoteMessage for Upload {}
+
+impl RemoteMessage for Download {}
+
+impl RemoteMessage for Unregistered {
+    fn name() -> &'static str {
+        "unregistered"
+    }
+}
+
+impl Handler<Add> for Counter {
+    type Return = u32;
+
+    async fn handle(&mut self, add: Add, _ctx: &mut Context<Self>) -> u32 {
+        self.0 += add.0;
+        self.0
+    }
+}
+
+impl Handler<Explode> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Explode, _ctx: &mut Context<Self>) {
+        panic!("Boom!")
+    }
+}
+
+impl Handler<Sleep> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, sleep: Sleep, _ctx: &mut Context<Self>) {
+        tokio::time::sleep(sleep.0).await;
+    }
+}
+
+impl Handler<Upload> for Counter {
+    type Return = usize;
+
+    async fn handle(&mut self, upload: Upload, _ctx: &mut Context<Self>) -> usize {
+        upload.0.len()
+    }
+}
+
+impl Handler<Download> for Counter {
+    type Return = Vec<u8>;
+
+    async fn handle(&mut self, download: Download, _ctx: &mut Context<Self>) -> Vec<u8> {
+        vec![0; download.0]
+    }
+}
+
+impl Handler<Unregistered> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Unregistered, _ctx: &mut Context<Self>) {}
+}
+
+type Io = Compat<tokio::io::DuplexStream>;
+
+/// Serve a [`Counter`] over an in-memory connection, returning the client side of it.
+fn serve(counter: Address<Counter>) -> Io {
+    let (client, server) = tokio::io::duplex(64);
+    let server_io = server.compat();
+
+    tokio::spawn(async move {
+        let server = Server::new(counter)
+            .handle::<Add>()
+            .handle::<Explode>()
+            .handle::<Sleep>()
+            .handle::<Upload>()
+            .handle::<Download>();
+
+        let _ = server.serve(server_io).await;
+    });
+
+    client.compat()
+}
+
+fn connect(io: Io) -> RemoteAddress<Counter> {
+    let (address, connection) = RemoteAddress::connect(io);
+    tokio::spawn(connection);
+
+    address
+}
+
+#[tokio::test]
+async fn return_values_are_sent_back() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter.clone()));
+
+    assert_eq!(remote.send(Add(2)).await, Ok(2));
+    assert_eq!(remote.send(Add(3)).priority(1).await, Ok(5));
+    assert_eq!(counter.send(Add(0)).await, Ok(5));
+}
+
+#[tokio::test]
+async fn detached_requests_resolve() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter));
+
+    let first = remote.send(Add(1)).detach().await.unwrap();
+    let second = remote.clone().send(Add(1)).detach().await.unwrap();
+
+    assert_eq!(first.await, Ok(1));
+    assert_eq!(second.await, Ok(2));
+}
+
+#[tokio::test]
+async fn handler_panics_are_sent_back() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter));
+
+    assert_eq!(remote.send(Explode).await, Err(Error::HandlerPanicked));
+}
+
+#[tokio::test]
+async fn timeouts_apply_to_remote_requests() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter));
+
+    let result = remote
+        .send(Sleep(Duration::from_secs(10)))
+        .timeout(Duration::from_millis(10))
+        .await;
+
+    assert_eq!(result, Err(Error::Timeout));
+}
+
+#[tokio::test]
+async fn connection_loss_disconnects_pending_requests() {
+    let (client, server) = tokio::io::duplex(64);
+    let remote = connect(client.compat());
+
+    let pending = remote.send(Add(1)).detach().await.unwrap();
+    drop(server);
+
+    assert_eq!(pending.await, Err(Error::Disconnected));
+    assert!(!remote.is_connected());
+    assert_eq!(remote.send(Add(1)).await, Err(Error::Disconnected));
+}
+
+#[tokio::test]
+async fn unregistered_messages_close_the_connection() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter));
+
+    assert_eq!(remote.send(Unregistered).await, Err(Error::Disconnected));
+    assert_eq!(remote.send(Add(1)).await, Err(Error::Disconnected));
+}
+
+#[tokio::test]
+async fn connection_finishes_once_addresses_are_dropped() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let (remote, connection) = RemoteAddress::<Counter>::connect(serve(counter));
+    let connection = tokio::spawn(connection);
+
+    let pending = remote.send(Add(1)).detach().await.unwrap();
+    drop(remote);
+
+    assert_eq!(pending.await, Ok(1));
+    assert!(connection.await.unwrap().is_ok());
+}
+
+#[tokio::test]
+async fn oversized_frames_close_the_connection() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let (mut client, server) = tokio::io::duplex(64);
+    let serve = tokio::spawn(async move { Server::new(counter).serve(server.compat()).await });
+
+    let len = u32::try_from(MAX_FRAME_LEN + 1).unwrap();
+    client.write_all(&len.to_le_bytes()).await.unwrap();
+
+    let error = serve.await.unwrap().unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[tokio::test]
+async fn oversized_messages_fail_without_closing_the_connection() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter));
+
+    let result = remote.send(Upload(vec![0; MAX_FRAME_LEN])).await;
+
+    assert_eq!(result, Err(Error::Serialization));
+    assert_eq!(remote.send(Upload(vec![0; 16])).await, Ok(16));
+}
+
+#[tokio::test]
+async fn oversized_return_values_fail_without_closing_the_connection() {
+    let counter = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+    let remote = connect(serve(counter));
+
+    let result = remote.send(Download(MAX_FRAME_LEN)).await;
+
+    assert_eq!(result, Err(Error::Serialization));
+    assert_eq!(remote.send(Download(16)).await, Ok(vec![0; 16]));
+}
@@ -0,0 +1,148 @@
+use std::time::Duration;
+
+use xtra::prelude::*;
+use xtra::resilience::{Backoff, CircuitBreaker, CircuitState, Resilient, RetryPolicy};
+use xtra::{Error, PanicAction};
+
+#[derive(Default)]
+struct Flaky {
+    calls: usize,
+}
+
+impl Actor for Flaky {
+    type Stop = ();
+
+    async fn stopped(self) {}
+
+    async fn on_panic(&mut self, _: Box<dyn std::any::Any + Send>) -> PanicAction {
+        PanicAction::Continue
+    }
+}
+
+/// Sleeps for the given duration during the first call only.
+#[derive(Clone)]
+struct SlowOnce(Duration);
+
+#[derive(Clone)]
+struct Explode;
+
+#[derive(Clone)]
+struct Calls;
+
+impl Handler<SlowOnce> for Flaky {
+    type Return = usize;
+
+    async fn handle(&mut self, slow: SlowOnce, _ctx: &mut Context<Self>) -> usize {
+        self.calls += 1;
+
+        if self.calls == 1 {
+            tokio::time::sleep(slow.0).await;
+        }
+
+        self.calls
+    }
+}
+
+impl Handler<Explode> for Flaky {
+    type Return = ();
+
+    async fn handle(&mut self, _: Explode, _ctx: &mut Context<Self>) {
+        self.calls += 1;
+        panic!("Boom!")
+    }
+}
+
+impl Handler<Calls> for Flaky {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Calls, _ctx: &mut Context<Self>) -> usize {
+        self.calls
+    }
+}
+
+#[tokio::test]
+async fn timed_out_attempts_are_retried() {
+    let address = xtra::spawn_tokio(Flaky::default(), Mailbox::unbounded());
+    let flaky = Resilient::new(address).retry(
+        RetryPolicy::new(3)
+            .attempt_timeout(Duration::from_millis(100))
+            .backoff(Backoff::Fixed(Duration::from_millis(10))),
+    );
+
+    assert_eq!(
+        flaky.send(SlowOnce(Duration::from_millis(150))).await,
+        Ok(2)
+    );
+}
+
+#[tokio::test]
+async fn retries_give_up_after_max_attempts() {
+    let address = xtra::spawn_tokio(Flaky::default(), Mailbox::unbounded());
+    let flaky = Resilient::new(address.clone())
+        .retry(RetryPolicy::new(3).retry_on(|error| *error == Error::HandlerPanicked));
+
+    assert_eq!(flaky.send(Explode).await, Err(Error::HandlerPanicked));
+    assert_eq!(address.send(Calls).await, Ok(3));
+}
+
+#[tokio::test]
+async fn disconnected_actor_is_not_retried() {
+    let (address, mailbox) = Mailbox::<Flaky>::unbounded();
+    drop(mailbox);
+
+    let flaky = Resilient::new(address)
+        .retry(RetryPolicy::new(3).backoff(Backoff::Fixed(Duration::from_secs(10))));
+
+    let result = tokio::time::timeout(Duration::from_secs(1), flaky.send(Calls)).await;
+    assert_eq!(result, Ok(Err(Error::Disconnected)));
+}
+
+#[tokio::test]
+async fn circuit_opens_after_failures_and_closes_after_trial() {
+    let address = xtra::spawn_tokio(Flaky::default(), Mailbox::unbounded());
+    let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+    let flaky = Resilient::new(address.clone()).circuit_breaker(breaker.clone());
+
+    assert_eq!(flaky.send(Explode).await, Err(Error::HandlerPanicked));
+    assert_eq!(breaker.state(), CircuitState::Closed);
+    assert_eq!(flaky.send(Explode).await, Err(Error::HandlerPanicked));
+    assert_eq!(breaker.state(), CircuitState::Open);
+
+    assert_eq!(flaky.send(Calls).await, Err(Error::CircuitOpen));
+    assert_eq!(
+        address.send(Calls).await,
+        Ok(2),
+        "message should not be sent"
+    );
+
+    tokio::time::sleep(Duration::from_millis(60)).await;
+    assert_eq!(breaker.state(), CircuitState::HalfOpen);
+
+    assert_eq!(flaky.send(Calls).await, Ok(2));
+    assert_eq!(breaker.state(), CircuitState::Closed);
+}
+
+#[tokio::test]
+async fn failed_trial_reopens_circuit() {
+    let address = xtra::spawn_tokio(Flaky::default(), Mailbox::unbounded());
+    let breaker = CircuitBreaker::new(1, Duration::from_millis(50));
+    let flaky = Resilient::new(address).circuit_breaker(breaker.clone());
+
+    assert_eq!(flaky.send(Explode).await, Err(Error::HandlerPanicked));
+    tokio::time::sleep(Duration::from_millis(60)).await;
+
+    assert_eq!(flaky.send(Explode).await, Err(Error::HandlerPanicked));
+    assert_eq!(breaker.state(), CircuitState::Open);
+}
+
+#[tokio::test]
+async fn circuit_breaker_is_shared_with_message_channels() {
+    let address = xtra::spawn_tokio(Flaky::default(), Mailbox::unbounded());
+    let breaker = CircuitBreaker::new(1, Duration::from_secs(10));
+    let flaky = Resilient::new(address.clone()).circuit_breaker(breaker.clone());
+    let channel =
+        Resilient::new(MessageChannel::<Calls, usize>::new(address)).circuit_breaker(breaker);
+
+    assert_eq!(flaky.send(Explode).await, Err(Error::HandlerPanicked));
+    assert_eq!(channel.send(Calls).await, Err(Error::CircuitOpen));
+}
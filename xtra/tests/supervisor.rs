@@ -0,0 +1,242 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use xtra::prelude::*;
+use xtra::supervisor::{ChildSpec, Strategy, Supervisor, SupervisorStop};
+use xtra::PanicAction;
+
+#[derive(Default, xtra::Actor)]
+struct Counter(usize);
+
+struct Inc;
+
+struct Get;
+
+struct Crash;
+
+impl Handler<Inc> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Get> for Counter {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+        self.0
+    }
+}
+
+impl Handler<Crash> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Crash, ctx: &mut Context<Self>) {
+        ctx.stop_self();
+    }
+}
+
+#[tokio::test]
+async fn one_for_one_restarts_on_same_address() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let supervisor =
+        Supervisor::new(Strategy::OneForOne).child(ChildSpec::new(mailbox, Counter::default));
+    tokio::spawn(supervisor.run());
+
+    address.send(Inc).await.unwrap();
+    address.send(Crash).await.unwrap();
+
+    assert!(address.is_connected());
+    assert_eq!(
+        address.send(Get).await,
+        Ok(0),
+        "actor should have been restarted"
+    );
+}
+
+#[tokio::test]
+async fn one_for_all_restarts_siblings() {
+    let (failing, failing_mailbox) = Mailbox::unbounded();
+    let (sibling, sibling_mailbox) = Mailbox::unbounded();
+    let supervisor = Supervisor::new(Strategy::OneForAll)
+        .child(ChildSpec::new(failing_mailbox, Counter::default))
+        .child(ChildSpec::new(sibling_mailbox, Counter::default));
+    tokio::spawn(supervisor.run());
+
+    sibling.send(Inc).await.unwrap();
+    failing.send(Crash).await.unwrap();
+    failing.send(Get).await.unwrap(); // Handled by the restarted instance
+
+    assert_eq!(
+        sibling.send(Get).await,
+        Ok(0),
+        "sibling should have been restarted"
+    );
+}
+
+#[tokio::test]
+async fn one_for_all_does_not_restart_temporary_siblings() {
+    let starts = Arc::new(AtomicUsize::new(0));
+    let sibling_starts = starts.clone();
+
+    let (failing, failing_mailbox) = Mailbox::unbounded();
+    let (_sibling, sibling_mailbox) = Mailbox::unbounded();
+    let supervisor = Supervisor::new(Strategy::OneForAll)
+        .child(ChildSpec::new(failing_mailbox, Counter::default))
+        .child(
+            ChildSpec::new(sibling_mailbox, move || {
+                sibling_starts.fetch_add(1, Ordering::SeqCst);
+                Counter::default()
+            })
+            .temporary(),
+        );
+    tokio::spawn(supervisor.run());
+
+    failing.send(Crash).await.unwrap();
+    failing.send(Get).await.unwrap(); // Handled by the restarted instance
+
+    assert_eq!(
+        starts.load(Ordering::SeqCst),
+        1,
+        "temporary sibling should not have been restarted"
+    );
+}
+
+#[tokio::test]
+async fn stopping_a_sibling_leaves_other_actors_on_its_mailbox_running() {
+    let (failing, failing_mailbox) = Mailbox::unbounded();
+    let (_sibling, sibling_mailbox) = Mailbox::unbounded();
+    let bystander = tokio::spawn(xtra::run(sibling_mailbox.clone(), Counter::default()));
+    let supervisor = Supervisor::new(Strategy::OneForAll)
+        .child(ChildSpec::new(failing_mailbox, Counter::default))
+        .child(ChildSpec::new(sibling_mailbox, Counter::default));
+    tokio::spawn(supervisor.run());
+
+    failing.send(Crash).await.unwrap();
+    failing.send(Get).await.unwrap(); // Handled by the restarted instance
+    tokio::task::yield_now().await;
+
+    assert!(
+        !bystander.is_finished(),
+        "actor sharing the mailbox of the sibling should not have been stopped"
+    );
+}
+
+#[tokio::test]
+async fn rest_for_one_leaves_earlier_children_running() {
+    let (earlier, earlier_mailbox) = Mailbox::unbounded();
+    let (failing, failing_mailbox) = Mailbox::unbounded();
+    let (later, later_mailbox) = Mailbox::unbounded();
+    let supervisor = Supervisor::new(Strategy::RestForOne)
+        .child(ChildSpec::new(earlier_mailbox, Counter::default))
+        .child(ChildSpec::new(failing_mailbox, Counter::default))
+        .child(ChildSpec::new(later_mailbox, Counter::default));
+    tokio::spawn(supervisor.run());
+
+    earlier.send(Inc).await.unwrap();
+    later.send(Inc).await.unwrap();
+    failing.send(Crash).await.unwrap();
+    failing.send(Get).await.unwrap(); // Handled by the restarted instance
+
+    assert_eq!(
+        later.send(Get).await,
+        Ok(0),
+        "later child should have been restarted"
+    );
+    assert_eq!(
+        earlier.send(Get).await,
+        Ok(1),
+        "earlier child should be untouched"
+    );
+}
+
+#[tokio::test]
+async fn gives_up_when_restart_intensity_is_exceeded() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let supervisor = Supervisor::new(Strategy::OneForOne)
+        .intensity(2, Duration::from_secs(60))
+        .child(ChildSpec::new(mailbox, Counter::default));
+    let supervisor = tokio::spawn(supervisor.run());
+
+    for _ in 0..3 {
+        let _ = address.send(Crash).await;
+    }
+
+    assert_eq!(
+        supervisor.await.unwrap(),
+        SupervisorStop::RestartIntensityExceeded
+    );
+    assert!(!address.is_connected());
+}
+
+#[tokio::test]
+async fn temporary_child_is_not_restarted() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let supervisor = Supervisor::new(Strategy::OneForOne)
+        .child(ChildSpec::new(mailbox, Counter::default).temporary());
+    let supervisor = tokio::spawn(supervisor.run());
+
+    address.send(Crash).await.unwrap();
+
+    assert_eq!(supervisor.await.unwrap(), SupervisorStop::Finished);
+    assert!(!address.is_connected());
+}
+
+struct Panic;
+
+#[derive(Default)]
+struct Fragile(usize);
+
+impl Actor for Fragile {
+    type Stop = ();
+
+    async fn stopped(self) {}
+
+    async fn on_panic(&mut self, _: Box<dyn std::any::Any + Send>) -> PanicAction {
+        PanicAction::Restart
+    }
+}
+
+impl Handler<Inc> for Fragile {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Get> for Fragile {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+        self.0
+    }
+}
+
+impl Handler<Panic> for Fragile {
+    type Return = ();
+
+    async fn handle(&mut self, _: Panic, _ctx: &mut Context<Self>) {
+        panic!("handler panicked on purpose")
+    }
+}
+
+#[tokio::test]
+async fn panic_restart_overrides_restart_policy() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let supervisor = Supervisor::new(Strategy::OneForOne)
+        .child(ChildSpec::new(mailbox, Fragile::default).temporary());
+    tokio::spawn(supervisor.run());
+
+    address.send(Inc).await.unwrap();
+    assert_eq!(address.send(Panic).await, Err(xtra::Error::HandlerPanicked));
+
+    assert_eq!(
+        address.send(Get).await,
+        Ok(0),
+        "actor should have been restarted"
+    );
+}
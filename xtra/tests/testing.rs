@@ -0,0 +1,202 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use xtra::prelude::*;
+use xtra::testing::{Executor, QueuedMessage, TestMailbox};
+
+#[derive(Default)]
+struct Recorder {
+    handled: Vec<&'static str>,
+    refuse_start: bool,
+}
+
+impl Actor for Recorder {
+    type Stop = &'static str;
+
+    async fn started(&mut self, _: &Mailbox<Self>) -> Result<(), Self::Stop> {
+        if self.refuse_start {
+            return Err("refused");
+        }
+
+        Ok(())
+    }
+
+    async fn stopped(self) -> Self::Stop {
+        "stopped"
+    }
+}
+
+struct Record(&'static str);
+
+#[derive(Clone)]
+struct Ping;
+
+struct StopSelf;
+
+impl Handler<Record> for Recorder {
+    type Return = ();
+
+    async fn handle(&mut self, record: Record, _ctx: &mut Context<Self>) {
+        self.handled.push(record.0);
+    }
+}
+
+impl Handler<Ping> for Recorder {
+    type Return = ();
+
+    async fn handle(&mut self, _: Ping, _ctx: &mut Context<Self>) {
+        self.handled.push("ping");
+    }
+}
+
+impl Handler<StopSelf> for Recorder {
+    type Return = ();
+
+    async fn handle(&mut self, _: StopSelf, ctx: &mut Context<Self>) {
+        ctx.stop_self();
+    }
+}
+
+#[test]
+fn messages_are_handled_one_step_at_a_time() {
+    let executor = Executor::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let mut recorder = TestMailbox::new(&executor, mailbox, Recorder::default());
+
+    executor
+        .block_on(address.send(Record("low")).detach())
+        .unwrap();
+    executor
+        .block_on(address.send(Record("high")).priority(2).detach())
+        .unwrap();
+
+    assert!(recorder.step());
+    assert_eq!(recorder.actor().unwrap().handled, ["high"]);
+
+    assert!(recorder.step());
+    assert_eq!(recorder.actor().unwrap().handled, ["high", "low"]);
+
+    assert!(!recorder.step());
+}
+
+#[test]
+fn queued_messages_are_described_in_handling_order() {
+    let executor = Executor::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let recorder = TestMailbox::new(&executor, mailbox, Recorder::default());
+
+    executor
+        .block_on(address.send(Record("a")).detach())
+        .unwrap();
+    executor
+        .block_on(address.broadcast(Ping).priority(1))
+        .unwrap();
+
+    let queued = recorder.queued();
+
+    assert_eq!(queued.len(), 2);
+    assert_eq!(
+        queued[0],
+        QueuedMessage {
+            message: std::any::type_name::<Ping>(),
+            priority: 1,
+            broadcast: true,
+        }
+    );
+    assert_eq!(
+        queued[1],
+        QueuedMessage {
+            message: std::any::type_name::<Record>(),
+            priority: 0,
+            broadcast: false,
+        }
+    );
+}
+
+#[test]
+fn run_until_idle_handles_all_queued_messages() {
+    let executor = Executor::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let mut recorder = TestMailbox::new(&executor, mailbox, Recorder::default());
+
+    for _ in 0..3 {
+        executor.block_on(address.send(Ping).detach()).unwrap();
+    }
+
+    assert_eq!(recorder.run_until_idle(), 3);
+    assert!(recorder.queued().is_empty());
+    assert!(!recorder.is_stopped());
+}
+
+#[test]
+fn dropping_all_addresses_stops_the_actor() {
+    let executor = Executor::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let mut recorder = TestMailbox::new(&executor, mailbox, Recorder::default());
+
+    drop(address);
+
+    assert!(recorder.step());
+    assert!(recorder.is_stopped());
+    assert!(recorder.actor().is_none());
+    assert_eq!(recorder.stop_reason(), Some(&"stopped"));
+    assert!(!recorder.step());
+}
+
+#[test]
+fn stop_self_stops_the_actor() {
+    let executor = Executor::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let mut recorder = TestMailbox::new(&executor, mailbox, Recorder::default());
+
+    executor.block_on(address.send(StopSelf).detach()).unwrap();
+    executor.block_on(address.send(Ping).detach()).unwrap();
+
+    assert_eq!(recorder.run_until_idle(), 1);
+    assert_eq!(recorder.stop_reason(), Some(&"stopped"));
+}
+
+#[test]
+fn started_error_is_the_stop_reason() {
+    let executor = Executor::new();
+    let (_address, mailbox) = Mailbox::unbounded();
+    let recorder = Recorder {
+        refuse_start: true,
+        ..Recorder::default()
+    };
+    let mut recorder = TestMailbox::new(&executor, mailbox, recorder);
+
+    assert!(recorder.is_stopped());
+    assert_eq!(recorder.stop_reason(), Some(&"refused"));
+    assert!(!recorder.step());
+}
+
+#[test]
+fn spawned_tasks_run_alongside_block_on() {
+    let executor = Executor::new();
+    let (address, mailbox) = Mailbox::unbounded();
+    let mut recorder = TestMailbox::new(&executor, mailbox, Recorder::default());
+
+    let sent = Rc::new(Cell::new(false));
+    executor.spawn({
+        let sent = sent.clone();
+        async move {
+            address.send(Ping).detach().await.unwrap();
+            sent.set(true);
+        }
+    });
+
+    assert!(executor.run_until_stalled());
+    assert!(sent.get());
+    assert!(!executor.run_until_stalled());
+    assert!(recorder.step());
+}
+
+#[test]
+#[should_panic(expected = "Executor stalled")]
+fn block_on_panics_when_stalled() {
+    let executor = Executor::new();
+    let (address, _mailbox) = Mailbox::<Recorder>::unbounded();
+
+    let _ = executor.block_on(address.send(Ping));
+}
@@ -0,0 +1,92 @@
+use std::time::{Duration, Instant};
+
+use xtra::prelude::*;
+use xtra::timer::TokioTimer;
+
+#[derive(Default, xtra::Actor)]
+struct Counter(usize);
+
+#[derive(Clone)]
+struct Inc;
+
+struct Get;
+
+impl Handler<Inc> for Counter {
+    type Return = ();
+
+    async fn handle(&mut self, _: Inc, _ctx: &mut Context<Self>) {
+        self.0 += 1;
+    }
+}
+
+impl Handler<Get> for Counter {
+    type Return = usize;
+
+    async fn handle(&mut self, _: Get, _ctx: &mut Context<Self>) -> usize {
+        self.0
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn send_after_delivers_once_delay_elapsed() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    address.send_after(Inc, Duration::from_millis(50), TokioTimer);
+
+    tokio::time::advance(Duration::from_millis(49)).await;
+    assert_eq!(address.send(Get).await, Ok(0));
+
+    // The paused clock only advances past the delay once the message has been handled.
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    assert_eq!(address.send(Get).await, Ok(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn send_at_in_the_past_delivers_immediately() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    address.send_at(Inc, Instant::now() - Duration::from_secs(1), TokioTimer);
+
+    tokio::time::sleep(Duration::from_millis(1)).await;
+    assert_eq!(address.send(Get).await, Ok(1));
+}
+
+#[tokio::test(start_paused = true)]
+async fn cancelled_timer_does_not_deliver() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    let handle = address.send_after(Inc, Duration::from_millis(50), TokioTimer);
+    handle.cancel();
+
+    assert!(handle.is_cancelled());
+    tokio::time::advance(Duration::from_millis(100)).await;
+    assert_eq!(address.send(Get).await, Ok(0));
+}
+
+#[tokio::test(start_paused = true)]
+async fn send_interval_delivers_until_cancelled() {
+    let address = xtra::spawn_tokio(Counter::default(), Mailbox::unbounded());
+
+    let handle = address.send_interval(Inc, Duration::from_millis(20), TokioTimer);
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    handle.cancel();
+
+    assert_eq!(address.send(Get).await, Ok(2));
+
+    tokio::time::advance(Duration::from_millis(100)).await;
+    assert_eq!(address.send(Get).await, Ok(2));
+}
+
+#[tokio::test(start_paused = true)]
+async fn scheduled_messages_do_not_keep_actor_alive() {
+    let (address, mailbox) = Mailbox::unbounded();
+    let actor = tokio::spawn(xtra::run(mailbox, Counter::default()));
+
+    address.send_interval(Inc, Duration::from_millis(20), TokioTimer);
+    drop(address);
+
+    tokio::time::timeout(Duration::from_secs(1), actor)
+        .await
+        .expect("actor should stop once all addresses are dropped")
+        .unwrap();
+}